@@ -0,0 +1,172 @@
+use crate::codec;
+use crate::error::SledoViewError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fmt::Write;
+
+/// Parse a byte literal from a command argument.
+///
+/// - `0x0001ff`: hex digits, two per byte
+/// - `b64:AAE=`: standard base64
+/// - `u64:42`, `i64le:-1`, `uuid:<uuid>`, ...: typed values, see
+///   `codec::parse_typed_literal`
+/// - anything else is text, where `\xNN`, `\n`, `\t`, `\r`, `\0` and `\\`
+///   are decoded; other backslashes are kept as typed
+pub fn parse_literal(literal: &str) -> Result<Vec<u8>, SledoViewError> {
+    let invalid = |message: &str| SledoViewError::InvalidLiteral {
+        literal: literal.to_string(),
+        message: message.to_string(),
+    };
+
+    if is_prefixed_literal(literal) {
+        if let Some(hex) = literal.strip_prefix("0x") {
+            return decode_hex(hex).ok_or_else(|| invalid("odd number of hex digits"));
+        }
+    }
+
+    if let Some(typed) = codec::parse_typed_literal(literal) {
+        return typed.map_err(|message| invalid(&message));
+    }
+
+    if let Some(encoded) = literal.strip_prefix("b64:") {
+        return BASE64
+            .decode(encoded)
+            .map_err(|e| invalid(&format!("invalid base64 ({})", e)));
+    }
+
+    let mut bytes = Vec::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                match decode_hex(&digits) {
+                    Some(byte) if digits.len() == 2 => bytes.extend(byte),
+                    _ => return Err(invalid("\\x must be followed by two hex digits")),
+                }
+            }
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.push(b'\\');
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => bytes.push(b'\\'),
+        }
+    }
+    Ok(bytes)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Escape raw bytes for display.
+///
+/// Printable UTF-8 text is shown as-is. Bytes that are not valid UTF-8 are
+/// shown as `\xNN`, control characters as `\n`, `\t`, `\r` or `\xNN`, and a
+/// backslash as `\\`, so distinct byte strings never display the same.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for ch in chunk.valid().chars() {
+            match ch {
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\t' => escaped.push_str("\\t"),
+                '\r' => escaped.push_str("\\r"),
+                c if c.is_control() && (c as u32) < 0x100 => {
+                    let _ = write!(escaped, "\\x{:02x}", c as u32);
+                }
+                c => escaped.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02x}", byte);
+        }
+    }
+
+    // Text that would read back as a hex or base64 literal gets its first
+    // character escaped, so `parse_literal` returns the same bytes
+    if is_prefixed_literal(&escaped) {
+        let first = escaped.remove(0);
+        escaped.insert_str(0, &format!("\\x{:02x}", first as u32));
+    }
+    escaped
+}
+
+/// Whether text is a hex, base64 or typed literal rather than plain text
+pub(crate) fn is_prefixed_literal(text: &str) -> bool {
+    match text.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()),
+        None => text.starts_with("b64:") || codec::parse_typed_literal(text).is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(b"user_001"), "user_001");
+        assert_eq!(escape("café".as_bytes()), "café");
+        assert_eq!(escape(&[0, 0, 0, 42]), "\\x00\\x00\\x00*");
+        assert_eq!(escape(b"a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+        assert_eq!(escape(&[b'k', 0xff, 0xfe]), "k\\xff\\xfe");
+        assert_eq!(escape(b"\x7f"), "\\x7f");
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(parse_literal("user_001").unwrap(), b"user_001");
+        assert_eq!(parse_literal("0x0001ff").unwrap(), [0x00, 0x01, 0xff]);
+        assert_eq!(parse_literal("0xABcd").unwrap(), [0xab, 0xcd]);
+        assert_eq!(parse_literal("b64:AAE=").unwrap(), [0x00, 0x01]);
+        assert_eq!(parse_literal("u32:42").unwrap(), [0, 0, 0, 42]);
+        assert_eq!(parse_literal("\\x00\\xff").unwrap(), [0x00, 0xff]);
+        assert_eq!(parse_literal("a\\tb\\\\c").unwrap(), b"a\tb\\c");
+        assert_eq!(parse_literal("café").unwrap(), "café".as_bytes());
+
+        // Not hex, so taken as text; unknown escapes are kept
+        assert_eq!(parse_literal("0xford").unwrap(), b"0xford");
+        assert_eq!(parse_literal("user_\\d").unwrap(), b"user_\\d");
+
+        assert!(parse_literal("0xabc").is_err());
+        assert!(parse_literal("b64:!!").is_err());
+        assert!(parse_literal("u32:-1").is_err());
+        assert!(parse_literal("\\x4").is_err());
+        assert!(parse_literal("\\xzz").is_err());
+    }
+
+    #[test]
+    fn test_escape_round_trips() {
+        for bytes in [
+            &b"plain"[..],
+            &[0, 1, 0xff],
+            b"tab\tand\\slash",
+            "ünï".as_bytes(),
+            b"0xbeef",
+            b"b64:AAE=",
+            b"u64:42",
+        ] {
+            assert_eq!(parse_literal(&escape(bytes)).unwrap(), bytes);
+        }
+    }
+}
@@ -1,4 +1,11 @@
-use clap::Parser;
+use crate::commands::{self, Command, Condition, CopyMode, CopySource, KeySelection, TreeAction};
+use crate::decode::Decoder;
+use crate::filter::{self, Filter, ValueKind};
+use crate::hexdump::{self, ByteRange};
+use crate::output::OutputFormat;
+use crate::page::{Page, Sort};
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -6,10 +13,791 @@ use std::path::PathBuf;
     name = "sledoview",
     about = "A CLI tool for viewing and managing SLED databases",
     version,
-    author = "Your Name <your.email@example.com>"
+    author = "Your Name <your.email@example.com>",
+    after_help = "Without a subcommand an interactive session is started. When \
+                  standard input is not a terminal, commands are read from it one \
+                  per line instead.\n\n\
+                  Exit codes: 0 success, 1 command failed, 2 invalid usage, \
+                  3 key not found, 4 database unavailable, 5 conflict"
 )]
 pub struct Args {
     /// Path to the SLED database file
     #[arg(help = "Path to the SLED database file")]
     pub database_path: PathBuf,
+
+    /// Existing tree to run a one-shot command against (instead of the default tree)
+    #[arg(long, global = true, value_name = "TREE")]
+    pub tree: Option<String>,
+
+    /// Refuse every command that writes: `set`, `cas`, `delete`, `cp`, `mv`,
+    /// `rename`, `tree ...`, `select --create`, `begin` and `commit`.
+    /// Sessions and one-shot commands that only read are always run this way.
+    /// SLED may still rewrite its files when opening the database; see --snapshot
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// Open a temporary copy of the database, read-only, so that not even
+    /// SLED writes to its files. Copying takes the time and disk space of the
+    /// whole database
+    #[arg(long, global = true, conflicts_with = "write")]
+    pub snapshot: bool,
+
+    /// Open interactive, piped and script sessions writable; they are
+    /// read-only unless this is given
+    #[arg(long, global = true, conflicts_with = "read_only")]
+    pub write: bool,
+
+    /// TOML or YAML file mapping tree name globs to key and value codecs
+    #[arg(long, global = true, value_name = "FILE")]
+    pub profile: Option<PathBuf>,
+
+    /// Output format for command results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Run the commands in a script file and exit
+    #[arg(long, value_name = "FILE")]
+    pub script: Option<PathBuf>,
+
+    /// Keep running a script (or piped input) after a command fails
+    #[arg(long)]
+    pub continue_on_error: bool,
+
+    /// Run a single command and exit instead of starting the interactive session
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+/// `--offset`, `--limit`, `--sort` and `--reverse` for commands that list
+/// keys or trees
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct PageArgs {
+    /// Skip this many results
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+    /// Show at most this many results
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub limit: Option<usize>,
+    /// Order by key bytes as stored, key as displayed (numbers by value),
+    /// value size or value bytes
+    #[arg(long, value_enum, default_value_t = Sort::Bytes)]
+    pub sort: Sort,
+    /// List in reverse order
+    #[arg(long)]
+    pub reverse: bool,
+}
+
+impl From<PageArgs> for Page {
+    fn from(args: PageArgs) -> Self {
+        Page {
+            offset: args.offset,
+            limit: args.limit,
+            sort: args.sort,
+            reverse: args.reverse,
+        }
+    }
+}
+
+/// Conditions on entries for `list` and `search`
+#[derive(clap::Args, Debug, Clone)]
+pub struct FilterArgs {
+    /// Only keys that also match this glob
+    #[arg(long, value_name = "GLOB")]
+    pub key: Option<String>,
+    /// Only values that also match this regex
+    #[arg(long, value_name = "REGEX")]
+    pub value: Option<String>,
+    /// Match all patterns ignoring case
+    #[arg(short = 'i', long)]
+    pub ignore_case: bool,
+    /// Only values of at least this size (bytes, or with a k, m or g suffix)
+    #[arg(long, value_name = "SIZE", value_parser = filter::parse_size)]
+    pub min_size: Option<usize>,
+    /// Only values of at most this size (bytes, or with a k, m or g suffix)
+    #[arg(long, value_name = "SIZE", value_parser = filter::parse_size)]
+    pub max_size: Option<usize>,
+    /// Only binary values, including MessagePack and CBOR
+    #[arg(long, conflicts_with = "text")]
+    pub binary: bool,
+    /// Only text values, including JSON
+    #[arg(long)]
+    pub text: bool,
+    /// Only empty values
+    #[arg(long)]
+    pub empty: bool,
+}
+
+impl From<&FilterArgs> for Filter {
+    fn from(args: &FilterArgs) -> Self {
+        let kind = match (args.text, args.binary) {
+            (true, _) => Some(ValueKind::Text),
+            (_, true) => Some(ValueKind::Binary),
+            _ => None,
+        };
+        Filter {
+            key: args.key.clone(),
+            value: args.value.clone(),
+            ignore_case: args.ignore_case,
+            min_size: args.min_size,
+            max_size: args.max_size,
+            kind,
+            empty: args.empty,
+        }
+    }
+}
+
+/// `--all-trees` and `--trees` for commands that can read every tree
+#[derive(clap::Args, Debug, Clone)]
+pub struct TreesArgs {
+    /// Read every tree, including the default tree, instead of the current one
+    #[arg(long, conflicts_with = "trees")]
+    pub all_trees: bool,
+    /// Read every tree whose name matches this glob instead of the current one
+    #[arg(long, value_name = "GLOB")]
+    pub trees: Option<String>,
+}
+
+impl TreesArgs {
+    /// The glob of the trees to read, if not the current tree
+    fn glob(&self) -> Option<String> {
+        if self.all_trees {
+            Some("*".to_string())
+        } else {
+            self.trees.clone()
+        }
+    }
+}
+
+/// Arguments of `cp`, `mv` and `rename`
+#[derive(clap::Args, Debug, Clone)]
+pub struct CopyArgs {
+    /// The key to copy; with --pattern or --regex, the target of the keys
+    #[arg(value_name = "KEY")]
+    pub key: String,
+    /// The target key, <tree>:<key> for another tree that exists
+    #[arg(
+        required_unless_present_any = ["pattern", "regex"],
+        conflicts_with_all = ["pattern", "regex"]
+    )]
+    pub target: Option<String>,
+    /// Copy every key matching a glob; $1, $2... in the target are what
+    /// its wildcards matched
+    #[arg(long, value_name = "GLOB", conflicts_with = "regex")]
+    pub pattern: Option<String>,
+    /// Copy every key matching a regex; $1 or ${name} in the target are
+    /// what its groups captured
+    #[arg(long, value_name = "REGEX")]
+    pub regex: Option<String>,
+    /// Overwrite target keys that exist
+    #[arg(long, short)]
+    pub force: bool,
+}
+
+impl CopyArgs {
+    fn to_command(&self, mode: CopyMode) -> Command {
+        let (source, target) = match (&self.pattern, &self.regex, &self.target) {
+            (Some(pattern), _, _) => (CopySource::Glob(pattern.clone()), &self.key),
+            (_, Some(regex), _) => (CopySource::Regex(regex.clone()), &self.key),
+            (_, _, Some(target)) => (CopySource::Key(self.key.clone()), target),
+            _ => unreachable!("clap requires a target, a pattern or a regex"),
+        };
+        Command::Copy {
+            mode,
+            source,
+            target: target.clone(),
+            force: self.force,
+        }
+    }
+}
+
+/// Subcommands of `tree`
+#[derive(Subcommand, Debug)]
+pub enum TreeCommand {
+    /// Create an empty tree
+    Create {
+        #[arg(value_name = "TREE")]
+        name: String,
+    },
+    /// Remove a tree and all its keys
+    Drop {
+        #[arg(value_name = "TREE")]
+        name: String,
+        /// Drop without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Remove all keys of a tree, keeping the tree
+    Clear {
+        #[arg(value_name = "TREE")]
+        name: String,
+        /// Clear without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Move all keys of a tree to a new tree and drop it
+    Rename {
+        #[arg(value_name = "TREE")]
+        name: String,
+        /// The new tree, which must not exist
+        to: String,
+        /// Rename without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Copy all keys of a tree to a new tree
+    Copy {
+        #[arg(value_name = "TREE")]
+        name: String,
+        /// The new tree, which must not exist
+        to: String,
+        /// Copy without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+impl TreeCommand {
+    fn to_command(&self) -> Command {
+        let (action, yes) = match self {
+            TreeCommand::Create { name } => (TreeAction::Create { tree: name.clone() }, true),
+            TreeCommand::Drop { name, yes } => (TreeAction::Drop { tree: name.clone() }, *yes),
+            TreeCommand::Clear { name, yes } => (TreeAction::Clear { tree: name.clone() }, *yes),
+            TreeCommand::Rename { name, to, yes } => (
+                TreeAction::Rename {
+                    tree: name.clone(),
+                    to: to.clone(),
+                },
+                *yes,
+            ),
+            TreeCommand::Copy { name, to, yes } => (
+                TreeAction::Copy {
+                    tree: name.clone(),
+                    to: to.clone(),
+                },
+                *yes,
+            ),
+        };
+        Command::Tree { action, yes }
+    }
+}
+
+/// One-shot commands, mirroring the interactive `Command` variants
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Show total number of records
+    Count,
+    /// List keys matching a glob pattern (or a regex with --regex)
+    List {
+        #[arg(default_value = "*")]
+        pattern: String,
+        /// Treat the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        trees: TreesArgs,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// List entries with keys from START (inclusive) to END (exclusive)
+    Range {
+        start: String,
+        end: String,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// List entries whose stored key starts with PREFIX
+    Prefix {
+        prefix: String,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// List the first keys
+    Head {
+        #[arg(default_value_t = commands::DEFAULT_HEAD_COUNT,
+              value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        count: usize,
+    },
+    /// List the last keys
+    Tail {
+        #[arg(default_value_t = commands::DEFAULT_HEAD_COUNT,
+              value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        count: usize,
+    },
+    /// Print the value and info for a specific key
+    Get {
+        key: String,
+        /// Show a hexdump of these bytes of the value (start..end, start.. or ..end)
+        #[arg(long)]
+        bytes: Option<ByteRange>,
+        /// Hexdump bytes per line
+        #[arg(long, value_parser = hexdump::parse_width)]
+        width: Option<usize>,
+        /// Decode the value: text, json, msgpack, cbor or bincode:<type>
+        #[arg(long = "as", value_name = "DECODER", conflicts_with_all = ["bytes", "width"])]
+        decoder: Option<Decoder>,
+    },
+    /// Create or update a key-value pair
+    Set {
+        key: String,
+        value: String,
+        /// Only write if the key does not exist yet
+        #[arg(long, conflicts_with = "if_equals")]
+        if_absent: bool,
+        /// Only write if the key currently has this value
+        #[arg(long, value_name = "VALUE")]
+        if_equals: Option<String>,
+    },
+    /// Replace a value only if it still equals the expected one
+    Cas {
+        key: String,
+        expected: String,
+        value: String,
+    },
+    /// Copy a key with its value as stored, to another tree with <tree>:<key>
+    Cp(CopyArgs),
+    /// Move a key, to another tree with <tree>:<key>
+    Mv(CopyArgs),
+    /// Rename a key within the current tree
+    Rename(CopyArgs),
+    /// Delete a key, or every key matching --pattern, --regex or --range
+    #[command(visible_alias = "del")]
+    Delete {
+        #[arg(
+            required_unless_present_any = ["pattern", "regex", "range"],
+            conflicts_with_all = ["pattern", "regex", "range", "yes", "dry_run"]
+        )]
+        key: Option<String>,
+        /// Delete the keys matching a glob pattern
+        #[arg(long, conflicts_with_all = ["regex", "range"])]
+        pattern: Option<String>,
+        /// Delete the keys matching a regular expression
+        #[arg(long, conflicts_with = "range")]
+        regex: Option<String>,
+        /// Delete the keys from START (inclusive) to END (exclusive)
+        #[arg(long, num_args = 2, value_names = ["START", "END"])]
+        range: Option<Vec<String>>,
+        /// Delete without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+        /// Only list the keys that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Search values matching a glob pattern (or a regex with --regex)
+    Search {
+        pattern: String,
+        /// Treat the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        trees: TreesArgs,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// List entries whose value is a JSON document matching a query, like
+    /// '$.status == "failed" && $.retries > 3'
+    Query {
+        query: String,
+        /// Show these fields of each document as a table, e.g. '$.status,$.retries'
+        #[arg(long, value_name = "PATHS", value_delimiter = ',')]
+        select: Vec<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        trees: TreesArgs,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Run a SQL SELECT with trees as tables of key, value, size and the
+    /// fields of JSON values
+    Sql { statement: String },
+    /// List trees matching a glob pattern (or a regex with --regex)
+    Trees {
+        #[arg(default_value = "*")]
+        pattern: String,
+        /// Treat the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Create, drop, clear, rename or copy a tree
+    Tree {
+        #[command(subcommand)]
+        action: TreeCommand,
+    },
+    /// List the trees that contain a key
+    Where { key: String },
+}
+
+impl CliCommand {
+    /// Convert the subcommand into the equivalent interactive command
+    pub fn to_command(&self) -> Command {
+        match self {
+            CliCommand::Count => Command::Count,
+            CliCommand::List {
+                pattern,
+                regex,
+                filter,
+                trees,
+                page,
+            } => Command::List {
+                pattern: pattern.clone(),
+                is_regex: *regex,
+                filter: filter.into(),
+                trees: trees.glob(),
+                page: (*page).into(),
+            },
+            CliCommand::Range { start, end, page } => Command::Range {
+                start: start.clone(),
+                end: end.clone(),
+                page: (*page).into(),
+            },
+            CliCommand::Prefix { prefix, page } => Command::Prefix {
+                prefix: prefix.clone(),
+                page: (*page).into(),
+            },
+            CliCommand::Head { count } => Command::Head { count: *count },
+            CliCommand::Tail { count } => Command::Tail { count: *count },
+            CliCommand::Get {
+                key,
+                bytes,
+                width,
+                decoder,
+            } => Command::Get {
+                key: key.clone(),
+                bytes: *bytes,
+                width: *width,
+                decoder: *decoder,
+            },
+            CliCommand::Set {
+                key,
+                value,
+                if_absent,
+                if_equals,
+            } => Command::Set {
+                key: key.clone(),
+                value: value.clone(),
+                condition: match if_equals {
+                    Some(expected) => Some(Condition::Equals(expected.clone())),
+                    None => if_absent.then_some(Condition::Absent),
+                },
+            },
+            CliCommand::Cas {
+                key,
+                expected,
+                value,
+            } => Command::Cas {
+                key: key.clone(),
+                expected: expected.clone(),
+                value: value.clone(),
+            },
+            CliCommand::Cp(args) => args.to_command(CopyMode::Copy),
+            CliCommand::Mv(args) => args.to_command(CopyMode::Move),
+            CliCommand::Rename(args) => args.to_command(CopyMode::Rename),
+            CliCommand::Delete { key: Some(key), .. } => Command::Delete { key: key.clone() },
+            CliCommand::Delete {
+                key: None,
+                pattern,
+                regex,
+                range,
+                yes,
+                dry_run,
+            } => Command::DeleteMatching {
+                selection: match (pattern, regex, range.as_deref()) {
+                    (Some(pattern), _, _) => KeySelection::Glob(pattern.clone()),
+                    (_, Some(regex), _) => KeySelection::Regex(regex.clone()),
+                    (_, _, Some([start, end])) => KeySelection::Range {
+                        start: start.clone(),
+                        end: end.clone(),
+                    },
+                    _ => unreachable!("clap requires a key, a pattern, a regex or a range"),
+                },
+                yes: *yes,
+                dry_run: *dry_run,
+            },
+            CliCommand::Search {
+                pattern,
+                regex,
+                filter,
+                trees,
+                page,
+            } => Command::Search {
+                pattern: pattern.clone(),
+                is_regex: *regex,
+                filter: filter.into(),
+                trees: trees.glob(),
+                page: (*page).into(),
+            },
+            CliCommand::Query {
+                query,
+                select,
+                filter,
+                trees,
+                page,
+            } => Command::Query {
+                query: query.clone(),
+                select: select.clone(),
+                filter: filter.into(),
+                trees: trees.glob(),
+                page: (*page).into(),
+            },
+            CliCommand::Sql { statement } => Command::Sql {
+                statement: statement.clone(),
+            },
+            CliCommand::Trees {
+                pattern,
+                regex,
+                page,
+            } => Command::Trees {
+                pattern: pattern.clone(),
+                is_regex: *regex,
+                page: (*page).into(),
+            },
+            CliCommand::Tree { action } => action.to_command(),
+            CliCommand::Where { key } => Command::Where { key: key.clone() },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_without_subcommand() {
+        let args = Args::try_parse_from(["sledoview", "my.db"]).unwrap();
+        assert_eq!(args.database_path, PathBuf::from("my.db"));
+        assert!(args.command.is_none());
+        assert!(args.tree.is_none());
+        assert!(!args.read_only);
+        assert_eq!(args.format, OutputFormat::Text);
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "--read-only"]).unwrap();
+        assert!(args.read_only);
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "--write"]).unwrap();
+        assert!(args.write && !args.read_only);
+        assert!(Args::try_parse_from(["sledoview", "my.db", "--write", "--read-only"]).is_err());
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "--snapshot"]).unwrap();
+        assert!(args.snapshot);
+        assert!(Args::try_parse_from(["sledoview", "my.db", "--write", "--snapshot"]).is_err());
+    }
+
+    #[test]
+    fn test_args_with_subcommand() {
+        let args = Args::try_parse_from(["sledoview", "my.db", "--tree", "users", "get", "user_1"])
+            .unwrap();
+        assert_eq!(args.tree.as_deref(), Some("users"));
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(command, Command::Get { key, .. } if key == "user_1"));
+
+        let args = Args::try_parse_from([
+            "sledoview",
+            "my.db",
+            "get",
+            "blob",
+            "--bytes",
+            "..64",
+            "--width",
+            "8",
+        ])
+        .unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Get { bytes: Some(range), width: Some(8), .. }
+                if range == ByteRange { start: 0, end: Some(64) }
+        ));
+        assert!(
+            Args::try_parse_from(["sledoview", "my.db", "get", "blob", "--width", "0"]).is_err()
+        );
+
+        let args =
+            Args::try_parse_from(["sledoview", "my.db", "list", "user_*", "--regex"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(
+            matches!(command, Command::List { pattern, is_regex, .. } if pattern == "user_*" && is_regex)
+        );
+
+        let args = Args::try_parse_from([
+            "sledoview",
+            "my.db",
+            "search",
+            "*@x",
+            "--offset",
+            "20",
+            "--limit",
+            "10",
+        ])
+        .unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Search {
+                page: Page {
+                    offset: 20,
+                    limit: Some(10),
+                    sort: Sort::Bytes,
+                    reverse: false
+                },
+                ..
+            }
+        ));
+        assert!(Args::try_parse_from(["sledoview", "my.db", "list", "--limit", "0"]).is_err());
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "list", "--all-trees"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(command, Command::List { trees: Some(trees), .. } if trees == "*"));
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "search", "x", "--trees", "user*"])
+            .unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(command, Command::Search { trees: Some(trees), .. } if trees == "user*"));
+        assert!(Args::try_parse_from([
+            "sledoview",
+            "my.db",
+            "list",
+            "--all-trees",
+            "--trees",
+            "a*"
+        ])
+        .is_err());
+
+        let args = Args::try_parse_from([
+            "sledoview",
+            "my.db",
+            "search",
+            "x",
+            "-i",
+            "--key",
+            "user_*",
+            "--min-size",
+            "1k",
+            "--binary",
+        ])
+        .unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Search { filter, .. } if filter == Filter {
+                key: Some("user_*".to_string()),
+                ignore_case: true,
+                min_size: Some(1024),
+                kind: Some(ValueKind::Binary),
+                ..Filter::default()
+            }
+        ));
+        assert!(
+            Args::try_parse_from(["sledoview", "my.db", "list", "--text", "--binary"]).is_err()
+        );
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "where", "user_1"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(command, Command::Where { key } if key == "user_1"));
+
+        let args =
+            Args::try_parse_from(["sledoview", "my.db", "delete", "--range", "a", "m", "--yes"])
+                .unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::DeleteMatching { selection: KeySelection::Range { start, end }, yes: true, .. }
+                if start == "a" && end == "m"
+        ));
+        assert!(Args::try_parse_from(["sledoview", "my.db", "delete"]).is_err());
+        assert!(Args::try_parse_from(["sledoview", "my.db", "delete", "k", "--yes"]).is_err());
+
+        let args =
+            Args::try_parse_from(["sledoview", "my.db", "mv", "user_1", "archive:user_1"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Copy { mode: CopyMode::Move, source: CopySource::Key(key), target, force: false }
+                if key == "user_1" && target == "archive:user_1"
+        ));
+        let args = Args::try_parse_from([
+            "sledoview",
+            "my.db",
+            "cp",
+            "--regex",
+            "^s_(.*)",
+            "old:$1",
+            "-f",
+        ])
+        .unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Copy { mode: CopyMode::Copy, source: CopySource::Regex(regex), target, force: true }
+                if regex == "^s_(.*)" && target == "old:$1"
+        ));
+        assert!(Args::try_parse_from(["sledoview", "my.db", "cp", "user_1"]).is_err());
+
+        let args =
+            Args::try_parse_from(["sledoview", "my.db", "tree", "rename", "a", "b", "-y"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Tree { action: TreeAction::Rename { tree, to }, yes: true }
+                if tree == "a" && to == "b"
+        ));
+        let args = Args::try_parse_from(["sledoview", "my.db", "tree", "create", "a"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(
+            command,
+            Command::Tree {
+                action: TreeAction::Create { .. },
+                ..
+            }
+        ));
+        assert!(Args::try_parse_from(["sledoview", "my.db", "tree", "copy", "a"]).is_err());
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "tail"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(matches!(command, Command::Tail { count: 10 }));
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "trees"]).unwrap();
+        let command = args.command.unwrap().to_command();
+        assert!(
+            matches!(command, Command::Trees { pattern, is_regex, .. } if pattern == "*" && !is_regex)
+        );
+    }
+
+    #[test]
+    fn test_args_format() {
+        let args =
+            Args::try_parse_from(["sledoview", "my.db", "count", "--format", "csv"]).unwrap();
+        assert_eq!(args.format, OutputFormat::Csv);
+
+        let args = Args::try_parse_from(["sledoview", "my.db", "--format", "ndjson"]).unwrap();
+        assert_eq!(args.format, OutputFormat::Ndjson);
+
+        assert!(Args::try_parse_from(["sledoview", "my.db", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_args_script() {
+        let args = Args::try_parse_from([
+            "sledoview",
+            "my.db",
+            "--script",
+            "fix.sled",
+            "--continue-on-error",
+        ])
+        .unwrap();
+        assert_eq!(args.script, Some(PathBuf::from("fix.sled")));
+        assert!(args.continue_on_error);
+    }
+
+    #[test]
+    fn test_args_invalid_subcommand() {
+        assert!(Args::try_parse_from(["sledoview", "my.db", "set", "only_key"]).is_err());
+        assert!(Args::try_parse_from(["sledoview", "my.db", "bogus"]).is_err());
+    }
 }
@@ -0,0 +1,604 @@
+use crate::bytes;
+use crate::decode::Decoder;
+use crate::error::SledoViewError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Fixed-width integer key layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntType {
+    U32Be,
+    U32Le,
+    U64Be,
+    U64Le,
+    I64Be,
+    I64Le,
+}
+
+impl IntType {
+    const ALL: [(&'static str, IntType); 6] = [
+        ("u32be", IntType::U32Be),
+        ("u32le", IntType::U32Le),
+        ("u64be", IntType::U64Be),
+        ("u64le", IntType::U64Le),
+        ("i64be", IntType::I64Be),
+        ("i64le", IntType::I64Le),
+    ];
+
+    /// Look up a type by name; without a suffix, big-endian is assumed
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "u32" => Some(IntType::U32Be),
+            "u64" => Some(IntType::U64Be),
+            "i64" => Some(IntType::I64Be),
+            _ => Self::ALL.iter().find(|(n, _)| *n == name).map(|(_, t)| *t),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, t)| t == self)
+            .map(|(name, _)| *name)
+            .expect("every type is listed")
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            IntType::U32Be | IntType::U32Le => 4,
+            _ => 8,
+        }
+    }
+
+    fn encode(&self, number: &str) -> Result<Vec<u8>, String> {
+        match self {
+            IntType::U32Be => number.parse::<u32>().map(|n| n.to_be_bytes().to_vec()),
+            IntType::U32Le => number.parse::<u32>().map(|n| n.to_le_bytes().to_vec()),
+            IntType::U64Be => number.parse::<u64>().map(|n| n.to_be_bytes().to_vec()),
+            IntType::U64Le => number.parse::<u64>().map(|n| n.to_le_bytes().to_vec()),
+            IntType::I64Be => number.parse::<i64>().map(|n| n.to_be_bytes().to_vec()),
+            IntType::I64Le => number.parse::<i64>().map(|n| n.to_le_bytes().to_vec()),
+        }
+        .map_err(|_| format!("'{}' is not a valid {}", number, self.name()))
+    }
+
+    fn decode(&self, key: &[u8]) -> Option<String> {
+        self.value(key).map(|n| n.to_string())
+    }
+
+    /// The number a key holds, if it has this type's width
+    fn value(&self, key: &[u8]) -> Option<i128> {
+        Some(match self {
+            IntType::U32Be => u32::from_be_bytes(key.try_into().ok()?).into(),
+            IntType::U32Le => u32::from_le_bytes(key.try_into().ok()?).into(),
+            IntType::U64Be => u64::from_be_bytes(key.try_into().ok()?).into(),
+            IntType::U64Le => u64::from_le_bytes(key.try_into().ok()?).into(),
+            IntType::I64Be => i64::from_be_bytes(key.try_into().ok()?).into(),
+            IntType::I64Le => i64::from_le_bytes(key.try_into().ok()?).into(),
+        })
+    }
+}
+
+/// One part of a decoded key, ordered by value
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortPart<'a> {
+    Number(i128),
+    Bytes(&'a [u8]),
+}
+
+/// Parse a typed literal such as `u64:42`, `i64le:-1` or `uuid:<uuid>`.
+///
+/// Returns `None` when the text is not a typed literal, so that `u64:abc`
+/// stays ordinary text.
+pub fn parse_typed_literal(text: &str) -> Option<Result<Vec<u8>, String>> {
+    let (prefix, rest) = text.split_once(':')?;
+    if prefix == "uuid" {
+        return is_uuid(rest).then(|| encode_uuid(rest));
+    }
+
+    let int = IntType::from_name(prefix)?;
+    let digits = rest.strip_prefix('-').unwrap_or(rest);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(int.encode(rest))
+}
+
+fn is_uuid(text: &str) -> bool {
+    let hex: String = text.chars().filter(|&c| c != '-').collect();
+    let hyphens_ok = text.len() == 32
+        || (text.len() == 36 && [8, 13, 18, 23].iter().all(|&i| text.as_bytes()[i] == b'-'));
+    hyphens_ok && hex.len() == 32 && hex.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn encode_uuid(text: &str) -> Result<Vec<u8>, String> {
+    if !is_uuid(text) {
+        return Err(format!("'{}' is not a valid UUID", text));
+    }
+    let hex: String = text.chars().filter(|&c| c != '-').collect();
+    Ok((0..32)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("checked hex digits"))
+        .collect())
+}
+
+fn format_uuid(key: &[u8]) -> Option<String> {
+    if key.len() != 16 {
+        return None;
+    }
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+/// How keys are shown and typed.
+///
+/// Every displayed key can be typed back in. Keys that do not fit a typed
+/// codec are shown as `0x...` hex literals, which are always accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum KeyCodec {
+    /// Text, with other bytes escaped as `\xNN`
+    #[default]
+    Text,
+    /// `0x` followed by hex digits
+    Hex,
+    /// `b64:` followed by standard base64
+    Base64,
+    /// A fixed-width integer, shown as a number
+    Int(IntType),
+    /// 16 bytes, shown as a hyphenated UUID
+    Uuid,
+    /// Parts laid out back to back; all but the last have a fixed width.
+    /// Shown as `(a, b)`.
+    Tuple(Vec<KeyCodec>),
+    /// Parts joined by a separator. Shown as `(a, b)`.
+    Split {
+        separator: Vec<u8>,
+        parts: Vec<KeyCodec>,
+    },
+}
+
+impl KeyCodec {
+    /// Width of every key in this layout, if fixed
+    pub fn width(&self) -> Option<usize> {
+        match self {
+            KeyCodec::Int(int) => Some(int.width()),
+            KeyCodec::Uuid => Some(16),
+            KeyCodec::Tuple(parts) => parts.iter().map(KeyCodec::width).sum(),
+            _ => None,
+        }
+    }
+
+    /// Whether keys are decoded into values, rather than shown as bytes
+    pub fn is_typed(&self) -> bool {
+        !matches!(self, KeyCodec::Text | KeyCodec::Hex | KeyCodec::Base64)
+    }
+
+    /// Whether keys in byte order are also in the order of their values, so
+    /// a range of values is a range of bytes. Little-endian and signed
+    /// integers, and split keys, are not.
+    pub fn is_byte_ordered(&self) -> bool {
+        match self {
+            KeyCodec::Int(int) => matches!(int, IntType::U32Be | IntType::U64Be),
+            KeyCodec::Tuple(parts) => parts.iter().all(KeyCodec::is_byte_ordered),
+            KeyCodec::Split { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Compare two keys by their values, part by part for composite keys;
+    /// `None` when either key does not fit this layout
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Option<Ordering> {
+        Some(self.sort_parts(a)?.cmp(&self.sort_parts(b)?))
+    }
+
+    fn sort_parts<'a>(&self, key: &'a [u8]) -> Option<Vec<SortPart<'a>>> {
+        self.pieces(key)?
+            .into_iter()
+            .map(|(part, piece)| match part {
+                KeyCodec::Int(int) => int.value(piece).map(SortPart::Number),
+                KeyCodec::Uuid if piece.len() != 16 => None,
+                _ => Some(SortPart::Bytes(piece)),
+            })
+            .collect()
+    }
+
+    /// The parts of a key with their codecs; a key of a simple layout is one
+    /// part. `None` when a composite key does not split into its parts.
+    fn pieces<'a>(&self, key: &'a [u8]) -> Option<Vec<(&KeyCodec, &'a [u8])>> {
+        match self {
+            KeyCodec::Tuple(parts) => {
+                let mut rest = key;
+                let mut pieces = Vec::with_capacity(parts.len());
+                for (i, part) in parts.iter().enumerate() {
+                    let piece = if i + 1 == parts.len() {
+                        rest
+                    } else {
+                        let (piece, tail) = rest.split_at_checked(part.width()?)?;
+                        rest = tail;
+                        piece
+                    };
+                    pieces.push((part, piece));
+                }
+                Some(pieces)
+            }
+            KeyCodec::Split { separator, parts } => {
+                let pieces = split_bytes(key, separator, parts.len());
+                (pieces.len() == parts.len()).then(|| parts.iter().zip(pieces).collect())
+            }
+            _ => Some(vec![(self, key)]),
+        }
+    }
+
+    /// Format a key for display
+    pub fn display(&self, key: &[u8]) -> String {
+        match self.decode(key) {
+            Some(text) => text,
+            None => KeyCodec::Hex.display(key),
+        }
+    }
+
+    fn decode(&self, key: &[u8]) -> Option<String> {
+        match self {
+            KeyCodec::Text => Some(bytes::escape(key)),
+            KeyCodec::Hex if key.is_empty() => Some(String::new()),
+            KeyCodec::Hex => {
+                let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+                Some(format!("0x{}", hex))
+            }
+            KeyCodec::Base64 => Some(format!("b64:{}", BASE64.encode(key))),
+            KeyCodec::Int(int) => int.decode(key),
+            KeyCodec::Uuid => format_uuid(key),
+            KeyCodec::Tuple(_) | KeyCodec::Split { .. } => {
+                let shown = self
+                    .pieces(key)?
+                    .into_iter()
+                    .map(|(part, piece)| part.decode_part(piece))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!("({})", shown.join(", ")))
+            }
+        }
+    }
+
+    /// Decode one part of a composite key; commas are escaped so the parts
+    /// can be split again when typed back in
+    fn decode_part(&self, piece: &[u8]) -> Option<String> {
+        self.decode(piece).map(|text| text.replace(',', "\\x2c"))
+    }
+
+    /// Parse a key typed by the user.
+    ///
+    /// Byte literals (`0x...`, `b64:...`, `u64:42`, ...) are always accepted;
+    /// anything else is read in this codec's layout.
+    pub fn parse(&self, input: &str) -> Result<Vec<u8>, SledoViewError> {
+        if !self.is_typed() || bytes::is_prefixed_literal(input) {
+            return bytes::parse_literal(input);
+        }
+        self.encode(input)
+            .map_err(|message| SledoViewError::InvalidLiteral {
+                literal: input.to_string(),
+                message,
+            })
+    }
+
+    fn encode(&self, input: &str) -> Result<Vec<u8>, String> {
+        let input = input.trim();
+        match self {
+            KeyCodec::Text | KeyCodec::Hex | KeyCodec::Base64 => {
+                bytes::parse_literal(input).map_err(|e| e.to_string())
+            }
+            KeyCodec::Int(int) => int.encode(input),
+            KeyCodec::Uuid => encode_uuid(input),
+            KeyCodec::Tuple(parts) => {
+                let pieces = encode_parts(input, parts)?;
+                for (piece, part) in pieces.iter().zip(parts).take(parts.len() - 1) {
+                    if part.width().is_some_and(|width| width != piece.len()) {
+                        return Err(format!(
+                            "part {} does not fit {}",
+                            bytes::escape(piece),
+                            part
+                        ));
+                    }
+                }
+                Ok(pieces.concat())
+            }
+            KeyCodec::Split { separator, parts } => {
+                Ok(encode_parts(input, parts)?.join(&separator[..]))
+            }
+        }
+    }
+}
+
+/// Split `(a, b, ...)` and encode each part with its codec
+fn encode_parts(input: &str, parts: &[KeyCodec]) -> Result<Vec<Vec<u8>>, String> {
+    let inner = input
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(input);
+    let pieces: Vec<&str> = inner.split(',').map(str::trim).collect();
+    if pieces.len() != parts.len() {
+        return Err(format!(
+            "expected {} comma-separated parts, got {}",
+            parts.len(),
+            pieces.len()
+        ));
+    }
+    pieces
+        .iter()
+        .zip(parts)
+        .map(|(piece, part)| {
+            if bytes::is_prefixed_literal(piece) {
+                bytes::parse_literal(piece).map_err(|e| e.to_string())
+            } else {
+                part.encode(piece)
+            }
+        })
+        .collect()
+}
+
+/// Split `key` on `separator` into at most `limit` pieces
+fn split_bytes<'a>(key: &'a [u8], separator: &[u8], limit: usize) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::with_capacity(limit);
+    let mut rest = key;
+    while pieces.len() + 1 < limit {
+        match rest.windows(separator.len()).position(|w| w == separator) {
+            Some(pos) => {
+                pieces.push(&rest[..pos]);
+                rest = &rest[pos + separator.len()..];
+            }
+            None => break,
+        }
+    }
+    pieces.push(rest);
+    pieces
+}
+
+impl FromStr for KeyCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let composite = |prefix: &str| {
+            s.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(')'))
+                .map(|inner| inner.split(',').map(str::trim).collect::<Vec<_>>())
+        };
+
+        if let Some(items) = composite("tuple(") {
+            let parts = parse_parts(&items)?;
+            if parts[..parts.len() - 1].iter().any(|p| p.width().is_none()) {
+                return Err(format!(
+                    "Invalid key codec '{}': only the last part of a tuple may vary in width",
+                    s
+                ));
+            }
+            return Ok(KeyCodec::Tuple(parts));
+        }
+
+        if let Some(items) = composite("split(") {
+            let separator = items
+                .first()
+                .and_then(|sep| bytes::parse_literal(sep).ok())
+                .filter(|sep| !sep.is_empty())
+                .ok_or_else(|| format!("Invalid key codec '{}': missing separator", s))?;
+            return Ok(KeyCodec::Split {
+                separator,
+                parts: parse_parts(&items[1..])?,
+            });
+        }
+
+        match s.to_lowercase().as_str() {
+            "text" => Ok(KeyCodec::Text),
+            "hex" => Ok(KeyCodec::Hex),
+            "base64" | "b64" => Ok(KeyCodec::Base64),
+            "uuid" => Ok(KeyCodec::Uuid),
+            name => IntType::from_name(name).map(KeyCodec::Int).ok_or_else(|| {
+                format!(
+                    "Unknown key codec '{}'. Use text, hex, base64, u32be, u32le, u64be, \
+                     u64le, i64be, i64le, uuid, tuple(...) or split(<sep>, ...)",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+/// Parse the parts of a composite codec, which must themselves be simple
+fn parse_parts(items: &[&str]) -> Result<Vec<KeyCodec>, String> {
+    if items.len() < 2 {
+        return Err("A composite key codec needs at least two parts".to_string());
+    }
+    items
+        .iter()
+        .map(|item| match item.parse::<KeyCodec>()? {
+            KeyCodec::Tuple(_) | KeyCodec::Split { .. } => {
+                Err(format!("Composite key codecs cannot be nested: '{}'", item))
+            }
+            part => Ok(part),
+        })
+        .collect()
+}
+
+impl fmt::Display for KeyCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |parts: &[KeyCodec]| {
+            parts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        match self {
+            KeyCodec::Text => f.write_str("text"),
+            KeyCodec::Hex => f.write_str("hex"),
+            KeyCodec::Base64 => f.write_str("base64"),
+            KeyCodec::Int(int) => f.write_str(int.name()),
+            KeyCodec::Uuid => f.write_str("uuid"),
+            KeyCodec::Tuple(parts) => write!(f, "tuple({})", join(parts)),
+            KeyCodec::Split { separator, parts } => write!(
+                f,
+                "split({},{})",
+                bytes::escape(separator).replace(',', "\\x2c"),
+                join(parts)
+            ),
+        }
+    }
+}
+
+/// The codecs used to show the keys and values of a tree
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TreeCodecs {
+    pub key: KeyCodec,
+    /// Decoder for every value; detected per value when `None`
+    pub value: Option<Decoder>,
+}
+
+impl TreeCodecs {
+    /// Whether these are the codecs used without a profile
+    pub fn is_default(&self) -> bool {
+        *self == TreeCodecs::default()
+    }
+}
+
+impl fmt::Display for TreeCodecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(decoder) => write!(f, "keys {}, values {}", self.key, decoder),
+            None => write!(f, "keys {}, values detected", self.key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codec(spec: &str) -> KeyCodec {
+        spec.parse().unwrap()
+    }
+
+    #[test]
+    fn test_key_codec_display() {
+        let key = [0, 0, 0, 42];
+        assert_eq!(KeyCodec::Text.display(&key), "\\x00\\x00\\x00*");
+        assert_eq!(KeyCodec::Hex.display(&key), "0x0000002a");
+        assert_eq!(KeyCodec::Base64.display(&key), "b64:AAAAKg==");
+        assert_eq!(codec("u32be").display(&key), "42");
+        assert_eq!(codec("u32le").display(&key), "704643072");
+
+        // Keys that do not fit are shown as hex
+        assert_eq!(codec("u64be").display(&key), "0x0000002a");
+
+        let uuid = encode_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        assert_eq!(
+            KeyCodec::Uuid.display(&uuid),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+    }
+
+    #[test]
+    fn test_composite_key_codecs() {
+        let tuple = codec("tuple(u32be, i64be)");
+        let mut key = 7u32.to_be_bytes().to_vec();
+        key.extend_from_slice(&(-1i64).to_be_bytes());
+        assert_eq!(tuple.display(&key), "(7, -1)");
+        assert_eq!(tuple.parse("(7, -1)").unwrap(), key);
+        assert_eq!(tuple.to_string(), "tuple(u32be,i64be)");
+
+        let split = codec("split(:, text, u64be)");
+        let mut key = b"tenant,a:".to_vec();
+        key.extend_from_slice(&1700000000u64.to_be_bytes());
+        assert_eq!(split.display(&key), "(tenant\\x2ca, 1700000000)");
+        assert_eq!(split.parse(&split.display(&key)).unwrap(), key);
+
+        assert!("tuple(text, u64be)".parse::<KeyCodec>().is_err());
+        assert!("tuple(u64be)".parse::<KeyCodec>().is_err());
+        assert!("tuple(u64be, tuple(u32be, u32be))"
+            .parse::<KeyCodec>()
+            .is_err());
+        assert!("split(, text, text)".parse::<KeyCodec>().is_err());
+    }
+
+    #[test]
+    fn test_key_codec_order() {
+        let u64le = codec("u64le");
+        assert!(!u64le.is_byte_ordered());
+        let (one, big) = (u64le.parse("2").unwrap(), u64le.parse("256").unwrap());
+        assert!(one > big);
+        assert_eq!(u64le.compare(&one, &big), Some(Ordering::Less));
+
+        let tuple = codec("tuple(u32be, i64be)");
+        assert!(!tuple.is_byte_ordered());
+        let (low, high) = (
+            tuple.parse("(7, -10)").unwrap(),
+            tuple.parse("(7, 5)").unwrap(),
+        );
+        assert_eq!(tuple.compare(&low, &high), Some(Ordering::Less));
+        assert_eq!(tuple.compare(&low, b"short"), None);
+
+        assert!(codec("tuple(u32be, text)").is_byte_ordered());
+        assert!(!codec("split(:, text, text)").is_byte_ordered());
+        assert!(KeyCodec::Text.is_byte_ordered());
+    }
+
+    #[test]
+    fn test_key_codec_parse() {
+        let u64be = codec("u64");
+        assert_eq!(u64be.parse("42").unwrap(), 42u64.to_be_bytes());
+        assert_eq!(u64be.parse("0x01").unwrap(), [1]);
+        assert!(u64be.parse("-1").is_err());
+        assert!(u64be.parse("abc").is_err());
+
+        assert_eq!(KeyCodec::Text.parse("42").unwrap(), b"42");
+        assert_eq!(KeyCodec::Text.parse("u64le:1").unwrap(), 1u64.to_le_bytes());
+
+        // Displayed keys read back as the same bytes
+        for codec in [
+            KeyCodec::Text,
+            KeyCodec::Hex,
+            KeyCodec::Base64,
+            codec("u32be"),
+            codec("u64be"),
+            codec("uuid"),
+        ] {
+            let key = [0, 0, 0, 42];
+            assert_eq!(codec.parse(&codec.display(&key)).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_parse_typed_literal() {
+        assert_eq!(
+            parse_typed_literal("u64:42"),
+            Some(Ok(42u64.to_be_bytes().to_vec()))
+        );
+        assert_eq!(
+            parse_typed_literal("i64le:-2"),
+            Some(Ok((-2i64).to_le_bytes().to_vec()))
+        );
+        assert_eq!(
+            parse_typed_literal("uuid:67e5504410b1426f9247bb680e5fe0c8").map(|r| r.unwrap().len()),
+            Some(16)
+        );
+        assert!(parse_typed_literal("u32:4294967296").unwrap().is_err());
+        assert_eq!(parse_typed_literal("u64:abc"), None);
+        assert_eq!(parse_typed_literal("user:42"), None);
+    }
+
+    #[test]
+    fn test_key_codec_from_str() {
+        assert_eq!("HEX".parse(), Ok(KeyCodec::Hex));
+        assert_eq!("b64".parse(), Ok(KeyCodec::Base64));
+        assert_eq!("u64".parse(), Ok(KeyCodec::Int(IntType::U64Be)));
+        assert!("u128".parse::<KeyCodec>().is_err());
+    }
+}
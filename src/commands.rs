@@ -1,104 +1,582 @@
-use crate::db::{KeyInfo, SledViewer};
+use crate::bytes;
+use crate::codec::TreeCodecs;
+use crate::db::{self, Scan, ScanObserver, ScanPage, SledViewer};
+use crate::decode::{self, Decoder};
+use crate::error::SledoViewError;
+use crate::filter::{self, Filter, ValueKind};
+use crate::hexdump::{self, ByteRange};
+use crate::interrupt;
+use crate::output::{
+    Ack, ByteView, CommandOutput, Copied, DecodedValue, Deletion, Located, OutputFormat,
+};
+use crate::page::Page;
+use crate::query;
+use crate::render;
+use crate::script;
+use crate::sql;
 use anyhow::Result;
 use colored::*;
+use regex::bytes::Regex;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// An argument of a command line, unquoted and as typed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Arg {
+    value: String,
+    raw: String,
+}
+
+impl AsRef<str> for Arg {
+    fn as_ref(&self) -> &str {
+        &self.value
+    }
+}
 
-/// Parse quoted arguments from a command line, handling escaped quotes
-fn parse_quoted_args(input: &str) -> Vec<String> {
+/// Parse quoted arguments from a command line, handling escaped quotes.
+///
+/// `\"` becomes a quote; other backslash sequences are kept as typed, to be
+/// decoded by `bytes::parse_literal` or passed through to a regex. Each
+/// argument is also kept as typed, quotes and escapes included.
+fn split_args(input: &str) -> Vec<Arg> {
     let mut args = Vec::new();
-    let mut current_arg = String::new();
+    let mut current = Arg {
+        value: String::new(),
+        raw: String::new(),
+    };
     let mut in_quotes = false;
     let mut escape_next = false;
-    let mut chars = input.chars().peekable();
-
-    while let Some(ch) = chars.next() {
+    for ch in input.chars() {
         if escape_next {
-            current_arg.push(ch);
+            if ch != '"' {
+                current.value.push('\\');
+            }
+            current.value.push(ch);
+            current.raw.push(ch);
             escape_next = false;
         } else {
             match ch {
                 '\\' => {
                     escape_next = true;
+                    current.raw.push(ch);
                 }
                 '"' => {
                     if in_quotes {
                         // End of quoted string - always push even if empty
                         in_quotes = false;
-                        args.push(current_arg.clone());
-                        current_arg.clear();
+                        current.raw.push(ch);
+                        args.push(std::mem::take(&mut current));
                     } else {
                         // Start of quoted string
                         in_quotes = true;
-                        if !current_arg.is_empty() {
-                            args.push(current_arg.clone());
-                            current_arg.clear();
+                        if !current.value.is_empty() {
+                            args.push(std::mem::take(&mut current));
                         }
+                        current.raw = ch.to_string();
                     }
                 }
                 ' ' | '\t' => {
                     if in_quotes {
-                        current_arg.push(ch);
-                    } else if !current_arg.is_empty() {
-                        args.push(current_arg.clone());
-                        current_arg.clear();
+                        current.value.push(ch);
+                        current.raw.push(ch);
+                    } else if !current.value.is_empty() {
+                        args.push(std::mem::take(&mut current));
                     }
                 }
                 _ => {
-                    current_arg.push(ch);
+                    current.value.push(ch);
+                    current.raw.push(ch);
                 }
             }
         }
     }
 
-    if !current_arg.is_empty() {
-        args.push(current_arg);
+    if escape_next {
+        current.value.push('\\');
+    }
+    if !current.value.is_empty() {
+        args.push(current);
     }
 
     args
 }
 
-/// Validate that a key contains only allowed characters for SLED
-fn validate_key(key: &str) -> Result<(), String> {
+/// The arguments as typed, for commands that take the rest of the line as an
+/// expression. A single argument is unquoted, so `query "$.ok == 1"` works.
+fn raw_text(args: &[Arg]) -> String {
+    match args {
+        [arg] => arg.value.clone(),
+        _ => args
+            .iter()
+            .map(|arg| arg.raw.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// Keys shown by `head` and `tail` unless a count is given
+pub const DEFAULT_HEAD_COUNT: usize = 10;
+
+/// Split `--offset N` and `--limit N` off the arguments of a listing command
+fn take_page<A: AsRef<str> + Clone>(args: &[A]) -> Option<(Vec<A>, Page)> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut page = Page::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--offset" => page.offset = args.next()?.as_ref().parse().ok()?,
+            "--limit" => page.limit = Some(parse_count(args.next()?.as_ref())?),
+            "--sort" => page.sort = args.next()?.as_ref().parse().ok()?,
+            "--reverse" => page.reverse = true,
+            _ => rest.push(arg.clone()),
+        }
+    }
+    Some((rest, page))
+}
+
+/// Split `--all-trees` and `--trees <glob>` off the arguments of `list` or
+/// `search`; `--all-trees` is the glob `*`
+fn take_trees<A: AsRef<str>>(args: Vec<A>) -> Option<(Vec<A>, Option<String>)> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut trees = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--all-trees" => trees = Some("*".to_string()),
+            "--trees" => trees = Some(args.next()?.as_ref().to_string()),
+            _ => rest.push(arg),
+        }
+    }
+    Some((rest, trees))
+}
+
+/// Split the filter options off the arguments of `list` or `search`
+fn take_filter<A: AsRef<str>>(args: Vec<A>) -> Option<(Vec<A>, Filter)> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut filter = Filter::default();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || Some(args.next()?.as_ref().to_string());
+        match arg.as_ref() {
+            "--key" => filter.key = Some(value()?),
+            "--value" => filter.value = Some(value()?),
+            "-i" | "--ignore-case" => filter.ignore_case = true,
+            "--min-size" => filter.min_size = Some(filter::parse_size(&value()?).ok()?),
+            "--max-size" => filter.max_size = Some(filter::parse_size(&value()?).ok()?),
+            "--text" | "--binary" => {
+                let kind = if arg.as_ref() == "--text" {
+                    ValueKind::Text
+                } else {
+                    ValueKind::Binary
+                };
+                // A value is never both
+                if filter.kind.replace(kind).is_some_and(|other| other != kind) {
+                    return None;
+                }
+            }
+            "--empty" => filter.empty = true,
+            _ => rest.push(arg),
+        }
+    }
+    Some((rest, filter))
+}
+
+/// Split `--select` off the arguments of `query`: paths separated by
+/// commas, and the option may be repeated
+fn take_select<A: AsRef<str>>(args: Vec<A>) -> Option<(Vec<A>, Vec<String>)> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut select = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg.as_ref() == "--select" {
+            let paths = args.next()?;
+            let paths = paths.as_ref();
+            select.extend(paths.split(',').map(|path| path.trim().to_string()));
+        } else {
+            rest.push(arg);
+        }
+    }
+    Some((rest, select))
+}
+
+/// A number of items to show, at least one
+fn parse_count(arg: &str) -> Option<usize> {
+    arg.parse().ok().filter(|&count| count > 0)
+}
+
+/// Validate a key before writing it
+fn validate_key(key: &[u8]) -> Result<(), String> {
     if key.is_empty() {
         return Err("Key cannot be empty".to_string());
     }
 
     if key.len() > 512 {
-        return Err("Key too long (max 512 characters)".to_string());
+        return Err("Key too long (max 512 bytes)".to_string());
     }
 
-    // Allow alphanumeric, underscore, hyphen, dot, colon, and forward slash
-    // These are commonly safe characters for database keys
-    for ch in key.chars() {
-        if !ch.is_ascii_alphanumeric() && !matches!(ch, '_' | '-' | '.' | ':' | '/' | ' ') {
-            return Err(format!(
-                "Invalid character '{}' in key. Allowed: a-z, A-Z, 0-9, _, -, ., :, /, space",
-                ch
-            ));
+    Ok(())
+}
+
+/// What `set` requires of the current value before writing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// The key does not exist (`--if-absent`)
+    Absent,
+    /// The key has this value (`--if-equals`)
+    Equals(String),
+}
+
+/// The keys of the current tree a bulk delete removes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySelection {
+    /// Keys matching a glob (`--pattern`)
+    Glob(String),
+    /// Keys matching a regex (`--regex`)
+    Regex(String),
+    /// Keys from `start` (inclusive) to `end` (exclusive) (`--range`)
+    Range { start: String, end: String },
+}
+
+impl KeySelection {
+    /// The scan reading the selected keys
+    fn scan(&self, viewer: &SledViewer) -> Result<Scan> {
+        Ok(match self {
+            KeySelection::Glob(pattern) => Scan::Keys {
+                pattern: pattern_bytes(pattern, false)?,
+                is_regex: false,
+                filter: Filter::default(),
+            },
+            KeySelection::Regex(pattern) => Scan::Keys {
+                pattern: pattern_bytes(pattern, true)?,
+                is_regex: true,
+                filter: Filter::default(),
+            },
+            KeySelection::Range { start, end } => {
+                let codec = &viewer.codecs().key;
+                Scan::Range {
+                    start: codec.parse(start)?,
+                    end: codec.parse(end)?,
+                }
+            }
+        })
+    }
+}
+
+/// What `cp`, `mv` and `rename` do with the source keys and the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMode {
+    /// Keep the source keys (`cp`)
+    Copy,
+    /// Delete the source keys (`mv`)
+    Move,
+    /// Delete the source keys; the target is a key of the current tree,
+    /// even when it contains `:` (`rename`)
+    Rename,
+}
+
+/// The keys of the current tree that `cp`, `mv` and `rename` read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopySource {
+    Key(String),
+    /// Keys matching a glob (`--pattern`)
+    Glob(String),
+    /// Keys matching a regex (`--regex`)
+    Regex(String),
+}
+
+/// A change to a whole tree (`tree create|drop|clear|rename|copy`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeAction {
+    Create {
+        tree: String,
+    },
+    /// Remove the tree and its keys
+    Drop {
+        tree: String,
+    },
+    /// Remove the keys, keeping the tree
+    Clear {
+        tree: String,
+    },
+    /// Move the keys to a new tree and drop this one
+    Rename {
+        tree: String,
+        to: String,
+    },
+    /// Copy the keys to a new tree
+    Copy {
+        tree: String,
+        to: String,
+    },
+}
+
+impl TreeAction {
+    /// The name the command is typed as
+    fn name(&self) -> &'static str {
+        match self {
+            TreeAction::Create { .. } => "tree create",
+            TreeAction::Drop { .. } => "tree drop",
+            TreeAction::Clear { .. } => "tree clear",
+            TreeAction::Rename { .. } => "tree rename",
+            TreeAction::Copy { .. } => "tree copy",
         }
     }
 
-    Ok(())
+    /// The tree the action reads or changes
+    fn tree(&self) -> &str {
+        match self {
+            TreeAction::Create { tree }
+            | TreeAction::Drop { tree }
+            | TreeAction::Clear { tree }
+            | TreeAction::Rename { tree, .. }
+            | TreeAction::Copy { tree, .. } => tree,
+        }
+    }
+
+    /// The new tree written by `rename` and `copy`
+    fn target(&self) -> Option<&str> {
+        match self {
+            TreeAction::Rename { to, .. } | TreeAction::Copy { to, .. } => Some(to),
+            _ => None,
+        }
+    }
+
+    /// The action in a confirmation error, e.g. "Dropping"; `None` for
+    /// `create`, which needs no confirmation
+    fn confirmed_as(&self) -> Option<&'static str> {
+        match self {
+            TreeAction::Create { .. } => None,
+            TreeAction::Drop { .. } => Some("Dropping"),
+            TreeAction::Clear { .. } => Some("Clearing"),
+            TreeAction::Rename { .. } => Some("Renaming"),
+            TreeAction::Copy { .. } => Some("Copying"),
+        }
+    }
+
+    /// The question confirming the action on a tree of `count` keys
+    fn question(&self, count: usize) -> String {
+        let tree = self.tree();
+        let to = self.target().unwrap_or_default();
+        match self {
+            TreeAction::Create { .. } => format!("Create tree '{}'?", tree),
+            TreeAction::Drop { .. } => format!("Drop tree '{}' and its {} keys?", tree, count),
+            TreeAction::Clear { .. } => format!("Delete the {} keys of tree '{}'?", count, tree),
+            TreeAction::Rename { .. } => {
+                format!("Rename tree '{}' with {} keys to '{}'?", tree, count, to)
+            }
+            TreeAction::Copy { .. } => {
+                format!("Copy the {} keys of tree '{}' to '{}'?", count, tree, to)
+            }
+        }
+    }
+}
+
+/// Split a `cp`/`mv` target into its tree and key: `<tree>:<key>`, or
+/// `:<key>` and `<key>` for the current tree. A byte literal like `u64:7`
+/// is a key.
+fn split_target(target: &str) -> (Option<&str>, &str) {
+    if bytes::is_prefixed_literal(target) {
+        return (None, target);
+    }
+    match target.split_once(':') {
+        Some(("", key)) => (None, key),
+        Some((tree, key)) => (Some(tree), key),
+        None => (None, target),
+    }
+}
+
+/// The target of a key that `regex` matched: `template` with `$1`,
+/// `${name}` and so on replaced by what the groups captured. Keys shown
+/// through a typed codec are matched as shown, as `list` does.
+fn substitute(
+    regex: &Regex,
+    key: &[u8],
+    template: &str,
+    codecs: &TreeCodecs,
+    target_codecs: &TreeCodecs,
+) -> Result<Vec<u8>> {
+    let subject = if codecs.key.is_typed() {
+        codecs.key.display(key).into_bytes()
+    } else {
+        key.to_vec()
+    };
+    let captures = regex
+        .captures(&subject)
+        .expect("the key was matched by the regex");
+
+    let mut target = Vec::new();
+    if target_codecs.key.is_typed() {
+        captures.expand(template.as_bytes(), &mut target);
+        Ok(target_codecs.key.parse(&String::from_utf8_lossy(&target))?)
+    } else {
+        captures.expand(&bytes::parse_literal(template)?, &mut target);
+        Ok(target)
+    }
 }
 
 #[derive(Debug)]
 pub enum Command {
     Count,
-    List { pattern: String, is_regex: bool },
-    Get { key: String },
-    Set { key: String, value: String },
-    Delete { key: String },
-    Search { pattern: String, is_regex: bool },
-    Trees { pattern: String, is_regex: bool },
-    Select { tree: String },
+    List {
+        pattern: String,
+        is_regex: bool,
+        /// Conditions on top of the pattern
+        filter: Filter,
+        /// Glob of the trees to list instead of the current tree
+        trees: Option<String>,
+        page: Page,
+    },
+    Range {
+        start: String,
+        end: String,
+        page: Page,
+    },
+    Prefix {
+        prefix: String,
+        page: Page,
+    },
+    /// The first keys of the tree
+    Head {
+        count: usize,
+    },
+    /// The last keys of the tree
+    Tail {
+        count: usize,
+    },
+    /// The page after the last listing's, `limit` items long if given
+    More {
+        limit: Option<usize>,
+    },
+    Get {
+        key: String,
+        /// Only show these bytes of the value, as a hexdump
+        bytes: Option<ByteRange>,
+        /// Hexdump bytes per line
+        width: Option<usize>,
+        /// Decode the value instead of showing it as stored
+        decoder: Option<Decoder>,
+    },
+    Set {
+        key: String,
+        value: String,
+        /// Only write if the current value meets this
+        condition: Option<Condition>,
+    },
+    /// Replace a value only if it is still `expected`
+    Cas {
+        key: String,
+        expected: String,
+        value: String,
+    },
+    Delete {
+        key: String,
+    },
+    /// Copy keys with their values as stored, or move them
+    Copy {
+        mode: CopyMode,
+        source: CopySource,
+        /// The target key, `<tree>:<key>` for another tree; for a glob or
+        /// regex source, a template of the target keys
+        target: String,
+        /// Overwrite target keys that exist
+        force: bool,
+    },
+    /// Create, drop, clear, rename or copy a tree; all but `create` need
+    /// confirmation by `yes` or the user
+    Tree {
+        action: TreeAction,
+        yes: bool,
+    },
+    /// Delete every selected key, once confirmed by `yes` or the user
+    DeleteMatching {
+        selection: KeySelection,
+        yes: bool,
+        /// Only list the keys that would be deleted
+        dry_run: bool,
+    },
+    Search {
+        pattern: String,
+        is_regex: bool,
+        /// Conditions on top of the pattern
+        filter: Filter,
+        /// Glob of the trees to search instead of the current tree
+        trees: Option<String>,
+        page: Page,
+    },
+    /// Entries whose value is a JSON document meeting a query
+    Query {
+        query: String,
+        /// Paths of the fields to show in a table (`--select`)
+        select: Vec<String>,
+        filter: Filter,
+        trees: Option<String>,
+        page: Page,
+    },
+    /// A SQL `SELECT` over trees as tables
+    Sql {
+        statement: String,
+    },
+    Trees {
+        pattern: String,
+        is_regex: bool,
+        page: Page,
+    },
+    Select {
+        tree: String,
+        /// Create the tree when it does not exist
+        create: bool,
+    },
     Unselect,
+    /// The trees that contain a key
+    Where {
+        key: String,
+    },
+    Source {
+        path: PathBuf,
+        continue_on_error: bool,
+    },
+    Format {
+        format: Option<OutputFormat>,
+    },
+    /// Start staging writes
+    Begin,
+    /// Apply the staged writes atomically
+    Commit,
+    /// Discard the staged writes
+    Rollback,
+    /// Show the staged writes
+    Status,
     Help,
     Exit,
 }
 
 impl Command {
+    /// Parse a command line; `--format` is not recognised here
+    #[allow(dead_code)] // the binary itself parses with `parse_with_format`
     pub fn parse(input: &str) -> Option<Command> {
-        let args = parse_quoted_args(input);
+        Self::from_args(&split_args(input))
+    }
+
+    /// Parse a command line, extracting a per-command `--format <format>` option
+    pub fn parse_with_format(input: &str) -> Option<(Command, Option<OutputFormat>)> {
+        let mut args = split_args(input);
+        let mut format = None;
 
+        if let Some(pos) = args.iter().position(|arg| arg.value == "--format") {
+            format = Some(args.get(pos + 1)?.value.parse().ok()?);
+            args.drain(pos..pos + 2);
+        }
+
+        Self::from_args(&args).map(|command| (command, format))
+    }
+
+    fn from_args(line: &[Arg]) -> Option<Command> {
+        let values: Vec<String> = line.iter().map(|arg| arg.value.clone()).collect();
+        let args = &values[..];
         if args.is_empty() {
             return None;
         }
@@ -106,583 +584,1064 @@ impl Command {
         match args[0].to_lowercase().as_str() {
             "count" => Some(Command::Count),
             "list" => {
+                let (args, page) = take_page(args)?;
+                let (args, trees) = take_trees(args)?;
+                let (args, filter) = take_filter(args)?;
                 if args.len() == 1 {
                     Some(Command::List {
                         pattern: "*".to_string(),
                         is_regex: false,
+                        filter: filter.clone(),
+                        trees,
+                        page,
                     })
                 } else if args.len() == 2 {
                     Some(Command::List {
                         pattern: args[1].clone(),
                         is_regex: false,
+                        filter: filter.clone(),
+                        trees,
+                        page,
                     })
                 } else if args.len() == 3 && args[1] == "regex" {
                     Some(Command::List {
                         pattern: args[2].clone(),
                         is_regex: true,
+                        filter: filter.clone(),
+                        trees,
+                        page,
                     })
                 } else {
                     None
                 }
             }
-            "get" => {
-                if args.len() >= 2 {
-                    Some(Command::Get {
-                        key: args[1].clone(),
-                    })
+            "range" => match take_page(args)? {
+                (args, page) if args.len() == 3 => Some(Command::Range {
+                    start: args[1].clone(),
+                    end: args[2].clone(),
+                    page,
+                }),
+                _ => None,
+            },
+            "prefix" => match take_page(args)? {
+                (args, page) if args.len() == 2 => Some(Command::Prefix {
+                    prefix: args[1].clone(),
+                    page,
+                }),
+                _ => None,
+            },
+            "head" | "tail" => {
+                let count = match args.len() {
+                    1 => DEFAULT_HEAD_COUNT,
+                    2 => parse_count(&args[1])?,
+                    _ => return None,
+                };
+                if args[0].eq_ignore_ascii_case("head") {
+                    Some(Command::Head { count })
                 } else {
-                    None
+                    Some(Command::Tail { count })
+                }
+            }
+            "more" | "next" => match args.len() {
+                1 => Some(Command::More { limit: None }),
+                2 => Some(Command::More {
+                    limit: Some(parse_count(&args[1])?),
+                }),
+                _ => None,
+            },
+            "get" => {
+                let key = args.get(1)?.clone();
+                let mut bytes = None;
+                let mut width = None;
+                let mut decoder = None;
+
+                let mut options = args[2..].iter();
+                while let Some(option) = options.next() {
+                    match option.as_str() {
+                        "--bytes" => bytes = Some(options.next()?.parse().ok()?),
+                        "--width" => width = Some(hexdump::parse_width(options.next()?).ok()?),
+                        "--as" => decoder = Some(options.next()?.parse().ok()?),
+                        _ => return None,
+                    }
                 }
+
+                // A decoded value has no byte layout to dump
+                if decoder.is_some() && (bytes.is_some() || width.is_some()) {
+                    return None;
+                }
+
+                Some(Command::Get {
+                    key,
+                    bytes,
+                    width,
+                    decoder,
+                })
             }
             "set" => {
-                if args.len() >= 3 {
-                    Some(Command::Set {
+                if args.len() < 3 {
+                    return None;
+                }
+                let mut condition = None;
+                let mut options = args[3..].iter();
+                while let Some(option) = options.next() {
+                    let parsed = match option.as_str() {
+                        "--if-absent" => Condition::Absent,
+                        "--if-equals" => Condition::Equals(options.next()?.clone()),
+                        _ => return None,
+                    };
+                    if condition.replace(parsed).is_some() {
+                        return None;
+                    }
+                }
+                Some(Command::Set {
+                    key: args[1].clone(),
+                    value: args[2].clone(),
+                    condition,
+                })
+            }
+            "cas" => {
+                if args.len() == 4 {
+                    Some(Command::Cas {
                         key: args[1].clone(),
-                        value: args[2].clone(),
+                        expected: args[2].clone(),
+                        value: args[3].clone(),
                     })
                 } else {
                     None
                 }
             }
             "delete" | "del" => {
-                if args.len() >= 2 {
-                    Some(Command::Delete {
-                        key: args[1].clone(),
-                    })
-                } else {
-                    None
+                let mut key = None;
+                let mut selection = None;
+                let mut yes = false;
+                let mut dry_run = false;
+
+                let mut args = args[1..].iter();
+                while let Some(arg) = args.next() {
+                    let parsed = match arg.as_str() {
+                        "--pattern" => KeySelection::Glob(args.next()?.clone()),
+                        "--regex" => KeySelection::Regex(args.next()?.clone()),
+                        "--range" => KeySelection::Range {
+                            start: args.next()?.clone(),
+                            end: args.next()?.clone(),
+                        },
+                        "--yes" | "-y" => {
+                            yes = true;
+                            continue;
+                        }
+                        "--dry-run" => {
+                            dry_run = true;
+                            continue;
+                        }
+                        _ if key.is_none() => {
+                            key = Some(arg.clone());
+                            continue;
+                        }
+                        _ => return None,
+                    };
+                    if selection.replace(parsed).is_some() {
+                        return None;
+                    }
                 }
+
+                match (key, selection) {
+                    (Some(key), None) if !yes && !dry_run => Some(Command::Delete { key }),
+                    (None, Some(selection)) => Some(Command::DeleteMatching {
+                        selection,
+                        yes,
+                        dry_run,
+                    }),
+                    _ => None,
+                }
+            }
+            "cp" | "mv" | "rename" => {
+                let mode = match args[0].to_lowercase().as_str() {
+                    "cp" => CopyMode::Copy,
+                    "mv" => CopyMode::Move,
+                    _ => CopyMode::Rename,
+                };
+                let mut source = None;
+                let mut positional = Vec::new();
+                let mut force = false;
+
+                let mut args = args[1..].iter();
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--pattern" if source.is_none() => {
+                            source = Some(CopySource::Glob(args.next()?.clone()))
+                        }
+                        "--regex" if source.is_none() => {
+                            source = Some(CopySource::Regex(args.next()?.clone()))
+                        }
+                        "--force" | "-f" => force = true,
+                        _ => positional.push(arg.clone()),
+                    }
+                }
+
+                let (source, target) = match (source, &positional[..]) {
+                    (None, [key, target]) => (CopySource::Key(key.clone()), target.clone()),
+                    (Some(source), [target]) => (source, target.clone()),
+                    _ => return None,
+                };
+                Some(Command::Copy {
+                    mode,
+                    source,
+                    target,
+                    force,
+                })
             }
             "search" => {
+                let (args, page) = take_page(args)?;
+                let (args, trees) = take_trees(args)?;
+                let (args, filter) = take_filter(args)?;
                 if args.len() == 1 {
                     None
                 } else if args.len() == 2 {
                     Some(Command::Search {
                         pattern: args[1].clone(),
                         is_regex: false,
+                        filter: filter.clone(),
+                        trees,
+                        page,
                     })
                 } else if args.len() == 3 && args[1] == "regex" {
                     Some(Command::Search {
                         pattern: args[2].clone(),
                         is_regex: true,
+                        filter: filter.clone(),
+                        trees,
+                        page,
                     })
                 } else {
                     None
                 }
             }
+            "query" => {
+                let (args, page) = take_page(line)?;
+                let (args, trees) = take_trees(args)?;
+                let (args, filter) = take_filter(args)?;
+                let (args, select) = take_select(args)?;
+                // The rest of the line is the query as typed, so it needs no
+                // quotes and its strings keep theirs
+                let query = raw_text(&args[1..]);
+                if query.is_empty() {
+                    return None;
+                }
+                Some(Command::Query {
+                    query,
+                    select,
+                    filter,
+                    trees,
+                    page,
+                })
+            }
+            "sql" => {
+                // The rest of the line is the statement, as for `query`
+                let statement = raw_text(&line[1..]);
+                if statement.is_empty() {
+                    return None;
+                }
+                Some(Command::Sql { statement })
+            }
             "trees" => {
+                let (args, page) = take_page(args)?;
                 if args.len() == 1 {
                     Some(Command::Trees {
                         pattern: "*".to_string(),
                         is_regex: false,
+                        page,
                     })
                 } else if args.len() == 2 {
                     Some(Command::Trees {
                         pattern: args[1].clone(),
                         is_regex: false,
+                        page,
                     })
                 } else if args.len() == 3 && args[1] == "regex" {
                     Some(Command::Trees {
                         pattern: args[2].clone(),
                         is_regex: true,
+                        page,
                     })
                 } else {
                     None
                 }
             }
             "select" => {
-                if args.len() >= 2 {
-                    Some(Command::Select {
-                        tree: args[1].clone(),
-                    })
-                } else {
-                    None
-                }
+                let create = args.iter().any(|arg| arg == "--create");
+                let tree = args[1..].iter().find(|arg| *arg != "--create")?;
+                Some(Command::Select {
+                    tree: tree.clone(),
+                    create,
+                })
+            }
+            "tree" => {
+                let yes = args.iter().any(|arg| arg == "--yes" || arg == "-y");
+                let args: Vec<&str> = args[1..]
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|arg| !matches!(*arg, "--yes" | "-y"))
+                    .collect();
+                let action = match args[..] {
+                    ["create", tree] => TreeAction::Create {
+                        tree: tree.to_string(),
+                    },
+                    ["drop", tree] => TreeAction::Drop {
+                        tree: tree.to_string(),
+                    },
+                    ["clear", tree] => TreeAction::Clear {
+                        tree: tree.to_string(),
+                    },
+                    ["rename", tree, to] => TreeAction::Rename {
+                        tree: tree.to_string(),
+                        to: to.to_string(),
+                    },
+                    ["copy", tree, to] => TreeAction::Copy {
+                        tree: tree.to_string(),
+                        to: to.to_string(),
+                    },
+                    _ => return None,
+                };
+                Some(Command::Tree { action, yes })
             }
             "unselect" => Some(Command::Unselect),
+            "where" => match args.len() {
+                2 => Some(Command::Where {
+                    key: args[1].clone(),
+                }),
+                _ => None,
+            },
+            "source" => match args.len() {
+                2 => Some(Command::Source {
+                    path: PathBuf::from(&args[1]),
+                    continue_on_error: false,
+                }),
+                3 if args[2] == "--continue" => Some(Command::Source {
+                    path: PathBuf::from(&args[1]),
+                    continue_on_error: true,
+                }),
+                _ => None,
+            },
+            "format" => match args.len() {
+                1 => Some(Command::Format { format: None }),
+                2 => Some(Command::Format {
+                    format: Some(args[1].parse().ok()?),
+                }),
+                _ => None,
+            },
+            "begin" => Some(Command::Begin),
+            "commit" => Some(Command::Commit),
+            "rollback" => Some(Command::Rollback),
+            "status" => Some(Command::Status),
             "help" | "?" => Some(Command::Help),
             "exit" | "quit" | "q" => Some(Command::Exit),
             _ => None,
         }
     }
 
-    fn format_value_preview(info: &KeyInfo) -> String {
-        if !info.is_utf8 {
-            return "(binary data)".red().to_string();
+    /// The name the command is typed as, used in messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Count => "count",
+            Command::List { .. } => "list",
+            Command::Range { .. } => "range",
+            Command::Prefix { .. } => "prefix",
+            Command::Head { .. } => "head",
+            Command::Tail { .. } => "tail",
+            Command::More { .. } => "more",
+            Command::Get { .. } => "get",
+            Command::Set { .. } => "set",
+            Command::Cas { .. } => "cas",
+            Command::Copy { mode, .. } => match mode {
+                CopyMode::Copy => "cp",
+                CopyMode::Move => "mv",
+                CopyMode::Rename => "rename",
+            },
+            Command::Tree { action, .. } => action.name(),
+            Command::Delete { .. } | Command::DeleteMatching { .. } => "delete",
+            Command::Search { .. } => "search",
+            Command::Query { .. } => "query",
+            Command::Sql { .. } => "sql",
+            Command::Trees { .. } => "trees",
+            Command::Select { .. } => "select",
+            Command::Unselect => "unselect",
+            Command::Where { .. } => "where",
+            Command::Source { .. } => "source",
+            Command::Format { .. } => "format",
+            Command::Begin => "begin",
+            Command::Commit => "commit",
+            Command::Rollback => "rollback",
+            Command::Status => "status",
+            Command::Help => "help",
+            Command::Exit => "exit",
         }
+    }
 
-        if info.value.is_empty() {
-            return "(empty)".bright_black().to_string();
-        }
+    /// Whether the command modifies the database
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Command::Set { .. }
+                | Command::Cas { .. }
+                | Command::Copy { .. }
+                | Command::Delete { .. }
+                | Command::DeleteMatching { dry_run: false, .. }
+                | Command::Tree { .. }
+                | Command::Select { create: true, .. }
+                | Command::Begin
+                | Command::Commit
+        )
+    }
 
-        // For short values, show them fully
-        if info.value.len() <= 50 {
-            return info.value.bright_green().to_string();
-        }
+    /// Execute the command and return its result without printing anything.
+    ///
+    /// `source` and `format` depend on session state; `execute_in_session`
+    /// handles them. Here `source` runs the script with text output and
+    /// `format` reports the default format. Listings are returned whole
+    /// unless `--limit` is given.
+    #[allow(dead_code)] // the binary itself executes with `execute_for`
+    pub fn execute(&self, viewer: &mut SledViewer) -> Result<CommandOutput> {
+        self.execute_paged(viewer, None, &mut ())
+    }
 
-        // For longer values, show a preview with truncation
-        let preview = info.value.chars().take(47).collect::<String>();
-        format!("{}...", preview).bright_green().to_string()
+    /// Execute the command for output in `format`. Listings shown as text are
+    /// split into pages of `render::TEXT_PAGE_SIZE` unless `--limit` is given.
+    pub fn execute_for(
+        &self,
+        viewer: &mut SledViewer,
+        format: OutputFormat,
+    ) -> Result<CommandOutput> {
+        let default_limit = format.is_text().then_some(render::TEXT_PAGE_SIZE);
+        self.execute_paged(viewer, default_limit, &mut ())
     }
 
-    pub fn execute(&self, viewer: &mut SledViewer) -> Result<()> {
-        match self {
-            Command::Count => {
-                let count = viewer.count()?;
-                println!(
-                    "{} {}",
-                    "Total records:".bright_blue().bold(),
-                    count.to_string().bright_yellow().bold()
-                );
-            }
-            Command::List { pattern, is_regex } => {
-                let keys = viewer.list_keys(pattern, *is_regex)?;
-                if keys.is_empty() {
-                    println!("{}", "No keys found matching the pattern.".yellow());
-                } else {
-                    let total_count = keys.len();
-                    let display_keys = if total_count > 50 {
-                        &keys[0..50]
-                    } else {
-                        &keys
-                    };
+    /// Write a value, only if the key's current value is `expected` when
+    /// given (`Some(None)` for absent)
+    fn set(
+        &self,
+        viewer: &mut SledViewer,
+        key: &str,
+        value: &str,
+        expected: Option<Option<Vec<u8>>>,
+    ) -> Result<CommandOutput> {
+        let key = viewer.codecs().key.parse(key)?;
+        let value = bytes::parse_literal(value)?;
+
+        // Validate the key first
+        validate_key(&key).map_err(anyhow::Error::msg)?;
+
+        match expected {
+            Some(expected) => viewer.compare_and_swap(&key, expected.as_deref(), Some(&value))?,
+            None => viewer.set_key(&key, &value)?,
+        }
+        Ok(CommandOutput::Ack(Ack {
+            command: self.name(),
+            target: viewer.codecs().key.display(&key),
+            value: Some(bytes::escape(&value)),
+            changed: true,
+            staged: viewer.staged().is_some(),
+        }))
+    }
 
-                    println!(
-                        "{} {} {}",
-                        "Found".bright_blue(),
-                        total_count.to_string().bright_yellow().bold(),
-                        "keys:".bright_blue()
-                    );
-
-                    for (i, key) in display_keys.iter().enumerate() {
-                        // Get value preview for each key
-                        match viewer.get_key(key) {
-                            Ok(info) => {
-                                let preview = Self::format_value_preview(&info);
-                                println!(
-                                    "  {}: {} = {}",
-                                    (i + 1).to_string().bright_black(),
-                                    key.bright_white(),
-                                    preview
-                                );
-                            }
-                            Err(_) => {
-                                // Key might have been deleted, just show key name
-                                println!(
-                                    "  {}: {} = {}",
-                                    (i + 1).to_string().bright_black(),
-                                    key.bright_white(),
-                                    "(error reading value)".red()
-                                );
+    /// Copy or move keys to their targets, all or none of them
+    fn copy(
+        &self,
+        viewer: &mut SledViewer,
+        mode: CopyMode,
+        source: &CopySource,
+        target: &str,
+        force: bool,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<CommandOutput> {
+        let (tree, target) = match mode {
+            CopyMode::Rename => (None, target),
+            CopyMode::Copy | CopyMode::Move => split_target(target),
+        };
+        let tree = tree.map(bytes::parse_literal).transpose()?;
+        let codecs = viewer.codecs().clone();
+        let target_codecs = match &tree {
+            Some(tree) => viewer.codecs_for(tree),
+            None => codecs.clone(),
+        };
+
+        // An empty target key, as in `archive:`, keeps the key
+        let pairs = match source {
+            CopySource::Key(key) => {
+                let from = codecs.key.parse(key)?;
+                let to = match target {
+                    "" => from.clone(),
+                    target => target_codecs.key.parse(target)?,
+                };
+                vec![(from, to)]
+            }
+            CopySource::Glob(pattern) | CopySource::Regex(pattern) => {
+                let is_regex = matches!(source, CopySource::Regex(_));
+                let pattern = pattern_bytes(pattern, is_regex)?;
+                let regex = db::compile_pattern(&pattern, is_regex, false)?;
+                let scan = Scan::Keys {
+                    pattern,
+                    is_regex,
+                    filter: Filter::default(),
+                };
+                viewer
+                    .matching_keys(&scan, observer)?
+                    .into_iter()
+                    .map(|from| {
+                        let to = match target {
+                            "" => from.clone(),
+                            template => {
+                                substitute(&regex, &from, template, &codecs, &target_codecs)?
                             }
-                        }
-                    }
-
-                    if total_count > 50 {
-                        println!(
-                            "{}",
-                            format!("... and {} more keys (showing first 50)", total_count - 50)
-                                .bright_yellow()
-                        );
-                    }
-                }
+                        };
+                        Ok((from, to))
+                    })
+                    .collect::<Result<Vec<_>>>()?
             }
-            Command::Get { key } => match viewer.get_key(key) {
-                Ok(info) => {
-                    print_key_info(&info);
-                }
-                Err(e) => {
-                    println!("{} {}", "Error:".bright_red().bold(), e.to_string().red());
-                }
-            },
-            Command::Set { key, value } => {
-                // Validate the key first
-                if let Err(error_msg) = validate_key(key) {
-                    println!("{} {}", "Error:".bright_red().bold(), error_msg.red());
-                    return Ok(());
-                }
+        };
+        for (_, to) in &pairs {
+            validate_key(to).map_err(anyhow::Error::msg)?;
+        }
 
-                match viewer.set_key(key, value) {
-                    Ok(_) => {
-                        println!(
-                            "{} {} {} {}",
-                            "✓".bright_green().bold(),
-                            "Successfully set key".bright_green(),
-                            key.bright_cyan().bold(),
-                            "with value".bright_green()
-                        );
-                        let truncated_value = if value.len() > 50 {
-                            format!("{}...", &value[..50])
-                        } else {
-                            value.clone()
-                        };
-                        println!(
-                            "  {} {}",
-                            "Value:".bright_blue(),
-                            truncated_value.bright_white()
-                        );
-                    }
-                    Err(e) => {
-                        println!(
-                            "{} {} {} {}",
-                            "✗".bright_red().bold(),
-                            "Failed to set key".bright_red(),
-                            key.bright_cyan().bold(),
-                            e.to_string().red()
-                        );
-                    }
-                }
+        viewer.copy_keys(&pairs, tree.as_deref(), mode != CopyMode::Copy, force)?;
+        Ok(CommandOutput::Copied(Copied {
+            command: self.name(),
+            pairs,
+            tree,
+            codecs,
+            target_codecs,
+            staged: viewer.staged().is_some(),
+        }))
+    }
+
+    /// Change a whole tree. `tree` and `to` are byte literals.
+    fn change_tree(
+        &self,
+        viewer: &mut SledViewer,
+        action: &TreeAction,
+        yes: bool,
+    ) -> Result<CommandOutput> {
+        let tree = bytes::parse_literal(action.tree())?;
+        let to = action.target().map(bytes::parse_literal).transpose()?;
+        if let (Some(confirmed_as), false) = (action.confirmed_as(), yes) {
+            return Err(SledoViewError::TreeConfirmationRequired {
+                action: confirmed_as.to_string(),
+                tree: bytes::escape(&tree),
             }
-            Command::Delete { key } => match viewer.delete_key(key) {
-                Ok(existed) => {
-                    if existed {
-                        println!(
-                            "{} {} {}",
-                            "✓".bright_green().bold(),
-                            "Successfully deleted key".bright_green(),
-                            key.bright_cyan().bold()
-                        );
-                    } else {
-                        println!(
-                            "{} {} {}",
-                            "!".bright_yellow().bold(),
-                            "Key not found:".bright_yellow(),
-                            key.bright_cyan().bold()
-                        );
-                    }
-                }
-                Err(e) => {
-                    println!(
-                        "{} {} {} {}",
-                        "✗".bright_red().bold(),
-                        "Failed to delete key".bright_red(),
-                        key.bright_cyan().bold(),
-                        e.to_string().red()
-                    );
-                }
-            },
-            Command::Search { pattern, is_regex } => {
-                let results = viewer.search_values(pattern, *is_regex)?;
-                if results.is_empty() {
-                    println!("{}", "No values found matching the pattern.".yellow());
+            .into());
+        }
+
+        let (changed, value) = match action {
+            TreeAction::Create { .. } => (viewer.create_tree(&tree)?, None),
+            TreeAction::Drop { .. } => (true, Some(viewer.drop_tree(&tree)?.to_string())),
+            TreeAction::Clear { .. } => (true, Some(viewer.clear_tree(&tree)?.to_string())),
+            TreeAction::Rename { .. } | TreeAction::Copy { .. } => {
+                let to = to.expect("rename and copy have a target");
+                if matches!(action, TreeAction::Rename { .. }) {
+                    viewer.rename_tree(&tree, &to)?;
                 } else {
-                    println!(
-                        "{} {} {}",
-                        "Found".bright_blue(),
-                        results.len().to_string().bright_yellow().bold(),
-                        "matches:".bright_blue()
-                    );
-                    for (i, pair) in results.iter().enumerate() {
-                        println!(
-                            "  {}: {} {} {}",
-                            (i + 1).to_string().bright_black(),
-                            pair.key.bright_cyan().bold(),
-                            "=>".bright_black(),
-                            truncate_value(&pair.value, 100).bright_white()
-                        );
-                    }
+                    viewer.copy_tree(&tree, &to)?;
                 }
+                (true, Some(bytes::escape(&to)))
             }
-            Command::Trees { pattern, is_regex } => {
-                let trees = viewer.list_trees(pattern, *is_regex)?;
-                if trees.is_empty() {
-                    println!("{}", "No trees found matching the pattern.".yellow());
-                } else {
-                    let total_count = trees.len();
-                    let display_trees = if total_count > 50 {
-                        &trees[0..50]
-                    } else {
-                        &trees
-                    };
-
-                    println!(
-                        "{} {} {}",
-                        "Found".bright_blue(),
-                        total_count.to_string().bright_yellow().bold(),
-                        "trees:".bright_blue()
-                    );
+        };
+        Ok(CommandOutput::Ack(Ack {
+            command: self.name(),
+            target: bytes::escape(&tree),
+            value,
+            changed,
+            staged: false,
+        }))
+    }
 
-                    for tree_name in display_trees {
-                        println!("  {}", tree_name.bright_cyan());
-                    }
+    /// Execute the command, reporting the progress of scans to `observer`
+    fn execute_paged(
+        &self,
+        viewer: &mut SledViewer,
+        default_limit: Option<usize>,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<CommandOutput> {
+        if self.is_write() && viewer.is_read_only() {
+            return Err(SledoViewError::ReadOnly {
+                command: self.name().to_string(),
+            }
+            .into());
+        }
 
-                    if total_count > 50 {
-                        println!(
-                            "{}",
-                            format!("... and {} more trees (showing first 50)", total_count - 50)
-                                .bright_yellow()
-                        );
-                    }
-                }
+        let output = match self {
+            Command::Count => CommandOutput::Count(viewer.count()?),
+            Command::List {
+                pattern,
+                is_regex,
+                filter,
+                trees,
+                page,
+            } => {
+                let scan = Scan::Keys {
+                    pattern: pattern_bytes(pattern, *is_regex)?,
+                    is_regex: *is_regex,
+                    filter: filter.clone(),
+                };
+                let page = page.or_limit(default_limit);
+                scan_in(viewer, trees.as_deref(), scan, page, observer)?.into()
             }
-            Command::Select { tree } => match viewer.select_tree(tree) {
-                Ok(_) => {
-                    println!(
-                        "{} {} {}",
-                        "✓".bright_green().bold(),
-                        "Selected tree:".bright_green(),
-                        tree.bright_cyan().bold()
-                    );
-                }
-                Err(e) => {
-                    println!(
-                        "{} {} {} {}",
-                        "✗".bright_red().bold(),
-                        "Failed to select tree".bright_red(),
-                        tree.bright_cyan().bold(),
-                        e.to_string().red()
-                    );
+            Command::Range { start, end, page } => {
+                let codec = &viewer.codecs().key;
+                let scan = Scan::Range {
+                    start: codec.parse(start)?,
+                    end: codec.parse(end)?,
+                };
+                viewer
+                    .scan(scan, page.or_limit(default_limit), observer)?
+                    .into()
+            }
+            // A prefix of a typed key is raw bytes, e.g. `u32:7` for `tuple(u32, u64)`
+            Command::Prefix { prefix, page } => {
+                let scan = Scan::Prefix(bytes::parse_literal(prefix)?);
+                viewer
+                    .scan(scan, page.or_limit(default_limit), observer)?
+                    .into()
+            }
+            Command::Head { count } => {
+                let page = Page {
+                    limit: Some(*count),
+                    ..Page::default()
+                };
+                viewer
+                    .scan(Scan::Prefix(Vec::new()), page, observer)?
+                    .into()
+            }
+            Command::Tail { count } => CommandOutput::Keys(viewer.tail(*count)?.into()),
+            Command::More { limit } => viewer.scan_more(*limit, observer)?.into(),
+            Command::Get {
+                key,
+                bytes,
+                width,
+                decoder,
+            } => {
+                let info = viewer.get_key(viewer.codecs().key.parse(key)?)?;
+                if let Some(decoder) = decoder {
+                    let value = decode::decode(&info.value, *decoder)?;
+                    CommandOutput::Decoded(DecodedValue {
+                        info,
+                        decoder: *decoder,
+                        value,
+                    })
+                } else if bytes.is_none() && width.is_none() {
+                    CommandOutput::KeyInfo(info)
+                } else {
+                    CommandOutput::Bytes(ByteView {
+                        info,
+                        range: bytes.unwrap_or_default(),
+                        width: width.unwrap_or(hexdump::DEFAULT_WIDTH),
+                    })
                 }
-            },
-            Command::Unselect => match viewer.unselect_tree() {
-                Ok(was_selected) => {
-                    if was_selected {
-                        println!(
-                            "{} {}",
-                            "✓".bright_green().bold(),
-                            "Tree unselected. Now working with the default tree.".bright_green()
-                        );
-                    } else {
-                        println!(
-                            "{} {}",
-                            "!".bright_yellow().bold(),
-                            "No tree was previously selected.".bright_yellow()
-                        );
+            }
+            Command::Set {
+                key,
+                value,
+                condition,
+            } => {
+                let expected = match condition {
+                    None => None,
+                    Some(Condition::Absent) => Some(None),
+                    Some(Condition::Equals(expected)) => {
+                        Some(Some(bytes::parse_literal(expected)?))
                     }
+                };
+                self.set(viewer, key, value, expected)?
+            }
+            Command::Cas {
+                key,
+                expected,
+                value,
+            } => {
+                let expected = bytes::parse_literal(expected)?;
+                self.set(viewer, key, value, Some(Some(expected)))?
+            }
+            Command::Delete { key } => {
+                let key = viewer.codecs().key.parse(key)?;
+                CommandOutput::Ack(Ack {
+                    command: self.name(),
+                    target: viewer.codecs().key.display(&key),
+                    value: None,
+                    changed: viewer.delete_key(&key)?,
+                    staged: viewer.staged().is_some(),
+                })
+            }
+            Command::Copy {
+                mode,
+                source,
+                target,
+                force,
+            } => self.copy(viewer, *mode, source, target, *force, observer)?,
+            Command::Tree { action, yes } => self.change_tree(viewer, action, *yes)?,
+            Command::DeleteMatching {
+                selection,
+                yes,
+                dry_run,
+            } => {
+                let keys = viewer.matching_keys(&selection.scan(viewer)?, observer)?;
+                if !dry_run && !yes && !keys.is_empty() {
+                    return Err(SledoViewError::ConfirmationRequired { count: keys.len() }.into());
                 }
-                Err(e) => {
-                    println!(
-                        "{} {} {}",
-                        "✗".bright_red().bold(),
-                        "Failed to unselect tree:".bright_red(),
-                        e.to_string().red()
-                    );
+                let deleted = !dry_run;
+                if deleted {
+                    viewer.delete_keys(&keys)?;
                 }
-            },
-            Command::Help => {
-                print_help();
+                CommandOutput::Deletion(Deletion {
+                    keys,
+                    codecs: viewer.codecs().clone(),
+                    deleted,
+                    staged: viewer.staged().is_some(),
+                })
+            }
+            Command::Search {
+                pattern,
+                is_regex,
+                filter,
+                trees,
+                page,
+            } => {
+                let scan = Scan::Values {
+                    pattern: pattern_bytes(pattern, *is_regex)?,
+                    is_regex: *is_regex,
+                    filter: filter.clone(),
+                };
+                let page = page.or_limit(default_limit);
+                scan_in(viewer, trees.as_deref(), scan, page, observer)?.into()
             }
-            Command::Exit => {
-                println!("{}", "Goodbye!".bright_green());
+            Command::Query {
+                query,
+                select,
+                filter,
+                trees,
+                page,
+            } => {
+                let scan = Scan::Query {
+                    query: query.clone(),
+                    select: select
+                        .iter()
+                        .map(|path| query::Path::parse(path))
+                        .collect::<Result<_, _>>()?,
+                    filter: filter.clone(),
+                };
+                let page = page.or_limit(default_limit);
+                scan_in(viewer, trees.as_deref(), scan, page, observer)?.into()
             }
+            Command::Sql { statement } => {
+                CommandOutput::Rows(sql::execute(viewer, statement, observer)?)
+            }
+            Command::Trees {
+                pattern,
+                is_regex,
+                page,
+            } => {
+                let scan = Scan::Trees {
+                    pattern: pattern_bytes(pattern, *is_regex)?,
+                    is_regex: *is_regex,
+                };
+                viewer
+                    .scan(scan, page.or_limit(default_limit), observer)?
+                    .into()
+            }
+            Command::Select { tree, create } => {
+                let tree = bytes::parse_literal(tree)?;
+                if *create {
+                    viewer.create_tree(&tree)?;
+                }
+                viewer.select_tree(&tree)?;
+                let codecs = viewer.codecs();
+                CommandOutput::Ack(Ack {
+                    command: self.name(),
+                    target: bytes::escape(&tree),
+                    value: (!codecs.is_default()).then(|| codecs.to_string()),
+                    changed: true,
+                    staged: false,
+                })
+            }
+            Command::Unselect => {
+                let target = viewer
+                    .get_selected_tree()
+                    .map(bytes::escape)
+                    .unwrap_or_default();
+                CommandOutput::Ack(Ack {
+                    command: self.name(),
+                    target,
+                    value: None,
+                    changed: viewer.unselect_tree()?,
+                    staged: false,
+                })
+            }
+            Command::Where { key } => CommandOutput::Located(Located {
+                key: key.clone(),
+                entries: viewer.locate(key)?,
+            }),
+            Command::Source {
+                path,
+                continue_on_error,
+            } => {
+                script::run_file(viewer, path, *continue_on_error, OutputFormat::Text)?;
+                CommandOutput::Empty
+            }
+            Command::Format {
+                format: Some(format),
+            } => CommandOutput::FormatChanged(*format),
+            Command::Format { format: None } => CommandOutput::CurrentFormat(OutputFormat::Text),
+            Command::Begin => {
+                viewer.begin()?;
+                CommandOutput::Ack(Ack {
+                    command: self.name(),
+                    target: String::new(),
+                    value: None,
+                    changed: true,
+                    staged: true,
+                })
+            }
+            Command::Commit => {
+                let count = viewer.commit()?;
+                CommandOutput::Ack(Ack {
+                    command: self.name(),
+                    target: count.to_string(),
+                    value: None,
+                    changed: count > 0,
+                    staged: false,
+                })
+            }
+            Command::Rollback => {
+                let count = viewer.rollback()?;
+                CommandOutput::Ack(Ack {
+                    command: self.name(),
+                    target: count.to_string(),
+                    value: None,
+                    changed: count > 0,
+                    staged: false,
+                })
+            }
+            Command::Status => CommandOutput::Status(viewer.staged().map(<[_]>::to_vec)),
+            Command::Help => CommandOutput::Help,
+            Command::Exit => CommandOutput::Empty,
+        };
+        Ok(output)
+    }
+}
+
+/// Glob patterns are byte literals; regexes are passed through for the regex
+/// engine to interpret its own escapes
+fn pattern_bytes(pattern: &str, is_regex: bool) -> Result<Vec<u8>> {
+    if is_regex {
+        Ok(pattern.as_bytes().to_vec())
+    } else {
+        Ok(bytes::parse_literal(pattern)?)
+    }
+}
+
+/// Scan the current tree, or every tree matching the glob `trees` if given
+fn scan_in(
+    viewer: &mut SledViewer,
+    trees: Option<&str>,
+    scan: Scan,
+    page: Page,
+    observer: &mut dyn ScanObserver,
+) -> Result<ScanPage> {
+    match trees {
+        Some(trees) => viewer.scan_trees(&bytes::parse_literal(trees)?, scan, page, observer),
+        None => viewer.scan(scan, page, observer),
+    }
+}
+
+/// Execute a command and print its result in `format`.
+///
+/// Text listings are printed entry by entry as the scan finds them, with the
+/// scan's progress on standard error. When the interactive session has
+/// installed its Ctrl-C handler, Ctrl-C cancels the scan.
+pub fn execute_and_print(
+    command: &Command,
+    viewer: &mut SledViewer,
+    format: OutputFormat,
+) -> Result<()> {
+    interrupt::clear();
+    if let Command::DeleteMatching {
+        selection,
+        yes: false,
+        dry_run: false,
+    } = command
+    {
+        return delete_confirmed(selection, viewer, format);
+    }
+    if let Command::Tree { action, yes: false } = command {
+        if action.confirmed_as().is_some() {
+            return tree_confirmed(action, viewer, format);
         }
-        Ok(())
     }
+    if !format.is_text() {
+        render::print(&command.execute_for(viewer, format)?, format);
+        return Ok(());
+    }
+
+    let mut stream = render::TextStream::new();
+    let output = command.execute_paged(viewer, Some(render::TEXT_PAGE_SIZE), &mut stream)?;
+    stream.finish(&output);
+    Ok(())
 }
 
-fn print_key_info(info: &KeyInfo) {
-    println!();
-    println!("{}", "═".repeat(50).bright_cyan());
-    println!(
-        "{} {}",
-        "Key:".bright_blue().bold(),
-        info.key.bright_cyan().bold()
-    );
-    println!(
-        "{} {} bytes",
-        "Size:".bright_blue().bold(),
-        info.size.to_string().bright_yellow()
-    );
-    println!(
-        "{} {}",
-        "UTF-8:".bright_blue().bold(),
-        if info.is_utf8 {
-            "Yes".bright_green()
-        } else {
-            "No".bright_red()
+/// List the keys a bulk delete would remove and delete exactly those once
+/// the user confirms. Without a terminal to ask on, `--yes` is required.
+fn delete_confirmed(
+    selection: &KeySelection,
+    viewer: &mut SledViewer,
+    format: OutputFormat,
+) -> Result<()> {
+    // The preview is a dry run, which a read-only viewer allows
+    if viewer.is_read_only() {
+        return Err(SledoViewError::ReadOnly {
+            command: "delete".to_string(),
+        }
+        .into());
+    }
+    let preview = Command::DeleteMatching {
+        selection: selection.clone(),
+        yes: false,
+        dry_run: true,
+    };
+    let mut stream = render::TextStream::new();
+    let CommandOutput::Deletion(mut deletion) = preview.execute_paged(viewer, None, &mut stream)?
+    else {
+        unreachable!("a bulk delete returns a deletion");
+    };
+    drop(stream);
+    render::print(&CommandOutput::Deletion(deletion.clone()), format);
+    if deletion.keys.is_empty() {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(SledoViewError::ConfirmationRequired {
+            count: deletion.keys.len(),
         }
-    );
-    println!("{}", "Value:".bright_blue().bold());
-    println!("{}", "─".repeat(50).bright_black());
+        .into());
+    }
+    if !confirm(&format!("Delete {} keys?", deletion.keys.len()))? {
+        eprintln!("{}", "Cancelled, no keys were deleted.".yellow());
+        return Ok(());
+    }
+    viewer.delete_keys(&deletion.keys)?;
+    deletion.deleted = true;
+    deletion.staged = viewer.staged().is_some();
+    render::print(&CommandOutput::Deletion(deletion), format);
+    Ok(())
+}
 
-    if info.value.len() > 1000 {
-        println!("{}", format!("{}...", &info.value[..1000]).bright_white());
-        println!(
-            "{}",
-            format!(
-                "(truncated, showing first 1000 characters of {})",
-                info.value.len()
-            )
-            .bright_black()
-        );
-    } else {
-        println!("{}", info.value.bright_white());
+/// Change a tree once the user confirms, after checking that the change
+/// can be made. Without a terminal to ask on, `--yes` is required.
+fn tree_confirmed(
+    action: &TreeAction,
+    viewer: &mut SledViewer,
+    format: OutputFormat,
+) -> Result<()> {
+    let command = Command::Tree {
+        action: action.clone(),
+        yes: true,
+    };
+    if viewer.is_read_only() {
+        return Err(SledoViewError::ReadOnly {
+            command: command.name().to_string(),
+        }
+        .into());
     }
+    let tree = bytes::parse_literal(action.tree())?;
+    let Some((source, _)) = viewer.existing_tree(&tree)? else {
+        return Err(SledoViewError::TreeNotFound {
+            tree: bytes::escape(&tree),
+        }
+        .into());
+    };
+    if let Some(to) = action.target() {
+        let to = bytes::parse_literal(to)?;
+        if viewer.tree_exists(&to) {
+            return Err(SledoViewError::TreeExists {
+                tree: bytes::escape(&to),
+            }
+            .into());
+        }
+    }
+    if !io::stdin().is_terminal() {
+        return Err(SledoViewError::TreeConfirmationRequired {
+            action: action.confirmed_as().unwrap_or_default().to_string(),
+            tree: bytes::escape(&tree),
+        }
+        .into());
+    }
+    if !confirm(&action.question(source.len()))? {
+        eprintln!("{}", "Cancelled, the tree was not changed.".yellow());
+        return Ok(());
+    }
+    render::print(&command.execute_for(viewer, format)?, format);
+    Ok(())
+}
 
-    println!("{}", "═".repeat(50).bright_cyan());
-    println!();
+/// Ask a yes/no question on the terminal; anything but `y` or `yes` is no
+fn confirm(question: &str) -> Result<bool> {
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
-fn truncate_value(value: &str, max_len: usize) -> String {
-    if value.len() <= max_len {
-        value.to_string()
-    } else {
-        format!("{}...", &value[..max_len])
+/// Warn that the writes staged in a transaction are lost, when a session
+/// ends without `commit`
+pub fn warn_uncommitted(viewer: &SledViewer) {
+    if let Some(staged) = viewer.staged().filter(|staged| !staged.is_empty()) {
+        eprintln!(
+            "{} {}",
+            "Warning:".bright_yellow().bold(),
+            format!(
+                "discarded {} staged write(s) that were not committed",
+                staged.len()
+            )
+            .yellow()
+        );
     }
 }
 
-fn print_help() {
-    println!();
-    println!("{}", "Available Commands:".bright_cyan().bold());
-    println!("{}", "═".repeat(50).bright_cyan());
-
-    println!(
-        "{:<25} Show total number of records",
-        "count".bright_green().bold()
-    );
-    println!(
-        "{:<25} List keys matching pattern (default: *)",
-        "list [pattern]".bright_green().bold()
-    );
-    println!(
-        "{:<25} List keys matching regex pattern",
-        "list regex <regex>".bright_green().bold()
-    );
-    println!(
-        "{:<25} Get value and info for a specific key",
-        "get <key>".bright_green().bold()
-    );
-    println!(
-        "{:<25} Set/update a key-value pair",
-        "set <key> <value>".bright_green().bold()
-    );
-    println!("{:<25} Delete a key", "delete <key>".bright_green().bold());
-    println!(
-        "{:<25} Search values matching pattern",
-        "search <pattern>".bright_green().bold()
-    );
-    println!(
-        "{:<25} Search values matching regex pattern",
-        "search regex <regex>".bright_green().bold()
-    );
-    println!(
-        "{:<25} Show this help message",
-        "help".bright_green().bold()
-    );
-    println!();
-    println!("{}", "Tree Management:".bright_blue().bold());
-    println!(
-        "{:<25} List all trees in the database",
-        "trees".bright_green().bold()
-    );
-    println!(
-        "{:<25} List trees matching glob pattern",
-        "trees <pattern>".bright_green().bold()
-    );
-    println!(
-        "{:<25} List trees matching regex pattern",
-        "trees regex <pattern>".bright_green().bold()
-    );
-    println!(
-        "{:<25} Select a tree to work with",
-        "select <tree>".bright_green().bold()
-    );
-    println!(
-        "{:<25} Unselect current tree (return to default)",
-        "unselect".bright_green().bold()
-    );
-
-    println!();
-    println!("{}", "Tree Usage:".bright_blue().bold());
-    println!(
-        "  {} Trees provide data isolation - keys in different trees are separate",
-        "•".bright_blue()
-    );
-    println!(
-        "  {} When a tree is selected, the prompt shows: {}",
-        "•".bright_blue(),
-        "[tree_name]>".bright_cyan()
-    );
-    println!(
-        "  {} All CRUD operations work on the selected tree",
-        "•".bright_blue()
-    );
-    println!(
-        "  {} Use 'unselect' to return to the default tree",
-        "•".bright_blue()
-    );
-
-    println!();
-    println!("{}", "Advanced Usage:".bright_blue().bold());
-    println!(
-        "{:<25} Show key completions for a command",
-        "complete <cmd>".bright_green().bold()
-    );
-    println!("{:<25} Exit the application", "exit".bright_green().bold());
-
-    println!();
-    println!("{}", "Examples:".bright_blue().bold());
-    println!("  {} {}", ">".bright_black(), "list user_*".bright_white());
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "list regex user_[0-9]+".bright_white()
-    );
-    println!("  {} {}", ">".bright_black(), "get user_123".bright_white());
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "set user_123 \"John Doe\"".bright_white()
-    );
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "set \"user name\" value".bright_white()
-    );
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "delete user_123".bright_white()
-    );
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "search *@example.com".bright_white()
-    );
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "search regex \\d{4}-\\d{2}-\\d{2}".bright_white()
-    );
-    println!("  {} {}", ">".bright_black(), "trees".bright_white());
-    println!("  {} {}", ">".bright_black(), "trees *_data".bright_white());
-    println!(
-        "  {} {}",
-        ">".bright_black(),
-        "select settings".bright_white()
-    );
-    println!(
-        "  {} {}",
-        "[settings]>".bright_white(),
-        "list".bright_white()
-    );
-    println!(
-        "  {} {}",
-        "[settings]>".bright_white(),
-        "unselect".bright_white()
-    );
-
-    println!();
-    println!("{}", "Note:".bright_blue().bold());
-    println!(
-        "  {} Use quotes for keys/values with spaces: {} or {}",
-        "•".bright_blue(),
-        "\"key name\"".bright_yellow(),
-        "\"value with spaces\"".bright_yellow()
-    );
-    println!(
-        "  {} Escape quotes in values: {} → {}",
-        "•".bright_blue(),
-        "\"He said \\\"hello\\\"\"".bright_yellow(),
-        "He said \"hello\"".bright_white()
-    );
-    println!("  {} Keys are auto-completed with TAB", "•".bright_blue());
-    println!();
+/// Execute a parsed line in a session and print its result.
+///
+/// The result is printed in `format_override` if given, otherwise in
+/// `session_format`. A `format <name>` command changes `session_format` for
+/// the commands that follow, and `source` runs its script in the current format.
+pub fn execute_in_session(
+    command: &Command,
+    format_override: Option<OutputFormat>,
+    viewer: &mut SledViewer,
+    session_format: &mut OutputFormat,
+) -> Result<()> {
+    let format = format_override.unwrap_or(*session_format);
+
+    let (output, format) = match command {
+        Command::Format {
+            format: Some(new_format),
+        } => {
+            *session_format = *new_format;
+            (CommandOutput::FormatChanged(*new_format), *new_format)
+        }
+        Command::Format { format: None } => (CommandOutput::CurrentFormat(format), format),
+        Command::Source {
+            path,
+            continue_on_error,
+        } => {
+            script::run_file(viewer, path, *continue_on_error, format)?;
+            (CommandOutput::Empty, format)
+        }
+        _ => return execute_and_print(command, viewer, format),
+    };
+
+    render::print(&output, format);
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::page::Sort;
 
     #[test]
     fn test_command_parse_count() {
@@ -694,44 +1653,313 @@ mod tests {
     fn test_command_parse_list() {
         let cmd = Command::parse("list");
         assert!(
-            matches!(cmd, Some(Command::List { pattern, is_regex }) if pattern == "*" && !is_regex)
+            matches!(cmd, Some(Command::List { pattern, is_regex, .. }) if pattern == "*" && !is_regex)
         );
 
         let cmd = Command::parse("list test*");
         assert!(
-            matches!(cmd, Some(Command::List { pattern, is_regex }) if pattern == "test*" && !is_regex)
+            matches!(cmd, Some(Command::List { pattern, is_regex, .. }) if pattern == "test*" && !is_regex)
         );
 
         let cmd = Command::parse("list regex test.*");
         assert!(
-            matches!(cmd, Some(Command::List { pattern, is_regex }) if pattern == "test.*" && is_regex)
+            matches!(cmd, Some(Command::List { pattern, is_regex, .. }) if pattern == "test.*" && is_regex)
+        );
+    }
+
+    #[test]
+    fn test_command_parse_range_and_prefix() {
+        let cmd = Command::parse("range user_1 user_5");
+        assert!(
+            matches!(cmd, Some(Command::Range { start, end, .. }) if start == "user_1" && end == "user_5")
+        );
+        assert!(Command::parse("range user_1").is_none());
+        assert!(Command::parse("range a b c").is_none());
+
+        let cmd = Command::parse("prefix \"user \"");
+        assert!(matches!(cmd, Some(Command::Prefix { prefix, .. }) if prefix == "user "));
+        assert!(Command::parse("prefix").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_across_trees() {
+        let cmd = Command::parse("list user_* --all-trees --limit 5");
+        assert!(matches!(
+            cmd,
+            Some(Command::List { pattern, trees: Some(trees), page: Page { limit: Some(5), .. }, .. })
+                if pattern == "user_*" && trees == "*"
+        ));
+        let cmd = Command::parse("search regex ^id --trees \"users_*\"");
+        assert!(matches!(
+            cmd,
+            Some(Command::Search { pattern, is_regex: true, trees: Some(trees), .. })
+                if pattern == "^id" && trees == "users_*"
+        ));
+        assert!(matches!(
+            Command::parse("list"),
+            Some(Command::List { trees: None, .. })
+        ));
+        assert!(Command::parse("search x --trees").is_none());
+
+        assert!(
+            matches!(Command::parse("where user_1"), Some(Command::Where { key }) if key == "user_1")
+        );
+        assert!(Command::parse("where").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_filters() {
+        let cmd = Command::parse("search regex ^a -i --key user_* --max-size 4k --text");
+        let expected = Filter {
+            key: Some("user_*".to_string()),
+            ignore_case: true,
+            max_size: Some(4096),
+            kind: Some(ValueKind::Text),
+            ..Filter::default()
+        };
+        assert!(matches!(
+            cmd,
+            Some(Command::Search { pattern, is_regex: true, filter, .. })
+                if pattern == "^a" && filter == expected
+        ));
+
+        let cmd = Command::parse("list --empty --value x --all-trees");
+        assert!(matches!(
+            cmd,
+            Some(Command::List { pattern, filter, trees: Some(_), .. })
+                if pattern == "*" && filter.empty && filter.value.as_deref() == Some("x")
+        ));
+
+        assert!(Command::parse("list --text --binary").is_none());
+        assert!(Command::parse("list --min-size lots").is_none());
+        assert!(Command::parse("search x --key").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_query() {
+        let cmd = Command::parse(
+            "query $.status == 'failed' && $.retries > 3 --select $.status,$.retries --key job_*",
         );
+        assert!(matches!(
+            cmd,
+            Some(Command::Query { query, select, filter, trees: None, .. })
+                if query == "$.status == 'failed' && $.retries > 3"
+                    && select == ["$.status", "$.retries"]
+                    && filter.key.as_deref() == Some("job_*")
+        ));
+
+        let cmd = Command::parse("query $.ok --all-trees --limit 5");
+        assert!(matches!(
+            cmd,
+            Some(Command::Query { query, select, trees: Some(_), page: Page { limit: Some(5), .. }, .. })
+                if query == "$.ok" && select.is_empty()
+        ));
+
+        // Strings keep their double quotes, and one quoted query is unquoted
+        let cmd = Command::parse("query $.status == \"failed\" && $.retries > 3 --key \"job_*\"");
+        assert!(matches!(
+            cmd,
+            Some(Command::Query { query, filter, .. })
+                if query == "$.status == \"failed\" && $.retries > 3"
+                    && filter.key.as_deref() == Some("job_*")
+        ));
+        let cmd = Command::parse("query \"$.status == 'failed'\" --limit 2");
+        assert!(matches!(
+            cmd,
+            Some(Command::Query { query, .. }) if query == "$.status == 'failed'"
+        ));
+
+        assert!(Command::parse("query").is_none());
+        assert!(Command::parse("query --select $.a").is_none());
+        assert!(Command::parse("query $.a --select").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_transaction() {
+        assert!(matches!(Command::parse("begin"), Some(Command::Begin)));
+        assert!(matches!(Command::parse("commit"), Some(Command::Commit)));
+        assert!(matches!(
+            Command::parse("rollback"),
+            Some(Command::Rollback)
+        ));
+        assert!(matches!(Command::parse("status"), Some(Command::Status)));
+        assert!(Command::Begin.is_write());
+        assert!(!Command::Status.is_write());
+    }
+
+    #[test]
+    fn test_command_parse_sql() {
+        let cmd =
+            Command::parse("sql SELECT key, len(value) FROM sessions WHERE key LIKE 'user_%'");
+        assert!(matches!(
+            cmd,
+            Some(Command::Sql { statement })
+                if statement == "SELECT key, len(value) FROM sessions WHERE key LIKE 'user_%'"
+        ));
+        let cmd = Command::parse("sql SELECT key FROM \"old jobs\" WHERE status = 'failed'");
+        assert!(matches!(
+            cmd,
+            Some(Command::Sql { statement })
+                if statement == "SELECT key FROM \"old jobs\" WHERE status = 'failed'"
+        ));
+        assert!(Command::parse("sql").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_paging() {
+        let cmd = Command::parse("list user_* --limit 20 --offset 40");
+        assert!(matches!(
+            cmd,
+            Some(Command::List { pattern, page: Page { offset: 40, limit: Some(20), sort: Sort::Bytes, reverse: false }, .. })
+                if pattern == "user_*"
+        ));
+        let cmd = Command::parse("trees --limit 5");
+        assert!(matches!(
+            cmd,
+            Some(Command::Trees {
+                page: Page {
+                    offset: 0,
+                    limit: Some(5),
+                    sort: Sort::Bytes,
+                    reverse: false
+                },
+                ..
+            })
+        ));
+        assert!(Command::parse("list --limit 0").is_none());
+        assert!(Command::parse("search x --offset").is_none());
+
+        assert!(matches!(
+            Command::parse("more"),
+            Some(Command::More { limit: None })
+        ));
+        assert!(matches!(
+            Command::parse("next 5"),
+            Some(Command::More { limit: Some(5) })
+        ));
+        assert!(matches!(
+            Command::parse("head"),
+            Some(Command::Head { count: 10 })
+        ));
+        assert!(matches!(
+            Command::parse("tail 3"),
+            Some(Command::Tail { count: 3 })
+        ));
+        assert!(Command::parse("tail 0").is_none());
+
+        let cmd = Command::parse("search *@x --sort size --reverse --limit 3");
+        assert!(matches!(
+            cmd,
+            Some(Command::Search {
+                page: Page {
+                    limit: Some(3),
+                    sort: Sort::Size,
+                    reverse: true,
+                    ..
+                },
+                ..
+            })
+        ));
+        assert!(Command::parse("list --sort sideways").is_none());
+        assert!(Command::parse("list --sort").is_none());
+    }
+
+    #[test]
+    fn test_execute_pages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for i in 0..60 {
+            viewer.set_key(format!("key_{i:02}"), "v").unwrap();
+        }
+        let keys = |output: CommandOutput| match output {
+            CommandOutput::Keys(keys) => keys,
+            other => panic!("unexpected output: {:?}", other),
+        };
+
+        // Text output pages by default, record formats get everything
+        let list = Command::parse("list").unwrap();
+        let page = keys(list.execute_for(&mut viewer, OutputFormat::Text).unwrap());
+        assert_eq!((page.len(), page.more), (render::TEXT_PAGE_SIZE, true));
+        let page = keys(Command::More { limit: None }.execute(&mut viewer).unwrap());
+        assert_eq!((page.start, page.len(), page.more), (50, 10, false));
+        assert!(Command::More { limit: None }.execute(&mut viewer).is_err());
+
+        let page = keys(list.execute_for(&mut viewer, OutputFormat::Json).unwrap());
+        assert!(page.is_complete());
+
+        let page = keys(
+            Command::parse("head 2")
+                .unwrap()
+                .execute(&mut viewer)
+                .unwrap(),
+        );
+        assert_eq!(page[1].key, b"key_01");
+        let page = keys(
+            Command::parse("more 3")
+                .unwrap()
+                .execute(&mut viewer)
+                .unwrap(),
+        );
+        assert_eq!(page[0].key, b"key_02");
+        assert_eq!(page.len(), 3);
+
+        let page = keys(
+            Command::parse("tail 2")
+                .unwrap()
+                .execute(&mut viewer)
+                .unwrap(),
+        );
+        assert_eq!(page[0].key, b"key_58");
+        assert_eq!(page[1].key, b"key_59");
     }
 
     #[test]
     fn test_command_parse_get() {
         let cmd = Command::parse("get test_key");
-        assert!(matches!(cmd, Some(Command::Get { key }) if key == "test_key"));
+        assert!(matches!(cmd, Some(Command::Get { key, .. }) if key == "test_key"));
 
         let cmd = Command::parse("get \"key with spaces\"");
-        assert!(matches!(cmd, Some(Command::Get { key }) if key == "key with spaces"));
+        assert!(matches!(cmd, Some(Command::Get { key, .. }) if key == "key with spaces"));
+
+        let cmd = Command::parse("get blob --bytes 16..32 --width 8");
+        assert!(matches!(
+            cmd,
+            Some(Command::Get { key, bytes: Some(range), width: Some(8), .. })
+                if key == "blob" && range == ByteRange { start: 16, end: Some(32) }
+        ));
+
+        assert!(Command::parse("get blob --bytes 32..16").is_none());
+        assert!(Command::parse("get blob --width 0").is_none());
+        assert!(Command::parse("get blob --width").is_none());
+        assert!(Command::parse("get blob extra").is_none());
+
+        let cmd = Command::parse("get blob --as bincode:u64");
+        assert!(matches!(
+            cmd,
+            Some(Command::Get {
+                decoder: Some(Decoder::Bincode(_)),
+                ..
+            })
+        ));
+        assert!(Command::parse("get blob --as xml").is_none());
+        assert!(Command::parse("get blob --as cbor --bytes 0..4").is_none());
     }
 
     #[test]
     fn test_command_parse_set() {
         let cmd = Command::parse("set key value");
         assert!(
-            matches!(cmd, Some(Command::Set { key, value }) if key == "key" && value == "value")
+            matches!(cmd, Some(Command::Set { key, value, condition: None }) if key == "key" && value == "value")
         );
 
         let cmd = Command::parse("set \"key with spaces\" \"value with spaces\"");
         assert!(
-            matches!(cmd, Some(Command::Set { key, value }) if key == "key with spaces" && value == "value with spaces")
+            matches!(cmd, Some(Command::Set { key, value, condition: None }) if key == "key with spaces" && value == "value with spaces")
         );
 
         let cmd = Command::parse("set key \"value with \\\"quotes\\\"\"");
         assert!(
-            matches!(cmd, Some(Command::Set { key, value }) if key == "key" && value == "value with \"quotes\"")
+            matches!(cmd, Some(Command::Set { key, value, condition: None }) if key == "key" && value == "value with \"quotes\"")
         );
 
         // Test incomplete set command
@@ -742,6 +1970,104 @@ mod tests {
         assert!(cmd.is_none());
     }
 
+    #[test]
+    fn test_command_parse_copy() {
+        let cmd = Command::parse("mv user_1 archive:user_1");
+        assert!(matches!(
+            cmd,
+            Some(Command::Copy { mode: CopyMode::Move, source: CopySource::Key(key), target, force: false })
+                if key == "user_1" && target == "archive:user_1"
+        ));
+
+        let cmd = Command::parse("cp --pattern session_* old:$1 --force");
+        assert!(matches!(
+            cmd,
+            Some(Command::Copy { mode: CopyMode::Copy, source: CopySource::Glob(glob), target, force: true })
+                if glob == "session_*" && target == "old:$1"
+        ));
+
+        let cmd = Command::parse("rename --regex ^a(.*) b$1");
+        assert!(matches!(
+            cmd,
+            Some(Command::Copy {
+                mode: CopyMode::Rename,
+                source: CopySource::Regex(_),
+                ..
+            })
+        ));
+
+        assert!(Command::parse("cp user_1").is_none());
+        assert!(Command::parse("mv a b c").is_none());
+        assert!(Command::parse("cp --pattern a* b c").is_none());
+        assert!(Command::parse("cp --pattern a* --regex b c").is_none());
+
+        assert_eq!(split_target("archive:user_1"), (Some("archive"), "user_1"));
+        assert_eq!(split_target(":user:1"), (None, "user:1"));
+        assert_eq!(split_target("u64:7"), (None, "u64:7"));
+    }
+
+    #[test]
+    fn test_command_parse_tree() {
+        let cmd = Command::parse("select sessions --create");
+        assert!(matches!(cmd, Some(Command::Select { tree, create: true }) if tree == "sessions"));
+
+        let cmd = Command::parse("tree drop sessions --yes");
+        assert!(matches!(
+            cmd,
+            Some(Command::Tree { action: TreeAction::Drop { tree }, yes: true }) if tree == "sessions"
+        ));
+        let cmd = Command::parse("tree copy sessions sessions_old");
+        assert!(matches!(
+            cmd,
+            Some(Command::Tree { action: TreeAction::Copy { tree, to }, yes: false })
+                if tree == "sessions" && to == "sessions_old"
+        ));
+        assert!(matches!(
+            Command::parse("tree create sessions").map(|cmd| cmd.is_write()),
+            Some(true)
+        ));
+
+        assert!(Command::parse("select --create").is_none());
+        assert!(Command::parse("tree").is_none());
+        assert!(Command::parse("tree drop").is_none());
+        assert!(Command::parse("tree rename a").is_none());
+        assert!(Command::parse("tree clear a b").is_none());
+        assert!(Command::parse("tree remove a").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_conditional_set() {
+        let cmd = Command::parse("set key value --if-absent");
+        assert!(matches!(
+            cmd,
+            Some(Command::Set {
+                condition: Some(Condition::Absent),
+                ..
+            })
+        ));
+
+        let cmd = Command::parse("set key new --if-equals \"old value\"");
+        assert!(matches!(
+            cmd,
+            Some(Command::Set { key, value, condition: Some(Condition::Equals(expected)) })
+                if key == "key" && value == "new" && expected == "old value"
+        ));
+
+        let cmd = Command::parse("cas key old new");
+        assert!(matches!(
+            cmd,
+            Some(Command::Cas { key, expected, value })
+                if key == "key" && expected == "old" && value == "new"
+        ));
+        assert!(Command::parse("cas key old new").unwrap().is_write());
+
+        assert!(Command::parse("set key value --if-equals").is_none());
+        assert!(Command::parse("set key value --if-absent --if-equals old").is_none());
+        assert!(Command::parse("set key value --force").is_none());
+        assert!(Command::parse("cas key old").is_none());
+        assert!(Command::parse("cas key old new extra").is_none());
+    }
+
     #[test]
     fn test_command_parse_delete() {
         let cmd = Command::parse("delete test_key");
@@ -753,6 +2079,32 @@ mod tests {
         let cmd = Command::parse("delete \"key with spaces\"");
         assert!(matches!(cmd, Some(Command::Delete { key }) if key == "key with spaces"));
 
+        let cmd = Command::parse("delete --pattern session_* --yes");
+        assert!(matches!(
+            cmd,
+            Some(Command::DeleteMatching { selection: KeySelection::Glob(glob), yes: true, dry_run: false })
+                if glob == "session_*"
+        ));
+
+        let cmd = Command::parse("delete --dry-run --range a m");
+        assert!(matches!(
+            cmd,
+            Some(Command::DeleteMatching { selection: KeySelection::Range { start, end }, yes: false, dry_run: true })
+                if start == "a" && end == "m"
+        ));
+
+        let cmd = Command::parse("del --regex ^tmp_");
+        assert!(matches!(
+            cmd,
+            Some(Command::DeleteMatching { selection: KeySelection::Regex(regex), .. }) if regex == "^tmp_"
+        ));
+
+        assert!(Command::parse("delete test_key --yes").is_none());
+        assert!(Command::parse("delete test_key --pattern a*").is_none());
+        assert!(Command::parse("delete --pattern a* --regex b").is_none());
+        assert!(Command::parse("delete --range a").is_none());
+        assert!(Command::parse("delete --yes").is_none());
+
         // Test incomplete delete command
         let cmd = Command::parse("delete");
         assert!(cmd.is_none());
@@ -762,12 +2114,12 @@ mod tests {
     fn test_command_parse_search() {
         let cmd = Command::parse("search *test*");
         assert!(
-            matches!(cmd, Some(Command::Search { pattern, is_regex }) if pattern == "*test*" && !is_regex)
+            matches!(cmd, Some(Command::Search { pattern, is_regex, .. }) if pattern == "*test*" && !is_regex)
         );
 
         let cmd = Command::parse("search regex .*test.*");
         assert!(
-            matches!(cmd, Some(Command::Search { pattern, is_regex }) if pattern == ".*test.*" && is_regex)
+            matches!(cmd, Some(Command::Search { pattern, is_regex, .. }) if pattern == ".*test.*" && is_regex)
         );
     }
 
@@ -792,6 +2144,10 @@ mod tests {
         assert!(matches!(cmd, Some(Command::Exit)));
     }
 
+    fn parse_quoted_args(input: &str) -> Vec<String> {
+        split_args(input).into_iter().map(|arg| arg.value).collect()
+    }
+
     #[test]
     fn test_parse_quoted_args() {
         // Simple unquoted arguments
@@ -817,25 +2173,35 @@ mod tests {
         // Single word in quotes
         let args = parse_quoted_args("get \"key\"");
         assert_eq!(args, vec!["get", "key"]);
+
+        // Other escapes are kept for literal decoding
+        let args = parse_quoted_args("set \"\\x00\\xff\" a\\\\b");
+        assert_eq!(args, vec!["set", "\\x00\\xff", "a\\\\b"]);
+
+        // Arguments are also kept as typed
+        let raw: Vec<String> = split_args("query $.a==\"x y\"  \\\"b")
+            .into_iter()
+            .map(|arg| arg.raw)
+            .collect();
+        assert_eq!(raw, vec!["query", "$.a==", "\"x y\"", "\\\"b"]);
     }
 
     #[test]
     fn test_validate_key() {
         // Valid keys
-        assert!(validate_key("user_123").is_ok());
-        assert!(validate_key("config.database").is_ok());
-        assert!(validate_key("app:settings").is_ok());
-        assert!(validate_key("path/to/key").is_ok());
-        assert!(validate_key("key with spaces").is_ok());
+        assert!(validate_key(b"user_123").is_ok());
+        assert!(validate_key(b"config.database").is_ok());
+        assert!(validate_key(b"app:settings").is_ok());
+        assert!(validate_key(b"path/to/key").is_ok());
+        assert!(validate_key(b"key with spaces").is_ok());
+        assert!(validate_key(b"key@any#chars$").is_ok());
+        assert!(validate_key(&[0, 0, 0, 1, 0xff]).is_ok());
 
         // Invalid keys
-        assert!(validate_key("").is_err());
-        assert!(validate_key("key@invalid").is_err());
-        assert!(validate_key("key#invalid").is_err());
-        assert!(validate_key("key$invalid").is_err());
+        assert!(validate_key(b"").is_err());
 
         // Too long key
-        let long_key = "a".repeat(600);
+        let long_key = vec![b'a'; 600];
         assert!(validate_key(&long_key).is_err());
     }
 
@@ -861,23 +2227,23 @@ mod tests {
     fn test_command_parse_trees() {
         let cmd = Command::parse("trees");
         assert!(
-            matches!(cmd, Some(Command::Trees { pattern, is_regex }) if pattern == "*" && !is_regex)
+            matches!(cmd, Some(Command::Trees { pattern, is_regex, .. }) if pattern == "*" && !is_regex)
         );
 
         let cmd = Command::parse("trees my_tree_*");
         assert!(
-            matches!(cmd, Some(Command::Trees { pattern, is_regex }) if pattern == "my_tree_*" && !is_regex)
+            matches!(cmd, Some(Command::Trees { pattern, is_regex, .. }) if pattern == "my_tree_*" && !is_regex)
         );
 
         let cmd = Command::parse("trees regex my_tree_\\d+");
         assert!(
-            matches!(cmd, Some(Command::Trees { pattern, is_regex }) if pattern == "my_tree_d+" && is_regex)
+            matches!(cmd, Some(Command::Trees { pattern, is_regex, .. }) if pattern == "my_tree_\\d+" && is_regex)
         );
 
         // Test trees command with "regex" as pattern (valid)
         let cmd = Command::parse("trees regex");
         assert!(
-            matches!(cmd, Some(Command::Trees { pattern, is_regex }) if pattern == "regex" && !is_regex)
+            matches!(cmd, Some(Command::Trees { pattern, is_regex, .. }) if pattern == "regex" && !is_regex)
         );
 
         // Test trees command with too many args
@@ -888,10 +2254,12 @@ mod tests {
     #[test]
     fn test_command_parse_select() {
         let cmd = Command::parse("select my_tree");
-        assert!(matches!(cmd, Some(Command::Select { tree }) if tree == "my_tree"));
+        assert!(matches!(cmd, Some(Command::Select { tree, create: false }) if tree == "my_tree"));
 
         let cmd = Command::parse("select tree_with_underscore");
-        assert!(matches!(cmd, Some(Command::Select { tree }) if tree == "tree_with_underscore"));
+        assert!(
+            matches!(cmd, Some(Command::Select { tree, create: false }) if tree == "tree_with_underscore")
+        );
 
         // Test incomplete select command
         let cmd = Command::parse("select");
@@ -907,4 +2275,308 @@ mod tests {
         let cmd = Command::parse("unselect extra_arg");
         assert!(matches!(cmd, Some(Command::Unselect)));
     }
+
+    #[test]
+    fn test_read_only_refuses_writes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        {
+            let db = sled::open(temp_dir.path()).unwrap();
+            db.insert(b"key", b"value").unwrap();
+            db.flush().unwrap();
+        }
+        let mut viewer = SledViewer::new_read_only(temp_dir.path()).unwrap();
+
+        let err = Command::parse("set key other")
+            .unwrap()
+            .execute(&mut viewer);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("read-only mode, 'set'"));
+        let err = Command::parse("delete key").unwrap().execute(&mut viewer);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("read-only mode, 'delete'"));
+        let err = Command::parse("delete --pattern k* --yes")
+            .unwrap()
+            .execute(&mut viewer);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("read-only mode, 'delete'"));
+        // Without --yes the keys are previewed before asking; refuse before that
+        let err = execute_and_print(
+            &Command::parse("delete --pattern k*").unwrap(),
+            &mut viewer,
+            OutputFormat::Text,
+        );
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("read-only mode, 'delete'"));
+        assert_eq!(viewer.get_key("key").unwrap().value, b"value");
+
+        assert!(Command::parse("count")
+            .unwrap()
+            .execute(&mut viewer)
+            .is_ok());
+        assert!(Command::parse("get key")
+            .unwrap()
+            .execute(&mut viewer)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_execute_with_byte_literals() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut execute = |line: &str| Command::parse(line).unwrap().execute(&mut viewer);
+
+        execute("set 0x0000002a b64:3q2+7w==").unwrap();
+        execute("set \"\\x00\\x00\\x00\\x2b\" \"tab\\there\"").unwrap();
+
+        match execute("get \\x00\\x00\\x00*").unwrap() {
+            CommandOutput::KeyInfo(info) => {
+                assert_eq!(info.key, [0, 0, 0, 42]);
+                assert_eq!(info.value, [0xde, 0xad, 0xbe, 0xef]);
+            }
+            other => panic!("unexpected output: {:?}", other),
+        }
+        match execute("list \\x00\\x00*").unwrap() {
+            CommandOutput::Keys(keys) => assert_eq!(keys.len(), 2),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        match execute("search 0xdeadbeef").unwrap() {
+            CommandOutput::SearchResults(results) => assert_eq!(results[0].key, [0, 0, 0, 42]),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        match execute("search tab\\t*").unwrap() {
+            CommandOutput::SearchResults(results) => assert_eq!(results[0].key, [0, 0, 0, 43]),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        assert!(matches!(
+            execute("delete 0x0000002b").unwrap(),
+            CommandOutput::Ack(Ack { changed: true, .. })
+        ));
+
+        execute("select 0x7472ff --create").unwrap();
+        execute("set k v").unwrap();
+        execute("unselect").unwrap();
+        match execute("trees 0x7472ff").unwrap() {
+            CommandOutput::Trees(trees) => assert_eq!(trees.items, vec![vec![0x74, 0x72, 0xff]]),
+            other => panic!("unexpected output: {:?}", other),
+        }
+
+        let err = execute("get 0xabc").unwrap_err();
+        assert!(err.to_string().contains("odd number of hex digits"));
+    }
+
+    #[test]
+    fn test_execute_tree_commands() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut execute = |line: &str| Command::parse(line).unwrap().execute(&mut viewer);
+
+        let err = execute("select sessions").unwrap_err();
+        assert_eq!(err.to_string(), "Tree 'sessions' does not exist");
+        execute("select sessions --create").unwrap();
+        execute("set s1 a").unwrap();
+        execute("set s2 b").unwrap();
+        assert!(matches!(
+            execute("tree create sessions").unwrap(),
+            CommandOutput::Ack(Ack { changed: false, .. })
+        ));
+
+        // Everything but create needs --yes
+        let err = execute("tree drop sessions").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Dropping tree 'sessions' needs confirmation: add --yes"
+        );
+        execute("tree copy sessions backup --yes").unwrap();
+        let err = execute("tree copy sessions backup --yes").unwrap_err();
+        assert_eq!(err.to_string(), "Tree 'backup' already exists");
+
+        // The selection follows a renamed tree
+        execute("tree rename sessions live --yes").unwrap();
+        assert!(matches!(execute("count").unwrap(), CommandOutput::Count(2)));
+        assert!(matches!(
+            execute("tree clear backup --yes").unwrap(),
+            CommandOutput::Ack(Ack { value: Some(count), .. }) if count == "2"
+        ));
+        execute("tree drop live --yes").unwrap();
+        match execute("trees").unwrap() {
+            CommandOutput::Trees(trees) => assert_eq!(trees.items, vec![b"backup".to_vec()]),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        assert!(execute("tree drop __sled__default --yes").is_err());
+        assert!(execute("tree clear missing --yes").is_err());
+    }
+
+    #[test]
+    fn test_execute_copy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut execute = |line: &str| Command::parse(line).unwrap().execute(&mut viewer);
+        let value = |output: CommandOutput| match output {
+            CommandOutput::KeyInfo(info) => info.value,
+            other => panic!("unexpected output: {:?}", other),
+        };
+
+        execute("set blob 0x00ff80").unwrap();
+        execute("set session_1 a").unwrap();
+        execute("set session_2 b").unwrap();
+        execute("set user:1 c").unwrap();
+
+        // Values are copied as stored, to another tree with <tree>:<key>
+        assert!(execute("cp blob archive:blob_copy").is_err());
+        execute("tree create archive").unwrap();
+        execute("cp blob archive:blob_copy").unwrap();
+        execute("mv blob archive:").unwrap();
+        assert!(execute("get blob").is_err());
+        execute("select archive").unwrap();
+        assert_eq!(value(execute("get blob").unwrap()), [0x00, 0xff, 0x80]);
+        assert_eq!(value(execute("get blob_copy").unwrap()), [0x00, 0xff, 0x80]);
+        execute("unselect").unwrap();
+
+        // Targets that exist are only overwritten with --force
+        let err = execute("cp session_1 session_2").unwrap_err();
+        assert!(err.to_string().contains("expected no value, found 'b'"));
+        execute("cp session_1 session_2 --force").unwrap();
+        assert_eq!(value(execute("get session_2").unwrap()), b"a");
+
+        // Wildcards and groups are substituted in the target
+        execute("tree create old").unwrap();
+        match execute("mv --pattern session_* old:s$1").unwrap() {
+            CommandOutput::Copied(copied) => assert_eq!(
+                copied.pairs,
+                [
+                    (b"session_1".to_vec(), b"s1".to_vec()),
+                    (b"session_2".to_vec(), b"s2".to_vec())
+                ]
+            ),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        execute("select old").unwrap();
+        execute("rename --regex ^s(?<n>\\d)$ session:${n}").unwrap();
+        assert_eq!(value(execute("get session:2").unwrap()), b"a");
+        execute("unselect").unwrap();
+
+        // A target with a colon in the current tree
+        execute("cp user:1 :user:2").unwrap();
+        execute("rename user:2 user:3").unwrap();
+        assert_eq!(value(execute("get user:3").unwrap()), b"c");
+        assert!(execute("get user:2").is_err());
+    }
+
+    #[test]
+    fn test_execute_returns_output() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut execute = |line: &str| Command::parse(line).unwrap().execute(&mut viewer).unwrap();
+
+        assert_eq!(
+            execute("set user_1 Alice"),
+            CommandOutput::Ack(Ack {
+                command: "set",
+                target: "user_1".to_string(),
+                value: Some("Alice".to_string()),
+                changed: true,
+                staged: false,
+            })
+        );
+        assert_eq!(execute("count"), CommandOutput::Count(1));
+
+        match execute("get user_1") {
+            CommandOutput::KeyInfo(info) => assert_eq!(info.value, b"Alice"),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        match execute("list user_*") {
+            CommandOutput::Keys(keys) => assert_eq!(keys[0].key, b"user_1"),
+            other => panic!("unexpected output: {:?}", other),
+        }
+        match execute("search Ali*") {
+            CommandOutput::SearchResults(results) => assert_eq!(results.len(), 1),
+            other => panic!("unexpected output: {:?}", other),
+        }
+
+        assert!(matches!(
+            execute("delete missing"),
+            CommandOutput::Ack(Ack { changed: false, .. })
+        ));
+        assert!(matches!(
+            execute("unselect"),
+            CommandOutput::Ack(Ack { changed: false, .. })
+        ));
+        assert_eq!(execute("help"), CommandOutput::Help);
+    }
+
+    #[test]
+    fn test_command_parse_format() {
+        let cmd = Command::parse("format");
+        assert!(matches!(cmd, Some(Command::Format { format: None })));
+
+        let cmd = Command::parse("format csv");
+        assert!(matches!(
+            cmd,
+            Some(Command::Format {
+                format: Some(OutputFormat::Csv)
+            })
+        ));
+
+        assert!(Command::parse("format xml").is_none());
+    }
+
+    #[test]
+    fn test_command_parse_with_format() {
+        let parsed = Command::parse_with_format("list user_* --format json");
+        assert!(matches!(
+            parsed,
+            Some((Command::List { pattern, is_regex, .. }, Some(OutputFormat::Json))) if pattern == "user_*" && !is_regex
+        ));
+
+        let parsed = Command::parse_with_format("get --format tsv \"key name\"");
+        assert!(matches!(
+            parsed,
+            Some((Command::Get { key, .. }, Some(OutputFormat::Tsv))) if key == "key name"
+        ));
+
+        let parsed = Command::parse_with_format("count");
+        assert!(matches!(parsed, Some((Command::Count, None))));
+
+        assert!(Command::parse_with_format("count --format").is_none());
+        assert!(Command::parse_with_format("count --format xml").is_none());
+    }
+
+    #[test]
+    fn test_execute_in_session_updates_format() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut session_format = OutputFormat::Text;
+
+        let (command, format) = Command::parse_with_format("format ndjson").unwrap();
+        execute_in_session(&command, format, &mut viewer, &mut session_format).unwrap();
+        assert_eq!(session_format, OutputFormat::Ndjson);
+
+        let (command, format) = Command::parse_with_format("count --format csv").unwrap();
+        execute_in_session(&command, format, &mut viewer, &mut session_format).unwrap();
+        assert_eq!(session_format, OutputFormat::Ndjson);
+    }
+
+    #[test]
+    fn test_command_parse_source() {
+        let cmd = Command::parse("source fix.sled");
+        assert!(
+            matches!(cmd, Some(Command::Source { path, continue_on_error }) if path.as_os_str() == "fix.sled" && !continue_on_error)
+        );
+
+        let cmd = Command::parse("source \"my script.sled\" --continue");
+        assert!(
+            matches!(cmd, Some(Command::Source { path, continue_on_error }) if path.as_os_str() == "my script.sled" && continue_on_error)
+        );
+
+        assert!(Command::parse("source").is_none());
+        assert!(Command::parse("source fix.sled --bogus").is_none());
+    }
 }
@@ -1,308 +1,1211 @@
+use crate::bytes;
+use crate::codec::TreeCodecs;
+use crate::decode::{self, ContentType};
 use crate::error::SledoViewError;
+use crate::filter::Filter;
+use crate::page::{self, Listing, Page, Sort};
+use crate::profile::Profile;
+use crate::query::{self, Query};
 use anyhow::Result;
-use regex::Regex;
-use sled::{Db, Tree};
-use std::path::Path;
+use regex::bytes::{Regex, RegexBuilder};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::{Batch, Db, Transactional, Tree};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::ops::{Bound, Range};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Add a write to a list of staged writes, replacing the one for the same
+/// key. Only the first write's condition is on the committed value, so it
+/// is the one kept.
+fn merge_write(writes: &mut Vec<StagedWrite>, mut write: StagedWrite) {
+    let replaced = writes
+        .iter()
+        .position(|staged| staged.tree == write.tree && staged.key == write.key);
+    if let Some(index) = replaced {
+        write.expected = writes.remove(index).expected;
+    }
+    writes.push(write);
+}
+
+/// The error of a conditional write that found another value than expected
+fn conflict(
+    codecs: &TreeCodecs,
+    key: &[u8],
+    expected: Option<&[u8]>,
+    current: Option<&[u8]>,
+) -> SledoViewError {
+    let describe = |value: Option<&[u8]>| match value {
+        Some(value) => format!("'{}'", bytes::escape(value)),
+        None => "no value".to_string(),
+    };
+    SledoViewError::Conflict {
+        key: codecs.key.display(key),
+        expected: describe(expected),
+        current: describe(current),
+    }
+}
+
+/// Name SLED uses for the default tree in `tree_names`
+pub const DEFAULT_TREE_NAME: &[u8] = b"__sled__default";
+
+/// How long to keep retrying when the database lock is still held
+const LOCK_RETRY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Open a SLED database, waiting briefly if its file lock is still held.
+///
+/// SLED releases the lock from a background thread after the last handle is
+/// dropped, so reopening a database right after closing it can fail
+/// transiently. A lock held by another process is still reported once the
+/// timeout expires.
+pub fn open_db(path: &Path) -> sled::Result<Db> {
+    let started = Instant::now();
+    loop {
+        match sled::open(path) {
+            Err(sled::Error::Io(ref e))
+                if is_lock_error(e) && started.elapsed() < LOCK_RETRY_TIMEOUT =>
+            {
+                thread::sleep(Duration::from_millis(20));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Take a shared lock on the database file, waiting briefly if it is held as
+/// `open_db` does. SLED holds an exclusive lock on that file while the
+/// database is open, so this fails while a handle is open elsewhere, and keeps
+/// one from being opened while the returned file is kept. Nothing is written.
+///
+/// Returns `None` when there is no database file to lock.
+pub fn lock_shared(path: &Path) -> std::io::Result<Option<File>> {
+    let file = match File::open(path.join("db")) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let started = Instant::now();
+    loop {
+        match file.try_lock_shared() {
+            Ok(()) => return Ok(Some(file)),
+            Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_RETRY_TIMEOUT => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::WouldBlock,
+                    "could not acquire lock on the database file",
+                ))
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+}
+
+/// Copy a database directory into a new temporary directory, holding a shared
+/// lock so that no writer changes it meanwhile. Opening the copy cannot change
+/// the original: SLED rewrites its files even when only reading.
+fn snapshot(path: &Path) -> Result<TempDir> {
+    let _lock = lock_shared(path).map_err(|e| {
+        if is_lock_error(&e) {
+            SledoViewError::DatabaseLocked {
+                path: path.display().to_string(),
+            }
+        } else {
+            SledoViewError::Io(e)
+        }
+    })?;
+    let dir = tempfile::Builder::new().prefix("sledoview-").tempdir()?;
+    copy_dir(path, dir.path())?;
+    Ok(dir)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check from filesystem permissions whether the database files can be written.
+///
+/// Files are opened for writing without modifying them, so nothing is ever
+/// written to the database to find out.
+pub fn is_path_writable(path: &Path) -> bool {
+    let dir_writable = fs::metadata(path)
+        .map(|m| !m.permissions().readonly())
+        .unwrap_or(false);
+    if !dir_writable {
+        return false;
+    }
+
+    ["conf", "db"]
+        .iter()
+        .map(|name| path.join(name))
+        .filter(|file| file.exists())
+        .all(|file| OpenOptions::new().write(true).open(file).is_ok())
+}
+
+/// SLED reports a held lock as a generic IO error, so recognize it by message
+pub fn is_lock_error(err: &std::io::Error) -> bool {
+    err.to_string().starts_with("could not acquire lock")
+}
+
+/// A listing of the current tree (or of the trees) that can be read a page
+/// at a time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scan {
+    /// Keys matching a glob or regex (`list`)
+    Keys {
+        pattern: Vec<u8>,
+        is_regex: bool,
+        filter: Filter,
+    },
+    /// Entries whose value matches a glob or regex (`search`)
+    Values {
+        pattern: Vec<u8>,
+        is_regex: bool,
+        filter: Filter,
+    },
+    /// Entries whose value is a JSON document meeting a query (`query`),
+    /// with the fields to show of each
+    Query {
+        query: String,
+        select: Vec<query::Path>,
+        filter: Filter,
+    },
+    /// Keys from `start` (inclusive) to `end` (exclusive) (`range`)
+    Range { start: Vec<u8>, end: Vec<u8> },
+    /// Keys starting with the given bytes (`prefix`, `head`)
+    Prefix(Vec<u8>),
+    /// Tree names matching a glob or regex (`trees`)
+    Trees { pattern: Vec<u8>, is_regex: bool },
+}
+
+/// A page read by `SledViewer::scan`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanPage {
+    Keys(Listing<KeyInfo>),
+    Values(Listing<KeyInfo>),
+    /// Query results with the fields to show of each
+    Projection {
+        columns: Vec<query::Path>,
+        entries: Listing<KeyInfo>,
+    },
+    Trees(Listing<Vec<u8>>),
+}
+
+/// Follows a scan while it runs, to show progress or print matches as they
+/// are found
+pub trait ScanObserver {
+    /// Called after every key read with the number read so far; an error
+    /// cancels the scan
+    fn scanned(&mut self, _count: usize) -> Result<(), SledoViewError> {
+        Ok(())
+    }
+
+    /// Called for each entry of the page as soon as it is found, with its
+    /// position in the whole listing
+    fn matched(&mut self, _scan: &Scan, _position: usize, _info: &KeyInfo) {}
+}
+
+/// Scan without observing
+impl ScanObserver for () {}
+
+/// Where the last paged scan stopped
+#[derive(Debug, Clone)]
+struct Cursor {
+    scan: Scan,
+    /// The tree glob of a scan across trees
+    trees: Option<Vec<u8>>,
+    after: Resume,
+    /// Number of items returned so far
+    position: usize,
+    /// The page the scan was read with, for its limit and order
+    page: Page,
+}
+
+/// The last item a page returned, which the next page starts after
+#[derive(Debug, Clone)]
+struct Resume {
+    /// The tree the key was read from, in a scan across trees
+    tree: Option<Vec<u8>>,
+    /// The last key or tree name
+    key: Vec<u8>,
+}
+
+/// A write staged by a transaction, applied on `commit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedWrite {
+    /// The tree written to, the default tree's as `__sled__default`
+    pub tree: Vec<u8>,
+    pub key: Vec<u8>,
+    /// The value to set, or `None` to delete the key
+    pub value: Option<Vec<u8>>,
+    /// For a conditional write, the value the key must still have on
+    /// `commit`, `Some(None)` when it must be absent
+    pub expected: Option<Option<Vec<u8>>>,
+    /// The codecs of the tree, to display the key
+    pub codecs: TreeCodecs,
+}
+
+/// What the entries of a key range are matched against; a scan returns
+/// the entries that match every pattern and pass the filter
+#[derive(Default)]
+struct EntryMatcher {
+    /// Patterns for the key: the raw key, or the key as displayed with a
+    /// typed key codec
+    key: Vec<Regex>,
+    /// Patterns for the value
+    value: Vec<Regex>,
+    /// Condition on the value as a JSON document
+    query: Option<Query>,
+    /// Keys from the first (inclusive) to the second (exclusive) by value,
+    /// for key codecs whose byte order is not the order of their values
+    key_range: Option<(Vec<u8>, Vec<u8>)>,
+    filter: Filter,
+}
+
+impl EntryMatcher {
+    /// Compile a filter's own patterns; `key_glob` is its `--key` glob as bytes
+    fn new(filter: &Filter, key_glob: Option<&[u8]>) -> Result<Self> {
+        let mut matcher = Self {
+            filter: filter.clone(),
+            ..Self::default()
+        };
+        if let Some(key) = key_glob {
+            matcher
+                .key
+                .push(compile_pattern(key, false, filter.ignore_case)?);
+        }
+        if let Some(value) = &filter.value {
+            matcher
+                .value
+                .push(compile_pattern(value.as_bytes(), true, filter.ignore_case)?);
+        }
+        Ok(matcher)
+    }
+
+    /// Where `key` and `value` match the first of their patterns, or `None`
+    /// when any pattern does not match
+    fn find(&self, key: &[u8], value: &[u8]) -> Option<Matched> {
+        Some(Matched {
+            key: find_all(&self.key, key)?,
+            value: find_all(&self.value, value)?,
+        })
+    }
+}
+
+/// Where the first of `regexes` matches `haystack`, if they all match it
+fn find_all(regexes: &[Regex], haystack: &[u8]) -> Option<Option<Range<usize>>> {
+    let mut first = None;
+    for regex in regexes {
+        let found = regex.find(haystack)?;
+        first.get_or_insert(found.range());
+    }
+    Some(first)
+}
 
 pub struct SledViewer {
     db: Db,
-    selected_tree: Option<String>,
+    path: PathBuf,
+    read_only: bool,
+    selected_tree: Option<Vec<u8>>,
+    profile: Profile,
+    codecs: TreeCodecs,
+    cursor: Option<Cursor>,
+    /// The writes staged since `begin`, in a transaction
+    transaction: Option<Vec<StagedWrite>>,
+    /// The copy of the database opened by `new_snapshot`, removed when the
+    /// viewer is dropped (after `db`, which is declared first)
+    snapshot: Option<TempDir>,
 }
 
 impl SledViewer {
     pub fn new(path: &Path) -> Result<Self> {
-        let db = sled::open(path)?;
+        let db = open_db(path)?;
         Ok(Self {
             db,
+            path: path.to_path_buf(),
+            read_only: false,
             selected_tree: None,
+            profile: Profile::default(),
+            codecs: TreeCodecs::default(),
+            cursor: None,
+            transaction: None,
+            snapshot: None,
         })
     }
 
+    /// Open the database for inspection only; write commands are refused.
+    ///
+    /// SLED may still rewrite its files when it opens the database; use
+    /// `new_snapshot` to leave them untouched.
+    pub fn new_read_only(path: &Path) -> Result<Self> {
+        let mut viewer = Self::new(path)?;
+        viewer.read_only = true;
+        Ok(viewer)
+    }
+
+    /// Open a temporary copy of the database for inspection only, so the
+    /// original files are never written, not even by SLED itself. Making the
+    /// copy takes as long as copying the database; it is removed when the
+    /// viewer is dropped.
+    pub fn new_snapshot(path: &Path) -> Result<Self> {
+        let snapshot = snapshot(path)?;
+        let mut viewer = Self::new_read_only(snapshot.path())?;
+        viewer.path = path.to_path_buf();
+        viewer.snapshot = Some(snapshot);
+        Ok(viewer)
+    }
+
+    /// Use a profile to pick the codecs of the current and later selected trees
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = profile;
+        self.update_codecs();
+    }
+
+    /// The codecs applied to the current tree
+    pub fn codecs(&self) -> &TreeCodecs {
+        &self.codecs
+    }
+
+    /// The codecs the profile gives a tree, the default tree's as
+    /// `__sled__default`
+    pub fn codecs_for(&self, tree: &[u8]) -> TreeCodecs {
+        self.profile.codecs_for(tree)
+    }
+
+    fn update_codecs(&mut self) {
+        let tree = self.selected_tree.as_deref().unwrap_or(DEFAULT_TREE_NAME);
+        self.codecs = self.profile.codecs_for(tree);
+    }
+
+    /// Whether the viewer was opened in read-only mode
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     pub fn count(&self) -> Result<usize> {
-        match &self.selected_tree {
-            Some(tree_name) => {
-                let tree = self.get_tree(tree_name)?;
-                Ok(tree.len())
+        Ok(self.current_tree()?.len())
+    }
+
+    /// List keys matching a glob (or regex) pattern, in byte order.
+    ///
+    /// With a typed key codec the pattern matches keys as displayed
+    /// (`4*` finds `u64` keys 4, 42, 400, ...), otherwise the raw bytes. A
+    /// glob over raw bytes only scans the keys starting with its literal
+    /// prefix, so `user_*` does not read the whole tree.
+    #[allow(dead_code)] // the binary lists keys with `scan`
+    pub fn list_keys<P: AsRef<[u8]>>(&self, pattern: P, is_regex: bool) -> Result<Vec<Vec<u8>>> {
+        let scan = Scan::Keys {
+            pattern: pattern.as_ref().to_vec(),
+            is_regex,
+            filter: Filter::default(),
+        };
+        self.entries(&scan, None, false)?
+            .filter_map(Result::transpose)
+            .map(|entry| entry.map(|info| info.key))
+            .collect()
+    }
+
+    /// The keys of the current tree in byte order, read as the iterator advances
+    pub fn iter_keys(&self) -> Result<impl Iterator<Item = Result<Vec<u8>>>> {
+        Ok(self
+            .current_tree()?
+            .iter()
+            .keys()
+            .map(|key| Ok(key?.to_vec())))
+    }
+
+    /// Get a key of the current tree; in a transaction, as staged
+    pub fn get_key<K: AsRef<[u8]>>(&self, key: K) -> Result<KeyInfo> {
+        let key = key.as_ref();
+        let value = match self.staged_value(key) {
+            Some(staged) => staged.map(|value| value.into()),
+            None => self.current_tree()?.get(key)?,
+        };
+        match value {
+            Some(value) => Ok(KeyInfo::new(key, &value).with_codecs(self.codecs.clone())),
+            None => Err(SledoViewError::KeyNotFound {
+                key: self.codecs.key.display(key),
             }
-            None => Ok(self.db.len()),
+            .into()),
         }
     }
 
-    pub fn list_keys(&self, pattern: &str, is_regex: bool) -> Result<Vec<String>> {
-        let mut keys = Vec::new();
+    /// Find entries whose value matches a glob (or regex) pattern, in key order
+    #[allow(dead_code)] // the binary itself searches a page at a time with `scan`
+    pub fn search_values<P: AsRef<[u8]>>(
+        &self,
+        pattern: P,
+        is_regex: bool,
+    ) -> Result<Vec<KeyInfo>> {
+        let scan = Scan::Values {
+            pattern: pattern.as_ref().to_vec(),
+            is_regex,
+            filter: Filter::default(),
+        };
+        self.entries(&scan, None, false)?
+            .filter_map(Result::transpose)
+            .collect()
+    }
 
-        if is_regex {
-            let regex = Regex::new(pattern).map_err(|_| SledoViewError::InvalidRegex {
-                pattern: pattern.to_string(),
-            })?;
+    /// Read a page of a scan of the current tree, or of the tree names.
+    ///
+    /// When more follows the page, the viewer remembers where it ended so
+    /// `scan_more` can continue from there. Entries are reported to
+    /// `observer` while the tree is read.
+    pub fn scan(
+        &mut self,
+        scan: Scan,
+        page: Page,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<ScanPage> {
+        self.cursor = None;
+        self.read_page(scan, None, None, page, 0, observer)
+    }
 
-            match &self.selected_tree {
-                Some(tree_name) => {
-                    let tree = self.get_tree(tree_name)?;
-                    for result in tree.iter() {
-                        let (key, _) = result?;
-                        let key_str = String::from_utf8_lossy(&key);
-                        if regex.is_match(&key_str) {
-                            keys.push(key_str.to_string());
-                        }
-                    }
-                }
-                None => {
-                    for result in self.db.iter() {
-                        let (key, _) = result?;
-                        let key_str = String::from_utf8_lossy(&key);
-                        if regex.is_match(&key_str) {
-                            keys.push(key_str.to_string());
-                        }
-                    }
-                }
+    /// Like `scan`, but read every tree whose name matches the glob `trees`
+    /// instead of the current tree: tree by tree in name order, each with
+    /// the codecs of its profile. Entries carry the tree they were read
+    /// from. The default tree is matched as `__sled__default`.
+    pub fn scan_trees(
+        &mut self,
+        trees: &[u8],
+        scan: Scan,
+        page: Page,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<ScanPage> {
+        self.cursor = None;
+        self.read_page(scan, Some(trees.to_vec()), None, page, 0, observer)
+    }
+
+    /// Read the page after the one the last scan returned, by default as long
+    /// as that one. The scan continues after the last key it returned, so
+    /// keys written or deleted in between are accounted for.
+    pub fn scan_more(
+        &mut self,
+        limit: Option<usize>,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<ScanPage> {
+        let cursor = self.cursor.take().ok_or(SledoViewError::NoMoreResults)?;
+        let page = Page {
+            offset: 0,
+            limit: limit.or(cursor.page.limit),
+            ..cursor.page
+        };
+        self.read_page(
+            cursor.scan,
+            cursor.trees,
+            Some(&cursor.after),
+            page,
+            cursor.position,
+            observer,
+        )
+    }
+
+    /// The last `count` entries of the current tree, in byte order
+    pub fn tail(&self, count: usize) -> Result<Vec<KeyInfo>> {
+        let mut entries = Vec::with_capacity(count);
+        for result in self.current_tree()?.iter().rev().take(count) {
+            let (key, value) = result?;
+            entries.push(KeyInfo::new(&key, &value).with_codecs(self.codecs.clone()));
+        }
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Every tree holding `key`, in name order, with the entry found there.
+    ///
+    /// The key is read with each tree's key codec; trees whose codec cannot
+    /// read it are skipped.
+    pub fn locate(&self, key: &str) -> Result<Vec<KeyInfo>> {
+        let mut found = Vec::new();
+        for name in self.tree_names() {
+            let codecs = self.profile.codecs_for(&name);
+            let Ok(key) = codecs.key.parse(key) else {
+                continue;
+            };
+            if let Some(value) = self.db.open_tree(&name)?.get(&key)? {
+                found.push(KeyInfo::new(&key, &value).with_codecs(codecs).in_tree(name));
             }
-        } else {
-            // Convert glob pattern to regex
-            let regex_pattern = glob_to_regex(pattern);
-            let regex = Regex::new(&regex_pattern).map_err(|_| SledoViewError::InvalidRegex {
-                pattern: pattern.to_string(),
-            })?;
+        }
+        Ok(found)
+    }
 
-            match &self.selected_tree {
-                Some(tree_name) => {
-                    let tree = self.get_tree(tree_name)?;
-                    for result in tree.iter() {
-                        let (key, _) = result?;
-                        let key_str = String::from_utf8_lossy(&key);
-                        if regex.is_match(&key_str) {
-                            keys.push(key_str.to_string());
+    fn read_page(
+        &mut self,
+        scan: Scan,
+        trees: Option<Vec<u8>>,
+        after: Option<&Resume>,
+        page: Page,
+        start: usize,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<ScanPage> {
+        let (result, last, position, more) = match &scan {
+            Scan::Trees { pattern, is_regex } => {
+                let mut names = self.list_trees(pattern, *is_regex)?;
+                match page.sort {
+                    Sort::Bytes => {}
+                    Sort::Key => names
+                        .sort_by(|a, b| page::natural_cmp(&bytes::escape(a), &bytes::escape(b))),
+                    sort => {
+                        return Err(SledoViewError::InvalidSort {
+                            listing: "trees".to_string(),
+                            sort: sort.to_string(),
                         }
+                        .into())
                     }
                 }
-                None => {
-                    for result in self.db.iter() {
-                        let (key, _) = result?;
-                        let key_str = String::from_utf8_lossy(&key);
-                        if regex.is_match(&key_str) {
-                            keys.push(key_str.to_string());
+                if page.reverse {
+                    names.reverse();
+                }
+                // Name order resumes after the last name, so trees created
+                // in between are accounted for; other orders by position
+                let names = names
+                    .into_iter()
+                    .skip(if page.sort == Sort::Bytes { 0 } else { start })
+                    .filter(|name| match after {
+                        Some(after) if page.sort == Sort::Bytes => {
+                            (*name > after.key) != page.reverse && *name != after.key
                         }
+                        _ => true,
+                    })
+                    .map(Ok::<_, anyhow::Error>);
+                let listing = page::paginate(names, page, start, |_, _| ())?;
+                let last = listing.items.last().map(|name| Resume {
+                    tree: None,
+                    key: name.clone(),
+                });
+                let (position, more) = (listing.start + listing.len(), listing.more);
+                (ScanPage::Trees(listing), last, position, more)
+            }
+            _ => {
+                // Byte order is read as SLED iterates, and resumes after the
+                // last key. Other orders read every match to sort them, and
+                // resume by position.
+                let sorted = page.sort != Sort::Bytes;
+                let after = after.filter(|_| !sorted);
+                let reverse = page.reverse && !sorted;
+
+                // Both closures report to the observer, one at a time
+                let observer = RefCell::new(observer);
+                let entries: Box<dyn Iterator<Item = _>> = match &trees {
+                    Some(trees) => Box::new(self.entries_across(trees, &scan, after, reverse)?),
+                    None => {
+                        Box::new(self.entries(&scan, after.map(|after| &after.key[..]), reverse)?)
                     }
-                }
+                };
+                let matches = entries
+                    .enumerate()
+                    .map(|(read, entry)| {
+                        observer.borrow_mut().scanned(read + 1)?;
+                        entry
+                    })
+                    .filter_map(Result::transpose);
+                let on_item =
+                    |position, info: &KeyInfo| observer.borrow_mut().matched(&scan, position, info);
+                let listing = if sorted {
+                    let mut matches = matches.collect::<Result<Vec<_>>>()?;
+                    sort_entries(&mut matches, page.sort);
+                    if page.reverse {
+                        matches.reverse();
+                    }
+                    let matches = matches.into_iter().skip(start).map(Ok::<_, anyhow::Error>);
+                    page::paginate(matches, page, start, on_item)?
+                } else {
+                    page::paginate(matches, page, start, on_item)?
+                };
+                let last = listing.items.last().map(|info| Resume {
+                    tree: info.tree.clone(),
+                    key: info.key.clone(),
+                });
+                let (position, more) = (listing.start + listing.len(), listing.more);
+                let result = match &scan {
+                    Scan::Query { select, .. } if !select.is_empty() => ScanPage::Projection {
+                        columns: select.clone(),
+                        entries: listing,
+                    },
+                    Scan::Values { .. } | Scan::Query { .. } => ScanPage::Values(listing),
+                    _ => ScanPage::Keys(listing),
+                };
+                (result, last, position, more)
             }
-        }
+        };
 
-        keys.sort();
-        Ok(keys)
+        if let (Some(after), true) = (last, more) {
+            self.cursor = Some(Cursor {
+                scan,
+                trees,
+                after,
+                position,
+                page,
+            });
+        }
+        Ok(result)
     }
 
-    pub fn get_key(&self, key: &str) -> Result<KeyInfo> {
-        let key_bytes = key.as_bytes();
+    /// Read the entries of a key scan of the current tree, starting after `after`
+    fn entries(
+        &self,
+        scan: &Scan,
+        after: Option<&[u8]>,
+        reverse: bool,
+    ) -> Result<impl Iterator<Item = Result<Option<KeyInfo>>>> {
+        tree_entries(
+            self.current_tree()?,
+            self.codecs.clone(),
+            scan,
+            after,
+            reverse,
+        )
+    }
 
-        let value_opt = match &self.selected_tree {
-            Some(tree_name) => {
-                let tree = self.get_tree(tree_name)?;
-                tree.get(key_bytes)?
+    /// Read the entries of a key scan in every tree matching the glob `trees`,
+    /// starting after `after`; in reverse, the trees are read in reverse too
+    fn entries_across(
+        &self,
+        trees: &[u8],
+        scan: &Scan,
+        after: Option<&Resume>,
+        reverse: bool,
+    ) -> Result<impl Iterator<Item = Result<Option<KeyInfo>>>> {
+        let matcher = compile_pattern(trees, false, false)?;
+        let after_tree = after.and_then(|after| after.tree.as_deref());
+
+        let mut names = self.tree_names();
+        if reverse {
+            names.reverse();
+        }
+        let mut scans = Vec::new();
+        for name in names {
+            let done =
+                after_tree.is_some_and(|tree| (name.as_slice() < tree) != reverse && name != tree);
+            if !matcher.is_match(&name) || done {
+                continue;
             }
-            None => self.db.get(key_bytes)?,
-        };
-
-        match value_opt {
-            Some(value) => {
-                let value_str = String::from_utf8_lossy(&value);
-                let size = value.len();
+            let after = after
+                .filter(|after| after.tree.as_ref() == Some(&name))
+                .map(|after| &after.key[..]);
+            let tree = self.db.open_tree(&name)?;
+            let codecs = self.profile.codecs_for(&name);
+            let entries = tree_entries(tree, codecs, scan, after, reverse)?;
+            scans.push(
+                entries.map(move |entry| {
+                    entry.map(|info| info.map(|info| info.in_tree(name.clone())))
+                }),
+            );
+        }
+        Ok(scans.into_iter().flatten())
+    }
+    /// Set a key-value pair in the database or selected tree; in a
+    /// transaction, stage it
+    pub fn set_key<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, key: K, value: V) -> Result<()> {
+        if self.transaction.is_some() {
+            self.stage(key.as_ref(), Some(value.as_ref()), None);
+            return Ok(());
+        }
+        let tree = self.current_tree()?;
+        tree.insert(key.as_ref(), value.as_ref())?;
+        tree.flush()?;
+        Ok(())
+    }
 
-                Ok(KeyInfo {
-                    key: key.to_string(),
-                    value: value_str.to_string(),
-                    size,
-                    is_utf8: String::from_utf8(value.to_vec()).is_ok(),
-                })
-            }
-            None => Err(SledoViewError::KeyNotFound {
-                key: key.to_string(),
+    /// Delete a key from the database or selected tree; in a transaction,
+    /// stage the delete. Returns whether the key existed.
+    pub fn delete_key<K: AsRef<[u8]>>(&mut self, key: K) -> Result<bool> {
+        let key = key.as_ref();
+        if self.transaction.is_some() {
+            let existed = match self.staged_value(key) {
+                Some(staged) => staged.is_some(),
+                None => self.current_tree()?.contains_key(key)?,
+            };
+            if existed {
+                self.stage(key, None, None);
             }
-            .into()),
+            return Ok(existed);
         }
+        let tree = self.current_tree()?;
+        let existed = tree.remove(key)?.is_some();
+        tree.flush()?;
+        Ok(existed)
     }
 
-    pub fn search_values(&self, pattern: &str, is_regex: bool) -> Result<Vec<KeyValuePair>> {
-        let mut results = Vec::new();
+    /// Delete keys of the current tree as one batch with a single flush; in a
+    /// transaction, stage the deletes. Returns the number of keys.
+    pub fn delete_keys(&mut self, keys: &[Vec<u8>]) -> Result<usize> {
+        if self.transaction.is_some() {
+            for key in keys {
+                self.stage(key, None, None);
+            }
+            return Ok(keys.len());
+        }
+        let mut batch = Batch::default();
+        for key in keys {
+            batch.remove(&key[..]);
+        }
+        let tree = self.current_tree()?;
+        tree.apply_batch(batch)?;
+        tree.flush()?;
+        Ok(keys.len())
+    }
 
-        if is_regex {
-            let regex = Regex::new(pattern).map_err(|_| SledoViewError::InvalidRegex {
-                pattern: pattern.to_string(),
-            })?;
+    /// The keys of the current tree that a scan selects, in byte order
+    pub fn matching_keys(
+        &self,
+        scan: &Scan,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::new();
+        for (read, entry) in self.entries(scan, None, false)?.enumerate() {
+            observer.scanned(read + 1)?;
+            if let Some(info) = entry? {
+                keys.push(info.key);
+            }
+        }
+        Ok(keys)
+    }
 
-            match &self.selected_tree {
-                Some(tree_name) => {
-                    let tree = self.get_tree(tree_name)?;
-                    for result in tree.iter() {
-                        let (key, value) = result?;
-                        let value_str = String::from_utf8_lossy(&value);
-                        if regex.is_match(&value_str) {
-                            results.push(KeyValuePair {
-                                key: String::from_utf8_lossy(&key).to_string(),
-                                value: value_str.to_string(),
-                            });
-                        }
-                    }
-                }
+    /// Copy keys of the current tree, with their values as stored, to
+    /// `tree`, which must exist, or within the current tree; each pair is a
+    /// source key and its target key. With `remove` the sources are deleted, moving the keys.
+    /// Unless `overwrite`, a target that exists and is not itself moved away
+    /// is a conflict. Nothing is written when any pair fails; the writes are
+    /// applied in one transaction, or staged in the one in progress.
+    pub fn copy_keys(
+        &mut self,
+        pairs: &[(Vec<u8>, Vec<u8>)],
+        tree: Option<&[u8]>,
+        remove: bool,
+        overwrite: bool,
+    ) -> Result<usize> {
+        let source_name = self.current_tree_name().to_vec();
+        let source_tree = self.current_tree()?;
+        let (target_name, target_tree, target_codecs) = match tree {
+            // Opening a missing tree would create it, even for a mistyped name
+            Some(name) => match self.existing_tree(name)? {
+                Some((tree, codecs)) => (name.to_vec(), tree, codecs),
                 None => {
-                    for result in self.db.iter() {
-                        let (key, value) = result?;
-                        let value_str = String::from_utf8_lossy(&value);
-                        if regex.is_match(&value_str) {
-                            results.push(KeyValuePair {
-                                key: String::from_utf8_lossy(&key).to_string(),
-                                value: value_str.to_string(),
-                            });
-                        }
+                    return Err(SledoViewError::TreeNotFound {
+                        tree: bytes::escape(name),
                     }
+                    .into())
                 }
+            },
+            None => (
+                source_name.clone(),
+                source_tree.clone(),
+                self.codecs.clone(),
+            ),
+        };
+
+        let mut targets: Vec<&[u8]> = pairs.iter().map(|(_, to)| &to[..]).collect();
+        targets.sort();
+        if let Some(pair) = targets.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(SledoViewError::DuplicateTarget {
+                key: target_codecs.key.display(pair[0]),
             }
-        } else {
-            // Convert glob pattern to regex
-            let regex_pattern = glob_to_regex(pattern);
-            let regex = Regex::new(&regex_pattern).map_err(|_| SledoViewError::InvalidRegex {
-                pattern: pattern.to_string(),
-            })?;
+            .into());
+        }
 
-            match &self.selected_tree {
-                Some(tree_name) => {
-                    let tree = self.get_tree(tree_name)?;
-                    for result in tree.iter() {
-                        let (key, value) = result?;
-                        let value_str = String::from_utf8_lossy(&value);
-                        if regex.is_match(&value_str) {
-                            results.push(KeyValuePair {
-                                key: String::from_utf8_lossy(&key).to_string(),
-                                value: value_str.to_string(),
-                            });
-                        }
-                    }
-                }
-                None => {
-                    for result in self.db.iter() {
-                        let (key, value) = result?;
-                        let value_str = String::from_utf8_lossy(&value);
-                        if regex.is_match(&value_str) {
-                            results.push(KeyValuePair {
-                                key: String::from_utf8_lossy(&key).to_string(),
-                                value: value_str.to_string(),
-                            });
-                        }
-                    }
+        // Sources are all read, and deleted, before any target is written,
+        // so keys can be moved onto each other's names
+        let mut writes = Vec::new();
+        let mut values = Vec::with_capacity(pairs.len());
+        for (from, _) in pairs {
+            let value = self
+                .value_in(&source_name, &source_tree, from)?
+                .ok_or_else(|| SledoViewError::KeyNotFound {
+                    key: self.codecs.key.display(from),
+                })?;
+            if remove {
+                let write = StagedWrite {
+                    tree: source_name.clone(),
+                    key: from.clone(),
+                    value: None,
+                    expected: Some(Some(value.clone())),
+                    codecs: self.codecs.clone(),
+                };
+                merge_write(&mut writes, write);
+            }
+            values.push(value);
+        }
+        for ((_, to), value) in pairs.iter().zip(values) {
+            let vacated = writes
+                .iter()
+                .any(|write| write.tree == target_name && write.key == *to);
+            if !overwrite && !vacated {
+                if let Some(current) = self.value_in(&target_name, &target_tree, to)? {
+                    return Err(conflict(&target_codecs, to, None, Some(&current)).into());
                 }
             }
+            let write = StagedWrite {
+                tree: target_name.clone(),
+                key: to.clone(),
+                value: Some(value),
+                expected: (!overwrite).then_some(None),
+                codecs: target_codecs.clone(),
+            };
+            merge_write(&mut writes, write);
         }
 
-        results.sort_by(|a, b| a.key.cmp(&b.key));
-        Ok(results)
+        match &mut self.transaction {
+            Some(staged) => writes
+                .into_iter()
+                .for_each(|write| merge_write(staged, write)),
+            None => self.apply(&writes)?,
+        }
+        Ok(pairs.len())
     }
 
-    /// Set a key-value pair in the database or selected tree
-    pub fn set_key(&self, key: &str, value: &str) -> Result<()> {
-        match &self.selected_tree {
-            Some(tree_name) => {
-                let tree = self.get_tree(tree_name)?;
-                tree.insert(key.as_bytes(), value.as_bytes())?;
-                tree.flush()?;
-            }
-            None => {
-                self.db.insert(key.as_bytes(), value.as_bytes())?;
-                self.db.flush()?;
+    /// Write a key only if its value is still `expected`, `None` meaning
+    /// absent; a `new` value of `None` deletes the key. Otherwise fails with
+    /// a conflict reporting the current value. In a transaction, the staged
+    /// value is checked now and the committed one again on `commit`.
+    pub fn compare_and_swap<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<()> {
+        let key = key.as_ref();
+        if self.transaction.is_some() {
+            let current = match self.staged_value(key) {
+                Some(staged) => staged.map(<[u8]>::to_vec),
+                None => self.current_tree()?.get(key)?.map(|value| value.to_vec()),
+            };
+            if current.as_deref() != expected {
+                return Err(conflict(&self.codecs, key, expected, current.as_deref()).into());
             }
+            self.stage(key, new, Some(expected.map(<[u8]>::to_vec)));
+            return Ok(());
+        }
+        let tree = self.current_tree()?;
+        if let Err(error) = tree.compare_and_swap(key, expected, new)? {
+            return Err(conflict(&self.codecs, key, expected, error.current.as_deref()).into());
         }
+        tree.flush()?;
         Ok(())
     }
 
-    /// Delete a key from the database or selected tree
-    pub fn delete_key(&self, key: &str) -> Result<bool> {
-        let existed = match &self.selected_tree {
-            Some(tree_name) => {
-                let tree = self.get_tree(tree_name)?;
-                let existed = tree.remove(key.as_bytes())?.is_some();
-                tree.flush()?;
-                existed
-            }
-            None => {
-                let existed = self.db.remove(key.as_bytes())?.is_some();
-                self.db.flush()?;
-                existed
-            }
+    /// Start a transaction: `set` and `delete` are staged, across trees,
+    /// until `commit` applies them or `rollback` discards them
+    pub fn begin(&mut self) -> Result<()> {
+        if self.transaction.is_some() {
+            return Err(SledoViewError::TransactionInProgress.into());
+        }
+        self.transaction = Some(Vec::new());
+        Ok(())
+    }
+
+    /// The writes staged so far, or `None` outside a transaction
+    pub fn staged(&self) -> Option<&[StagedWrite]> {
+        self.transaction.as_deref()
+    }
+
+    /// Apply the staged writes atomically and end the transaction, returning
+    /// the number of writes. When applying fails, or a conditional write
+    /// finds another value, nothing is written and the transaction stays open.
+    pub fn commit(&mut self) -> Result<usize> {
+        let writes = self
+            .transaction
+            .as_ref()
+            .ok_or(SledoViewError::NoTransaction)?;
+        self.apply(writes)?;
+
+        let count = writes.len();
+        self.transaction = None;
+        Ok(count)
+    }
+
+    /// Discard the staged writes and end the transaction, returning the
+    /// number of writes discarded
+    pub fn rollback(&mut self) -> Result<usize> {
+        let writes = self
+            .transaction
+            .take()
+            .ok_or(SledoViewError::NoTransaction)?;
+        Ok(writes.len())
+    }
+
+    /// The name of the current tree, the default tree's as `__sled__default`
+    fn current_tree_name(&self) -> &[u8] {
+        self.selected_tree.as_deref().unwrap_or(DEFAULT_TREE_NAME)
+    }
+
+    /// The value staged for a key of the current tree: `Some(None)` when its
+    /// delete is staged, `None` when nothing is staged for it
+    fn staged_value(&self, key: &[u8]) -> Option<Option<&[u8]>> {
+        self.staged_in(self.current_tree_name(), key)
+    }
+
+    /// Like `staged_value`, for a key of any tree
+    fn staged_in(&self, tree: &[u8], key: &[u8]) -> Option<Option<&[u8]>> {
+        self.transaction
+            .iter()
+            .flatten()
+            .find(|write| write.tree == tree && write.key == key)
+            .map(|write| write.value.as_deref())
+    }
+
+    /// The value of a key of `tree`, named `name`, as staged if it is
+    fn value_in(&self, name: &[u8], tree: &Tree, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(match self.staged_in(name, key) {
+            Some(staged) => staged.map(<[u8]>::to_vec),
+            None => tree.get(key)?.map(|value| value.to_vec()),
+        })
+    }
+
+    /// Stage a write to the current tree, replacing what was staged for the
+    /// same key
+    fn stage(&mut self, key: &[u8], value: Option<&[u8]>, expected: Option<Option<Vec<u8>>>) {
+        let write = StagedWrite {
+            tree: self.current_tree_name().to_vec(),
+            key: key.to_vec(),
+            value: value.map(<[u8]>::to_vec),
+            expected,
+            codecs: self.codecs.clone(),
         };
-        Ok(existed)
+        if let Some(writes) = &mut self.transaction {
+            merge_write(writes, write);
+        }
     }
 
-    /// Check if the database is writable
-    pub fn is_writable(&self) -> bool {
-        // Try a test operation to check if the database is writable
-        let test_result = match &self.selected_tree {
-            Some(tree_name) => {
-                if let Ok(tree) = self.get_tree(tree_name) {
-                    match tree.insert(b"__sledoview_test__", b"test") {
-                        Ok(_) => {
-                            let _ = tree.remove(b"__sledoview_test__");
-                            let _ = tree.flush();
-                            true
+    /// Write staged writes atomically, checking the values conditional
+    /// writes expect, then flush
+    fn apply(&self, writes: &[StagedWrite]) -> Result<()> {
+        let mut names: Vec<&[u8]> = writes.iter().map(|write| &write.tree[..]).collect();
+        names.sort();
+        names.dedup();
+        let trees = names
+            .iter()
+            .map(|name| self.db.open_tree(name))
+            .collect::<sled::Result<Vec<Tree>>>()?;
+
+        trees[..]
+            .transaction(|views| {
+                for write in writes {
+                    let index = names
+                        .binary_search(&&write.tree[..])
+                        .expect("every tree written to is open");
+                    if let Some(expected) = &write.expected {
+                        let current = views[index].get(&write.key[..])?;
+                        if current.as_deref() != expected.as_deref() {
+                            return Err(ConflictableTransactionError::Abort(conflict(
+                                &write.codecs,
+                                &write.key,
+                                expected.as_deref(),
+                                current.as_deref(),
+                            )));
                         }
-                        Err(_) => false,
                     }
-                } else {
-                    false
-                }
-            }
-            None => match self.db.insert(b"__sledoview_test__", b"test") {
-                Ok(_) => {
-                    let _ = self.db.remove(b"__sledoview_test__");
-                    let _ = self.db.flush();
-                    true
+                    match &write.value {
+                        Some(value) => views[index].insert(&write.key[..], &value[..])?,
+                        None => views[index].remove(&write.key[..])?,
+                    };
                 }
-                Err(_) => false,
-            },
-        };
-        test_result
+                Ok(())
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(conflict) => conflict,
+                TransactionError::Storage(e) => SledoViewError::from(e),
+            })?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Check if the database is writable.
+    ///
+    /// This never writes to the database: it is false in read-only mode and
+    /// otherwise derived from the permissions of the database files.
+    pub fn is_writable(&self) -> bool {
+        !self.read_only && is_path_writable(&self.path)
     }
 
     /// List all tree names, optionally filtered by pattern
-    pub fn list_trees(&self, pattern: &str, is_regex: bool) -> Result<Vec<String>> {
-        let mut tree_names = Vec::new();
+    pub fn list_trees<P: AsRef<[u8]>>(&self, pattern: P, is_regex: bool) -> Result<Vec<Vec<u8>>> {
+        let regex = compile_pattern(pattern.as_ref(), is_regex, false)?;
 
-        // Get all tree names from the database
-        let all_trees = self.db.tree_names();
+        let mut tree_names: Vec<Vec<u8>> = self
+            .db
+            .tree_names()
+            .into_iter()
+            // Skip the default tree (empty name or __sled__default)
+            .filter(|name| !name.is_empty() && name != DEFAULT_TREE_NAME)
+            .filter(|name| regex.is_match(name))
+            .map(|name| name.to_vec())
+            .collect();
 
-        for tree_name_bytes in all_trees {
-            let tree_name = String::from_utf8_lossy(&tree_name_bytes).to_string();
+        tree_names.sort();
+        Ok(tree_names)
+    }
 
-            // Skip the default tree (empty name or __sled__default)
-            if tree_name.is_empty() || tree_name == "__sled__default" {
-                continue;
+    /// The names of all trees, the default tree's as `__sled__default`, sorted
+    fn tree_names(&self) -> Vec<Vec<u8>> {
+        let mut names: Vec<Vec<u8>> = self
+            .db
+            .tree_names()
+            .into_iter()
+            .map(|name| match &name[..] {
+                b"" => DEFAULT_TREE_NAME.to_vec(),
+                name => name.to_vec(),
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Whether a tree exists; the default tree is named `__sled__default`
+    pub fn tree_exists(&self, name: &[u8]) -> bool {
+        self.tree_names().iter().any(|t| t == name)
+    }
+
+    /// Create a tree, returning `false` when it already exists
+    pub fn create_tree<T: AsRef<[u8]>>(&self, tree_name: T) -> Result<bool> {
+        let name = tree_name.as_ref();
+        if self.tree_exists(name) {
+            return Ok(false);
+        }
+        self.db.open_tree(name)?;
+        self.db.flush()?;
+        Ok(true)
+    }
+
+    /// Remove a tree and all its keys, returning how many keys it had. The
+    /// tree is unselected when it was selected.
+    pub fn drop_tree(&mut self, name: &[u8]) -> Result<usize> {
+        self.refuse_default_tree(name, "dropped")?;
+        let count = self.existing_tree_for_change(name)?.len();
+        self.db.drop_tree(name)?;
+        self.db.flush()?;
+        if self.selected_tree.as_deref() == Some(name) {
+            self.unselect_tree()?;
+        }
+        Ok(count)
+    }
+
+    /// Remove all keys of a tree, returning how many there were
+    pub fn clear_tree(&mut self, name: &[u8]) -> Result<usize> {
+        let tree = self.existing_tree_for_change(name)?;
+        let count = tree.len();
+        tree.clear()?;
+        tree.flush()?;
+        Ok(count)
+    }
+
+    /// Copy all keys of a tree into a new tree, returning how many were copied
+    pub fn copy_tree(&mut self, from: &[u8], to: &[u8]) -> Result<usize> {
+        let source = self.existing_tree_for_change(from)?;
+        if self.tree_exists(to) {
+            return Err(SledoViewError::TreeExists {
+                tree: bytes::escape(to),
             }
+            .into());
+        }
+        let mut batch = Batch::default();
+        let mut count = 0;
+        for entry in source.iter() {
+            let (key, value) = entry?;
+            batch.insert(key, value);
+            count += 1;
+        }
+        self.db.open_tree(to)?.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(count)
+    }
 
-            let matches = if is_regex {
-                let regex = Regex::new(pattern).map_err(|_| SledoViewError::InvalidRegex {
-                    pattern: pattern.to_string(),
-                })?;
-                regex.is_match(&tree_name)
-            } else {
-                let regex_pattern = glob_to_regex(pattern);
-                let regex =
-                    Regex::new(&regex_pattern).map_err(|_| SledoViewError::InvalidRegex {
-                        pattern: pattern.to_string(),
-                    })?;
-                regex.is_match(&tree_name)
-            };
+    /// Give a tree a new name, returning how many keys were moved. The
+    /// selection follows the tree.
+    ///
+    /// SLED cannot rename a tree, so its keys are copied to a new tree, which
+    /// is checked, and the old tree is then dropped. This is not atomic: when
+    /// the drop fails, both trees are left and the error says so.
+    pub fn rename_tree(&mut self, from: &[u8], to: &[u8]) -> Result<usize> {
+        self.refuse_default_tree(from, "renamed")?;
+        let count = self.copy_tree(from, to)?;
+        let copied = self.db.open_tree(to)?.len();
+        if copied != count {
+            return Err(SledoViewError::TreeOperation {
+                message: format!(
+                    "Renaming '{}' stopped: '{}' holds {} of its {} keys; '{}' was kept",
+                    bytes::escape(from),
+                    bytes::escape(to),
+                    copied,
+                    count,
+                    bytes::escape(from)
+                ),
+            }
+            .into());
+        }
+        if let Err(e) = self.db.drop_tree(from).and_then(|_| self.db.flush()) {
+            return Err(SledoViewError::TreeOperation {
+                message: format!(
+                    "Copied {} keys of '{}' to '{}' but could not drop '{}', so both trees exist: {}",
+                    count,
+                    bytes::escape(from),
+                    bytes::escape(to),
+                    bytes::escape(from),
+                    e
+                ),
+            }
+            .into());
+        }
+        if self.selected_tree.as_deref() == Some(from) {
+            self.select_tree(to)?;
+        }
+        Ok(count)
+    }
 
-            if matches {
-                tree_names.push(tree_name);
+    fn refuse_default_tree(&self, name: &[u8], change: &str) -> Result<()> {
+        if name.is_empty() || name == DEFAULT_TREE_NAME {
+            return Err(SledoViewError::TreeOperation {
+                message: format!("The default tree cannot be {}", change),
             }
+            .into());
         }
+        Ok(())
+    }
 
-        tree_names.sort();
-        Ok(tree_names)
+    /// A tree that a tree command changes as a whole. Such changes cannot be
+    /// staged, so they are refused during a transaction.
+    fn existing_tree_for_change(&self, name: &[u8]) -> Result<Tree> {
+        if self.transaction.is_some() {
+            return Err(SledoViewError::TransactionInProgress.into());
+        }
+        match self.existing_tree(name)? {
+            Some((tree, _)) => Ok(tree),
+            None => Err(SledoViewError::TreeNotFound {
+                tree: bytes::escape(name),
+            }
+            .into()),
+        }
     }
 
-    /// Select a tree to work with
-    pub fn select_tree(&mut self, tree_name: &str) -> Result<()> {
-        // Verify the tree exists by trying to open it
-        let _ = self.get_tree(tree_name)?;
-        self.selected_tree = Some(tree_name.to_string());
+    /// Select an existing tree to work with
+    pub fn select_tree<T: AsRef<[u8]>>(&mut self, tree_name: T) -> Result<()> {
+        if !self.tree_exists(tree_name.as_ref()) {
+            return Err(SledoViewError::TreeNotFound {
+                tree: bytes::escape(tree_name.as_ref()),
+            }
+            .into());
+        }
+        self.selected_tree = Some(tree_name.as_ref().to_vec());
+        self.cursor = None;
+        self.update_codecs();
         Ok(())
     }
 
@@ -310,60 +1213,357 @@ impl SledViewer {
     pub fn unselect_tree(&mut self) -> Result<bool> {
         let was_selected = self.selected_tree.is_some();
         self.selected_tree = None;
+        self.cursor = None;
+        self.update_codecs();
         Ok(was_selected)
     }
 
     /// Get the currently selected tree name
-    pub fn get_selected_tree(&self) -> Option<&String> {
-        self.selected_tree.as_ref()
+    pub fn get_selected_tree(&self) -> Option<&[u8]> {
+        self.selected_tree.as_deref()
+    }
+
+    /// The selected tree, or the default tree when none is selected
+    fn current_tree(&self) -> Result<Tree> {
+        match &self.selected_tree {
+            Some(tree_name) => self.get_tree(tree_name),
+            None => Ok((*self.db).clone()),
+        }
+    }
+
+    /// A tree and its codecs, or `None` when no tree has that name
+    pub fn existing_tree(&self, name: &[u8]) -> Result<Option<(Tree, TreeCodecs)>> {
+        if !self.tree_exists(name) {
+            return Ok(None);
+        }
+        Ok(Some((
+            self.db.open_tree(name)?,
+            self.profile.codecs_for(name),
+        )))
     }
 
     /// Get a tree by name
-    fn get_tree(&self, name: &str) -> Result<Tree> {
-        self.db.open_tree(name.as_bytes()).map_err(|e| {
+    fn get_tree(&self, name: &[u8]) -> Result<Tree> {
+        // Opening a missing tree creates it, which read-only mode must not do
+        if self.read_only && !self.db.tree_names().iter().any(|t| t == name) {
+            return Err(SledoViewError::TreeNotFound {
+                tree: bytes::escape(name),
+            }
+            .into());
+        }
+
+        self.db.open_tree(name).map_err(|e| {
             SledoViewError::TreeOperation {
-                message: format!("Failed to open tree '{}': {}", name, e),
+                message: format!("Failed to open tree '{}': {}", bytes::escape(name), e),
             }
             .into()
         })
     }
 }
 
-#[derive(Debug)]
+/// A stored entry. Keys and values are kept exactly as stored; use
+/// `display_key` and the renderers to display them.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyInfo {
-    pub key: String,
-    pub value: String,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
     pub size: usize,
-    pub is_utf8: bool,
+    pub content_type: ContentType,
+    /// How the key and value are shown, from the tree's profile
+    pub codecs: TreeCodecs,
+    /// The tree the entry was read from, in results across trees
+    pub tree: Option<Vec<u8>>,
+    /// Where a `list` or `search` pattern matched the entry
+    pub matched: Matched,
+}
+
+/// The parts of an entry that the first key and value patterns of a scan
+/// matched, as byte ranges. With a typed key codec the range is of the key
+/// as displayed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Matched {
+    pub key: Option<Range<usize>>,
+    pub value: Option<Range<usize>>,
 }
 
-#[derive(Debug)]
-pub struct KeyValuePair {
-    pub key: String,
-    pub value: String,
+impl KeyInfo {
+    pub fn new(key: &[u8], value: &[u8]) -> Self {
+        Self {
+            key: key.to_vec(),
+            value: value.to_vec(),
+            size: value.len(),
+            content_type: ContentType::detect(value),
+            codecs: TreeCodecs::default(),
+            tree: None,
+            matched: Matched::default(),
+        }
+    }
+
+    pub fn with_codecs(mut self, codecs: TreeCodecs) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    pub fn in_tree(mut self, tree: Vec<u8>) -> Self {
+        self.tree = Some(tree);
+        self
+    }
+
+    /// The key as shown to users
+    pub fn display_key(&self) -> String {
+        self.codecs.key.display(&self.key)
+    }
+
+    /// The value as text, with invalid UTF-8 replaced
+    pub fn value_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.value)
+    }
+
+    /// The value as a JSON document: decoded by the tree profile's decoder,
+    /// or else detected as JSON, MessagePack or CBOR
+    pub fn document(&self) -> Option<serde_json::Value> {
+        self.codecs
+            .value
+            .and_then(|decoder| decode::decode(&self.value, decoder).ok())
+            .or_else(|| decode::decode_detected(&self.value, self.content_type))
+    }
 }
 
-fn glob_to_regex(pattern: &str) -> String {
+/// Read the entries of a key scan of `tree` in byte order, starting after
+/// `after`. Yields one item per key read: the entry if the scan matches it.
+///
+/// Only the part of the tree the scan can match is read: a range, a prefix,
+/// or the literal prefix of a glob over raw keys.
+fn tree_entries(
+    tree: Tree,
+    codecs: TreeCodecs,
+    scan: &Scan,
+    after: Option<&[u8]>,
+    reverse: bool,
+) -> Result<impl Iterator<Item = Result<Option<KeyInfo>>>> {
+    let (lower, upper, matcher) = match scan {
+        Scan::Keys {
+            pattern,
+            is_regex,
+            filter,
+        } => {
+            let key_glob = filter.key_glob()?;
+            let mut matcher = EntryMatcher::new(filter, key_glob.as_deref())?;
+            let regex = compile_pattern(pattern, *is_regex, filter.ignore_case)?;
+            matcher.key.insert(0, regex);
+            let globs = [(!*is_regex).then_some(&pattern[..]), key_glob.as_deref()];
+            let prefix = key_prefix(&globs, filter, &codecs);
+            (prefix.to_vec(), prefix_end(prefix), matcher)
+        }
+        Scan::Values {
+            pattern,
+            is_regex,
+            filter,
+        } => {
+            let key_glob = filter.key_glob()?;
+            let mut matcher = EntryMatcher::new(filter, key_glob.as_deref())?;
+            let regex = compile_pattern(pattern, *is_regex, filter.ignore_case)?;
+            matcher.value.insert(0, regex);
+            let prefix = key_prefix(&[key_glob.as_deref()], filter, &codecs);
+            (prefix.to_vec(), prefix_end(prefix), matcher)
+        }
+        Scan::Query { query, filter, .. } => {
+            let key_glob = filter.key_glob()?;
+            let mut matcher = EntryMatcher::new(filter, key_glob.as_deref())?;
+            matcher.query = Some(Query::parse(query)?);
+            let prefix = key_prefix(&[key_glob.as_deref()], filter, &codecs);
+            (prefix.to_vec(), prefix_end(prefix), matcher)
+        }
+        Scan::Range { start, end } => {
+            // With little-endian or signed integer keys, for instance, a
+            // range of values is not a range of bytes: every key is read
+            // and compared by value. Bounds that do not fit the codec, like
+            // byte literals, are compared as bytes.
+            let by_value = match codecs.key.compare(start, end) {
+                Some(order) if !codecs.key.is_byte_ordered() => Some(order),
+                _ => None,
+            };
+            if by_value.unwrap_or_else(|| start.cmp(end)) == Ordering::Greater {
+                return Err(SledoViewError::InvalidRange {
+                    start: codecs.key.display(start),
+                    end: codecs.key.display(end),
+                }
+                .into());
+            }
+            match by_value {
+                Some(_) => {
+                    let matcher = EntryMatcher {
+                        key_range: Some((start.clone(), end.clone())),
+                        ..EntryMatcher::default()
+                    };
+                    (Vec::new(), None, matcher)
+                }
+                None => (start.clone(), Some(end.clone()), EntryMatcher::default()),
+            }
+        }
+        Scan::Prefix(prefix) => (prefix.clone(), prefix_end(prefix), EntryMatcher::default()),
+        Scan::Trees { .. } => unreachable!("tree names are not entries"),
+    };
+
+    let (lower, upper) = match (after, reverse) {
+        (Some(after), false) => (
+            Bound::Excluded(after.to_vec()),
+            upper.map_or(Bound::Unbounded, Bound::Excluded),
+        ),
+        (Some(after), true) => (Bound::Included(lower), Bound::Excluded(after.to_vec())),
+        (None, _) => (
+            Bound::Included(lower),
+            upper.map_or(Bound::Unbounded, Bound::Excluded),
+        ),
+    };
+
+    let range = tree.range((lower, upper));
+    let entries: Box<dyn Iterator<Item = _>> = if reverse {
+        Box::new(range.rev())
+    } else {
+        Box::new(range)
+    };
+    Ok(entries.map(move |result| {
+        let (key, value) = result?;
+        if !matcher.filter.accepts_size(value.len()) {
+            return Ok(None);
+        }
+        if let Some((start, end)) = &matcher.key_range {
+            let in_range = codecs.key.compare(start, &key).is_some_and(Ordering::is_le)
+                && codecs.key.compare(&key, end).is_some_and(Ordering::is_lt);
+            if !in_range {
+                return Ok(None);
+            }
+        }
+
+        let found = if codecs.key.is_typed() && !matcher.key.is_empty() {
+            matcher.find(codecs.key.display(&key).as_bytes(), &value)
+        } else {
+            matcher.find(&key, &value)
+        };
+        let Some(matched) = found else {
+            return Ok(None);
+        };
+
+        let info = KeyInfo::new(&key, &value).with_codecs(codecs.clone());
+        if !matcher.filter.accepts_content(info.content_type) {
+            return Ok(None);
+        }
+        if let Some(query) = &matcher.query {
+            if !info
+                .document()
+                .is_some_and(|document| query.matches(&document))
+            {
+                return Ok(None);
+            }
+        }
+        Ok(Some(KeyInfo { matched, ..info }))
+    }))
+}
+
+/// Sort entries read in byte order (and tree by tree, across trees) by a
+/// sort other than byte order; entries that tie keep their byte order
+fn sort_entries(entries: &mut Vec<KeyInfo>, sort: Sort) {
+    match sort {
+        Sort::Bytes => {}
+        Sort::Key => {
+            let mut keyed: Vec<(String, KeyInfo)> = entries
+                .drain(..)
+                .map(|info| (info.display_key(), info))
+                .collect();
+            keyed.sort_by(|(a, _), (b, _)| page::natural_cmp(a, b));
+            entries.extend(keyed.into_iter().map(|(_, info)| info));
+        }
+        Sort::Size => entries.sort_by_key(|info| info.size),
+        Sort::Value => entries.sort_by(|a, b| a.value.cmp(&b.value)),
+    }
+}
+
+/// The longest literal prefix of the key globs of a scan, which every
+/// matching key starts with; empty when keys are not matched as raw bytes
+fn key_prefix<'a>(globs: &[Option<&'a [u8]>], filter: &Filter, codecs: &TreeCodecs) -> &'a [u8] {
+    if filter.ignore_case || codecs.key.is_typed() {
+        return &[];
+    }
+    globs
+        .iter()
+        .flatten()
+        .map(|glob| glob_literal_prefix(glob))
+        .max_by_key(|prefix| prefix.len())
+        .unwrap_or_default()
+}
+
+/// Compile a glob or regex pattern for matching raw keys, values and tree names.
+///
+/// A glob may contain any bytes; a regex must be valid UTF-8 and can match
+/// raw bytes with `(?-u)`.
+pub(crate) fn compile_pattern(pattern: &[u8], is_regex: bool, ignore_case: bool) -> Result<Regex> {
+    let invalid = || SledoViewError::InvalidRegex {
+        pattern: bytes::escape(pattern),
+    };
+    let regex = if is_regex {
+        std::str::from_utf8(pattern)
+            .map_err(|_| invalid())?
+            .to_string()
+    } else {
+        glob_to_regex(pattern)
+    };
+    let regex = RegexBuilder::new(&regex)
+        .case_insensitive(ignore_case)
+        .build();
+    Ok(regex.map_err(|_| invalid())?)
+}
+
+/// The bytes every match of a glob starts with: everything before its first
+/// wildcard or character class
+fn glob_literal_prefix(pattern: &[u8]) -> &[u8] {
+    let end = pattern
+        .iter()
+        .position(|b| matches!(b, b'*' | b'?' | b'[' | b']'))
+        .unwrap_or(pattern.len());
+    &pattern[..end]
+}
+
+/// The first key after all keys starting with `prefix`, or `None` when no
+/// such key exists (the prefix is empty or all `0xff`)
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xff)?;
+    let mut end = prefix[..=last].to_vec();
+    end[last] += 1;
+    Some(end)
+}
+
+/// Translate a glob into a byte regex. `*` and `?` also match bytes that are
+/// not valid UTF-8, so binary keys can be matched by glob. Each wildcard is
+/// a capture group, numbered from 1, for `cp` and `mv` targets.
+fn glob_to_regex(pattern: &[u8]) -> String {
     let mut regex = String::new();
     regex.push('^');
 
-    for ch in pattern.chars() {
-        match ch {
-            '*' => regex.push_str(".*"),
-            '?' => regex.push('.'),
-            '[' => regex.push('['),
-            ']' => regex.push(']'),
-            '\\' => regex.push_str("\\\\"),
-            '^' => regex.push_str("\\^"),
-            '$' => regex.push_str("\\$"),
-            '.' => regex.push_str("\\."),
-            '|' => regex.push_str("\\|"),
-            '+' => regex.push_str("\\+"),
-            '(' => regex.push_str("\\("),
-            ')' => regex.push_str("\\)"),
-            '{' => regex.push_str("\\{"),
-            '}' => regex.push_str("\\}"),
-            c => regex.push(c),
+    for chunk in pattern.utf8_chunks() {
+        for ch in chunk.valid().chars() {
+            match ch {
+                '*' => regex.push_str("((?s-u:.)*)"),
+                '?' => regex.push_str("((?s:.)|(?s-u:.))"),
+                '[' => regex.push('['),
+                ']' => regex.push(']'),
+                '\\' => regex.push_str("\\\\"),
+                '^' => regex.push_str("\\^"),
+                '$' => regex.push_str("\\$"),
+                '.' => regex.push_str("\\."),
+                '|' => regex.push_str("\\|"),
+                '+' => regex.push_str("\\+"),
+                '(' => regex.push_str("\\("),
+                ')' => regex.push_str("\\)"),
+                '{' => regex.push_str("\\{"),
+                '}' => regex.push_str("\\}"),
+                c if c.is_control() => regex.push_str(&format!("\\x{{{:x}}}", c as u32)),
+                c => regex.push(c),
+            }
+        }
+        // Bytes that are not UTF-8 are matched exactly
+        for byte in chunk.invalid() {
+            regex.push_str(&format!("(?-u:\\x{:02x})", byte));
         }
     }
 
@@ -374,6 +1574,7 @@ fn glob_to_regex(pattern: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filter::ValueKind;
     use tempfile::TempDir;
 
     fn create_test_db() -> TempDir {
@@ -389,11 +1590,823 @@ mod tests {
 
     #[test]
     fn test_glob_to_regex() {
-        assert_eq!(glob_to_regex("*"), "^.*$");
-        assert_eq!(glob_to_regex("test*"), "^test.*$");
-        assert_eq!(glob_to_regex("*test"), "^.*test$");
-        assert_eq!(glob_to_regex("test?"), "^test.$");
-        assert_eq!(glob_to_regex("test.txt"), "^test\\.txt$");
+        assert_eq!(glob_to_regex(b"*"), "^((?s-u:.)*)$");
+        assert_eq!(glob_to_regex(b"test*"), "^test((?s-u:.)*)$");
+        assert_eq!(glob_to_regex(b"*test"), "^((?s-u:.)*)test$");
+        assert_eq!(glob_to_regex(b"test?"), "^test((?s:.)|(?s-u:.))$");
+        assert_eq!(glob_to_regex(b"test.txt"), "^test\\.txt$");
+        assert_eq!(
+            glob_to_regex(&[0, 0xff, b'*']),
+            "^\\x{0}(?-u:\\xff)((?s-u:.)*)$"
+        );
+
+        // Each wildcard is a group
+        let regex = compile_pattern(b"user_?_*", false, false).unwrap();
+        let captures = regex.captures(b"user_7_abc").unwrap();
+        assert_eq!(&captures[1], b"7");
+        assert_eq!(&captures[2], b"abc");
+    }
+
+    #[test]
+    fn test_glob_literal_prefix() {
+        assert_eq!(glob_literal_prefix(b"user_*"), b"user_");
+        assert_eq!(glob_literal_prefix(b"user_?_[ab]*"), b"user_");
+        assert_eq!(glob_literal_prefix(b"*_id"), b"");
+        assert_eq!(glob_literal_prefix(b"exact"), b"exact");
+        assert_eq!(glob_literal_prefix(&[0, 0xff, b'*']), [0, 0xff]);
+    }
+
+    /// The displayed keys of a page of entries, and whether more follow
+    fn page_keys(page: ScanPage) -> (Vec<String>, bool) {
+        match page {
+            ScanPage::Keys(listing)
+            | ScanPage::Values(listing)
+            | ScanPage::Projection {
+                entries: listing, ..
+            } => (
+                listing.iter().map(|info| info.display_key()).collect(),
+                listing.more,
+            ),
+            ScanPage::Trees(listing) => (
+                listing.iter().map(|name| bytes::escape(name)).collect(),
+                listing.more,
+            ),
+        }
+    }
+
+    #[test]
+    fn test_range_and_prefix_scans() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for key in ["user_1", "user_2", "user_3", "users", "video_1"] {
+            viewer.set_key(key, "v").unwrap();
+        }
+        let mut keys =
+            |scan: Scan| page_keys(viewer.scan(scan, Page::default(), &mut ()).unwrap()).0;
+
+        let range = |start: &str, end: &str| Scan::Range {
+            start: start.into(),
+            end: end.into(),
+        };
+        assert_eq!(keys(range("user_2", "users")), vec!["user_2", "user_3"]);
+        assert!(keys(range("b", "b")).is_empty());
+        assert_eq!(
+            keys(Scan::Prefix(b"user_".to_vec())),
+            vec!["user_1", "user_2", "user_3"]
+        );
+        assert_eq!(keys(Scan::Prefix(Vec::new())).len(), 7);
+
+        let err = viewer
+            .scan(range("z", "a"), Page::default(), &mut ())
+            .unwrap_err();
+        assert!(err.to_string().contains("'z' sorts after 'a'"));
+
+        // Globs with a literal prefix still match exactly
+        assert_eq!(
+            viewer.list_keys("user?", false).unwrap(),
+            vec![b"users".to_vec()]
+        );
+        assert_eq!(viewer.list_keys("*_1", false).unwrap().len(), 2);
+        assert_eq!(prefix_end(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_end(&[b'a', 0xff]), Some(b"b".to_vec()));
+        assert_eq!(prefix_end(&[0xff]), None);
+    }
+
+    #[test]
+    fn test_scan_pages() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for i in 1..=5 {
+            viewer.set_key(format!("user_{i}"), "v").unwrap();
+        }
+        let users = Scan::Keys {
+            pattern: b"user_*".to_vec(),
+            is_regex: false,
+            filter: Filter::default(),
+        };
+        let page = Page {
+            offset: 1,
+            limit: Some(2),
+            ..Page::default()
+        };
+
+        let (keys, more) = page_keys(viewer.scan(users.clone(), page, &mut ()).unwrap());
+        assert_eq!((keys, more), (vec!["user_2".into(), "user_3".into()], true));
+
+        // Keys written before the cursor do not shift the next page
+        viewer.set_key("user_0", "v").unwrap();
+        let next = viewer.scan_more(None, &mut ()).unwrap();
+        let ScanPage::Keys(listing) = &next else {
+            panic!("unexpected page: {:?}", next);
+        };
+        assert_eq!(listing.start, 3);
+        assert_eq!(page_keys(next).0, vec!["user_4", "user_5"]);
+        // The page ended exactly at the last key, so nothing is left
+        assert!(viewer.scan_more(None, &mut ()).is_err());
+
+        // Selecting another tree forgets the cursor
+        viewer.scan(users, page, &mut ()).unwrap();
+        viewer.create_tree("other").unwrap();
+        viewer.select_tree("other").unwrap();
+        assert!(viewer.scan_more(None, &mut ()).is_err());
+
+        assert!(viewer.tail(2).unwrap().is_empty());
+        viewer.unselect_tree().unwrap();
+        let tail: Vec<String> = viewer
+            .tail(2)
+            .unwrap()
+            .iter()
+            .map(KeyInfo::display_key)
+            .collect();
+        assert_eq!(tail, vec!["user_4", "user_5"]);
+    }
+
+    /// Records what a scan reports, cancelling it after `cancel_after` keys
+    #[derive(Default)]
+    struct Recorder {
+        scanned: usize,
+        matched: Vec<(usize, String)>,
+        cancel_after: Option<usize>,
+    }
+
+    impl ScanObserver for Recorder {
+        fn scanned(&mut self, count: usize) -> Result<(), SledoViewError> {
+            self.scanned = count;
+            match self.cancel_after {
+                Some(limit) if count > limit => Err(SledoViewError::Interrupted),
+                _ => Ok(()),
+            }
+        }
+
+        fn matched(&mut self, _scan: &Scan, position: usize, info: &KeyInfo) {
+            self.matched.push((position, info.display_key()));
+        }
+    }
+
+    #[test]
+    fn test_scan_filters() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        viewer.set_key("User_A", "Alice Smith").unwrap();
+        viewer.set_key("user_b", "bob").unwrap();
+        viewer.set_key("user_c", "").unwrap();
+        viewer.set_key("blob", [0, 255]).unwrap();
+        viewer.set_key("big", "x".repeat(2000)).unwrap();
+
+        let mut keys = |scan: Scan, observer: &mut dyn ScanObserver| {
+            let page = viewer.scan(scan, Page::default(), observer).unwrap();
+            page_keys(page).0
+        };
+        let list = |pattern: &[u8], filter: Filter| Scan::Keys {
+            pattern: pattern.to_vec(),
+            is_regex: false,
+            filter,
+        };
+
+        let ignore_case = Filter {
+            ignore_case: true,
+            ..Filter::default()
+        };
+        assert_eq!(
+            keys(list(b"user_*", ignore_case), &mut ()),
+            vec!["User_A", "user_b", "user_c"]
+        );
+
+        // All conditions must hold
+        let filter = Filter {
+            key: Some("*_b".to_string()),
+            value: Some("^b".to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(keys(list(b"user_*", filter), &mut ()), vec!["user_b"]);
+
+        let large = Filter {
+            min_size: Some(1024),
+            ..Filter::default()
+        };
+        assert_eq!(keys(list(b"*", large), &mut ()), vec!["big"]);
+        let empty = Filter {
+            empty: true,
+            ..Filter::default()
+        };
+        assert_eq!(keys(list(b"*", empty), &mut ()), vec!["user_c"]);
+        let binary = Filter {
+            kind: Some(ValueKind::Binary),
+            ..Filter::default()
+        };
+        assert_eq!(keys(list(b"*", binary), &mut ()), vec!["blob"]);
+
+        // A `--key` glob limits the keys read like a `list` glob does
+        let search = Scan::Values {
+            pattern: b"*".to_vec(),
+            is_regex: false,
+            filter: Filter {
+                key: Some("user_*".to_string()),
+                kind: Some(ValueKind::Text),
+                ..Filter::default()
+            },
+        };
+        let mut recorder = Recorder::default();
+        assert_eq!(keys(search, &mut recorder), vec!["user_b", "user_c"]);
+        assert_eq!(recorder.scanned, 2);
+
+        // Entries tell where the patterns matched
+        let search = Scan::Values {
+            pattern: b"smi".to_vec(),
+            is_regex: true,
+            filter: Filter {
+                key: Some("user*".to_string()),
+                ignore_case: true,
+                ..Filter::default()
+            },
+        };
+        let ScanPage::Values(found) = viewer.scan(search, Page::default(), &mut ()).unwrap() else {
+            panic!("expected search results");
+        };
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].matched,
+            Matched {
+                key: Some(0..6),
+                value: Some(6..9)
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_scan() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        viewer
+            .set_key("job_1", r#"{"status":"failed","retries":4}"#)
+            .unwrap();
+        viewer
+            .set_key("job_2", r#"{"status":"failed","retries":1}"#)
+            .unwrap();
+        viewer.set_key("job_3", r#"{"status":"done"}"#).unwrap();
+        viewer
+            .set_key("run_1", r#"{"status":"failed","retries":9}"#)
+            .unwrap();
+        viewer.set_key("job_4", "failed").unwrap();
+
+        let query = |query: &str, key: Option<&str>| Scan::Query {
+            query: query.to_string(),
+            select: Vec::new(),
+            filter: Filter {
+                key: key.map(str::to_string),
+                ..Filter::default()
+            },
+        };
+        let mut keys =
+            |scan: Scan| page_keys(viewer.scan(scan, Page::default(), &mut ()).unwrap()).0;
+
+        assert_eq!(
+            keys(query("$.status == 'failed' && $.retries > 3", None)),
+            vec!["job_1", "run_1"]
+        );
+        assert_eq!(
+            keys(query("$.status == 'failed'", Some("job_*"))),
+            vec!["job_1", "job_2"]
+        );
+        // Values that are not JSON documents never match
+        assert_eq!(keys(query("!$.retries", Some("job_*"))), vec!["job_3"]);
+
+        let select = Scan::Query {
+            query: "$.retries".to_string(),
+            select: vec![query::Path::parse("$.retries").unwrap()],
+            filter: Filter::default(),
+        };
+        let page = viewer.scan(select, Page::default(), &mut ()).unwrap();
+        assert!(matches!(page, ScanPage::Projection { columns, entries }
+            if columns.len() == 1 && entries.len() == 3));
+
+        let invalid = viewer.scan(query("$.status ==", None), Page::default(), &mut ());
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_scan_reports_to_observer() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for i in 0..20 {
+            viewer
+                .set_key(format!("key_{i:02}"), format!("{}", i % 2))
+                .unwrap();
+        }
+        let odd = Scan::Values {
+            pattern: b"1".to_vec(),
+            is_regex: false,
+            filter: Filter::default(),
+        };
+        let page = Page {
+            offset: 1,
+            limit: Some(2),
+            ..Page::default()
+        };
+
+        let mut recorder = Recorder::default();
+        viewer.scan(odd.clone(), page, &mut recorder).unwrap();
+        // Offset entries are skipped silently and reading stops after the page
+        assert_eq!(
+            recorder.matched,
+            vec![(1, "key_03".to_string()), (2, "key_05".to_string())]
+        );
+        assert_eq!(recorder.scanned, 9);
+
+        let mut recorder = Recorder {
+            cancel_after: Some(5),
+            ..Recorder::default()
+        };
+        let err = viewer
+            .scan(odd, Page::default(), &mut recorder)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SledoViewError>(),
+            Some(SledoViewError::Interrupted)
+        ));
+        assert_eq!(recorder.scanned, 6);
+        assert_eq!(recorder.matched.len(), 2);
+    }
+
+    #[test]
+    fn test_transaction() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        assert!(viewer.commit().is_err());
+        assert!(viewer.rollback().is_err());
+
+        viewer.begin().unwrap();
+        assert!(viewer.begin().is_err());
+        viewer.set_key("new_key", "staged").unwrap();
+        viewer.set_key("new_key", "staged again").unwrap();
+        assert!(viewer.delete_key("test_key").unwrap());
+        assert!(!viewer.delete_key("missing").unwrap());
+        viewer.create_tree("other").unwrap();
+        viewer.select_tree("other").unwrap();
+        viewer.set_key("other_key", "v").unwrap();
+
+        // Staged writes are read back, but not yet in the database
+        let staged = viewer.staged().unwrap();
+        assert_eq!(staged.len(), 3);
+        assert_eq!(staged[0].tree, DEFAULT_TREE_NAME);
+        assert_eq!(staged[0].value.as_deref(), Some(&b"staged again"[..]));
+        assert_eq!(staged[2].tree, b"other");
+        assert_eq!(viewer.get_key("other_key").unwrap().value, b"v");
+        assert_eq!(viewer.count().unwrap(), 0);
+        viewer.unselect_tree().unwrap();
+        assert!(viewer.get_key("test_key").is_err());
+        assert_eq!(viewer.count().unwrap(), 2);
+
+        assert_eq!(viewer.commit().unwrap(), 3);
+        assert!(viewer.staged().is_none());
+        assert_eq!(viewer.get_key("new_key").unwrap().value, b"staged again");
+        assert!(viewer.get_key("test_key").is_err());
+        viewer.select_tree("other").unwrap();
+        assert_eq!(viewer.count().unwrap(), 1);
+
+        viewer.begin().unwrap();
+        viewer.delete_key("other_key").unwrap();
+        assert_eq!(viewer.rollback().unwrap(), 1);
+        assert_eq!(viewer.get_key("other_key").unwrap().value, b"v");
+    }
+
+    #[test]
+    fn test_delete_keys() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for key in ["session_1", "session_2", "session_3", "user_1"] {
+            viewer.set_key(key, "v").unwrap();
+        }
+        let sessions = Scan::Keys {
+            pattern: b"session_*".to_vec(),
+            is_regex: false,
+            filter: Filter::default(),
+        };
+        let keys = viewer.matching_keys(&sessions, &mut ()).unwrap();
+        assert_eq!(keys, [&b"session_1"[..], b"session_2", b"session_3"]);
+        let range = Scan::Range {
+            start: b"session_2".to_vec(),
+            end: b"session_9".to_vec(),
+        };
+        assert_eq!(viewer.matching_keys(&range, &mut ()).unwrap().len(), 2);
+
+        viewer.begin().unwrap();
+        assert_eq!(viewer.delete_keys(&keys[..1]).unwrap(), 1);
+        assert_eq!(viewer.staged().unwrap().len(), 1);
+        viewer.rollback().unwrap();
+
+        assert_eq!(viewer.delete_keys(&keys).unwrap(), 3);
+        assert!(viewer.matching_keys(&sessions, &mut ()).unwrap().is_empty());
+        assert!(viewer.get_key("user_1").is_ok());
+    }
+
+    #[test]
+    fn test_tree_lifecycle() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+        assert!(viewer.create_tree("users").unwrap());
+        viewer.select_tree("users").unwrap();
+        viewer.set_key("u1", "a").unwrap();
+        viewer.set_key("u2", [0xff]).unwrap();
+
+        // Copies keep values as stored, and never overwrite a tree
+        assert_eq!(viewer.copy_tree(b"users", b"users_old").unwrap(), 2);
+        assert!(viewer.copy_tree(b"users", b"users_old").is_err());
+        assert!(viewer.copy_tree(b"missing", b"other").is_err());
+
+        // The selection follows a renamed tree and is dropped with it
+        assert_eq!(viewer.rename_tree(b"users", b"accounts").unwrap(), 2);
+        assert_eq!(viewer.get_selected_tree(), Some(&b"accounts"[..]));
+        assert_eq!(viewer.get_key("u2").unwrap().value, [0xff]);
+        assert_eq!(viewer.drop_tree(b"accounts").unwrap(), 2);
+        assert!(viewer.get_selected_tree().is_none());
+        assert_eq!(
+            viewer.list_trees("*", false).unwrap(),
+            vec![b"users_old".to_vec()]
+        );
+
+        assert!(viewer.drop_tree(DEFAULT_TREE_NAME).is_err());
+        assert!(viewer.rename_tree(DEFAULT_TREE_NAME, b"x").is_err());
+
+        // Whole-tree changes cannot be staged in a transaction
+        viewer.begin().unwrap();
+        assert!(viewer.clear_tree(b"users_old").is_err());
+        viewer.rollback().unwrap();
+
+        assert_eq!(viewer.clear_tree(b"users_old").unwrap(), 2);
+        assert!(viewer.tree_exists(b"users_old"));
+        viewer.select_tree("users_old").unwrap();
+        assert_eq!(viewer.count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_copy_keys() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let pair = |from: &str, to: &str| (from.as_bytes().to_vec(), to.as_bytes().to_vec());
+        let value =
+            |viewer: &SledViewer, key: &str| viewer.get_key(key).ok().map(|info| info.value);
+
+        // Keys can be moved onto the names of keys moved away
+        viewer.set_key("a", "1").unwrap();
+        viewer.set_key("b", "2").unwrap();
+        let rotate = [pair("a", "b"), pair("b", "c")];
+        assert_eq!(viewer.copy_keys(&rotate, None, true, false).unwrap(), 2);
+        assert_eq!(value(&viewer, "a"), None);
+        assert_eq!(value(&viewer, "b"), Some(b"1".to_vec()));
+        assert_eq!(value(&viewer, "c"), Some(b"2".to_vec()));
+
+        // One conflict and nothing is written
+        let err = viewer
+            .copy_keys(&[pair("b", "x"), pair("c", "test_key")], None, false, false)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SledoViewError>(),
+            Some(SledoViewError::Conflict { .. })
+        ));
+        assert_eq!(value(&viewer, "x"), None);
+        let err = viewer
+            .copy_keys(&[pair("b", "x"), pair("c", "x")], None, false, false)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Several keys would be written to x");
+        assert!(viewer
+            .copy_keys(&[pair("missing", "x")], None, false, false)
+            .is_err());
+
+        // A missing target tree is not created, directly or in a transaction
+        let not_found = |err: anyhow::Error| {
+            matches!(
+                err.downcast_ref::<SledoViewError>(),
+                Some(SledoViewError::TreeNotFound { .. })
+            )
+        };
+        let err = viewer
+            .copy_keys(&[pair("b", "b")], Some(b"archiv"), false, false)
+            .unwrap_err();
+        assert!(not_found(err));
+        viewer.begin().unwrap();
+        let err = viewer
+            .copy_keys(&[pair("b", "b")], Some(b"archiv"), true, false)
+            .unwrap_err();
+        assert!(not_found(err));
+        assert!(viewer.staged().unwrap().is_empty());
+        viewer.rollback().unwrap();
+        assert!(!viewer.tree_exists(b"archiv"));
+
+        // Across trees, and staged in a transaction
+        viewer.create_tree("archive").unwrap();
+        viewer.begin().unwrap();
+        viewer
+            .copy_keys(&[pair("b", "b")], Some(b"archive"), true, false)
+            .unwrap();
+        assert_eq!(viewer.staged().unwrap().len(), 2);
+        assert_eq!(value(&viewer, "b"), None);
+        viewer.commit().unwrap();
+        viewer.select_tree("archive").unwrap();
+        assert_eq!(value(&viewer, "b"), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+        viewer
+            .compare_and_swap("balance", None, Some(b"50"))
+            .unwrap();
+        let err = viewer
+            .compare_and_swap("balance", None, Some(b"60"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Conflict on key balance: expected no value, found '50'"
+        );
+        let err = viewer
+            .compare_and_swap("balance", Some(b"40"), Some(b"60"))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SledoViewError>(),
+            Some(SledoViewError::Conflict { current, .. }) if current == "'50'"
+        ));
+        viewer
+            .compare_and_swap("balance", Some(b"50"), Some(b"60"))
+            .unwrap();
+        assert_eq!(viewer.get_key("balance").unwrap().value, b"60");
+
+        // In a transaction the staged value is checked, and the committed
+        // one again on commit
+        viewer.begin().unwrap();
+        viewer
+            .compare_and_swap("balance", Some(b"60"), Some(b"70"))
+            .unwrap();
+        viewer
+            .compare_and_swap("balance", Some(b"70"), Some(b"80"))
+            .unwrap();
+        assert!(viewer
+            .compare_and_swap("balance", Some(b"60"), Some(b"90"))
+            .is_err());
+        assert_eq!(
+            viewer.staged().unwrap()[0].expected,
+            Some(Some(b"60".to_vec()))
+        );
+        viewer
+            .current_tree()
+            .unwrap()
+            .insert("balance", "65")
+            .unwrap();
+        let err = viewer.commit().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Conflict on key balance: expected '60', found '65'"
+        );
+        assert!(viewer.staged().is_some());
+        assert_eq!(viewer.rollback().unwrap(), 1);
+        assert_eq!(viewer.get_key("balance").unwrap().value, b"65");
+    }
+
+    #[test]
+    fn test_scan_sorted() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for (key, value) in [
+            ("user_10", "bb"),
+            ("user_9", "a"),
+            ("user_2", "cccc"),
+            ("user_1", "ccc"),
+        ] {
+            viewer.set_key(key, value).unwrap();
+        }
+        let users = Scan::Keys {
+            pattern: b"user_*".to_vec(),
+            is_regex: false,
+            filter: Filter::default(),
+        };
+        let mut keys = |sort: Sort, reverse: bool| {
+            let page = Page {
+                sort,
+                reverse,
+                ..Page::default()
+            };
+            page_keys(viewer.scan(users.clone(), page, &mut ()).unwrap()).0
+        };
+
+        assert_eq!(
+            keys(Sort::Bytes, false),
+            ["user_1", "user_10", "user_2", "user_9"]
+        );
+        assert_eq!(
+            keys(Sort::Bytes, true),
+            ["user_9", "user_2", "user_10", "user_1"]
+        );
+        assert_eq!(
+            keys(Sort::Key, false),
+            ["user_1", "user_2", "user_9", "user_10"]
+        );
+        assert_eq!(
+            keys(Sort::Size, true),
+            ["user_2", "user_1", "user_10", "user_9"]
+        );
+        assert_eq!(
+            keys(Sort::Value, false),
+            ["user_9", "user_10", "user_1", "user_2"]
+        );
+
+        // Sorted pages continue by position
+        let page = Page {
+            limit: Some(3),
+            sort: Sort::Key,
+            reverse: true,
+            ..Page::default()
+        };
+        let (keys, more) = page_keys(viewer.scan(users.clone(), page, &mut ()).unwrap());
+        assert_eq!(
+            (keys, more),
+            (
+                vec!["user_10".into(), "user_9".into(), "user_2".into()],
+                true
+            )
+        );
+        assert_eq!(
+            page_keys(viewer.scan_more(None, &mut ()).unwrap()),
+            (vec!["user_1".into()], false)
+        );
+
+        // Reverse byte order continues before the last key
+        let page = Page {
+            limit: Some(2),
+            reverse: true,
+            ..Page::default()
+        };
+        viewer.scan(users, page, &mut ()).unwrap();
+        viewer.set_key("user_3", "v").unwrap();
+        assert_eq!(
+            page_keys(viewer.scan_more(None, &mut ()).unwrap()),
+            (vec!["user_10".into(), "user_1".into()], false)
+        );
+    }
+
+    #[test]
+    fn test_scan_tree_pages() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        for tree in ["a", "b", "c"] {
+            viewer.create_tree(tree).unwrap();
+            viewer.select_tree(tree).unwrap();
+        }
+        viewer.unselect_tree().unwrap();
+
+        let trees = Scan::Trees {
+            pattern: b"*".to_vec(),
+            is_regex: false,
+        };
+        let page = Page {
+            offset: 0,
+            limit: Some(2),
+            ..Page::default()
+        };
+        assert_eq!(
+            page_keys(viewer.scan(trees.clone(), page, &mut ()).unwrap()).0,
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            page_keys(viewer.scan_more(None, &mut ()).unwrap()),
+            (vec!["c".into()], false)
+        );
+
+        let reverse = Page {
+            reverse: true,
+            ..page
+        };
+        assert_eq!(
+            page_keys(viewer.scan(trees.clone(), reverse, &mut ()).unwrap()).0,
+            vec!["c", "b"]
+        );
+        assert_eq!(
+            page_keys(viewer.scan_more(None, &mut ()).unwrap()),
+            (vec!["a".into()], false)
+        );
+        let by_size = Page {
+            sort: Sort::Size,
+            ..page
+        };
+        assert!(viewer.scan(trees, by_size, &mut ()).is_err());
+    }
+
+    /// The entries of a page as `tree/key`
+    fn tree_keys(page: ScanPage) -> (Vec<String>, bool) {
+        let (ScanPage::Keys(listing) | ScanPage::Values(listing)) = page else {
+            panic!("unexpected page: {:?}", page);
+        };
+        let keys = listing
+            .iter()
+            .map(|info| {
+                let tree = bytes::escape(info.tree.as_deref().unwrap());
+                format!("{}/{}", tree, info.display_key())
+            })
+            .collect();
+        (keys, listing.more)
+    }
+
+    #[test]
+    fn test_scan_across_trees() {
+        let temp_dir = create_test_db();
+        {
+            let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+            for (tree, key) in [
+                ("users_a", "user_1"),
+                ("users_a", "user_2"),
+                ("users_b", "user_1"),
+                ("archive", "user_3"),
+            ] {
+                viewer.create_tree(tree).unwrap();
+                viewer.select_tree(tree).unwrap();
+                viewer.set_key(key, "v").unwrap();
+            }
+        }
+
+        let mut viewer = SledViewer::new_read_only(temp_dir.path()).unwrap();
+        let users = Scan::Keys {
+            pattern: b"user_*".to_vec(),
+            is_regex: false,
+            filter: Filter::default(),
+        };
+        let page = Page {
+            offset: 0,
+            limit: Some(2),
+            ..Page::default()
+        };
+
+        // Trees are read in name order, and paging continues across them
+        assert_eq!(
+            tree_keys(
+                viewer
+                    .scan_trees(b"*", users.clone(), page, &mut ())
+                    .unwrap()
+            ),
+            (vec!["archive/user_3".into(), "users_a/user_1".into()], true)
+        );
+        assert_eq!(
+            tree_keys(viewer.scan_more(None, &mut ()).unwrap()),
+            (
+                vec!["users_a/user_2".into(), "users_b/user_1".into()],
+                false
+            )
+        );
+
+        // In reverse, so are the trees
+        let reverse = Page {
+            reverse: true,
+            ..page
+        };
+        assert_eq!(
+            tree_keys(
+                viewer
+                    .scan_trees(b"*", users.clone(), reverse, &mut ())
+                    .unwrap()
+            ),
+            (vec!["users_b/user_1".into(), "users_a/user_2".into()], true)
+        );
+        assert_eq!(
+            tree_keys(viewer.scan_more(None, &mut ()).unwrap()),
+            (
+                vec!["users_a/user_1".into(), "archive/user_3".into()],
+                false
+            )
+        );
+
+        let values = Scan::Values {
+            pattern: b"*value".to_vec(),
+            is_regex: false,
+            filter: Filter::default(),
+        };
+        let (keys, _) = tree_keys(
+            viewer
+                .scan_trees(b"*", values, Page::default(), &mut ())
+                .unwrap(),
+        );
+        assert_eq!(
+            keys,
+            vec!["__sled__default/another_key", "__sled__default/test_key"]
+        );
+
+        let (keys, _) = tree_keys(
+            viewer
+                .scan_trees(b"users_*", users, Page::default(), &mut ())
+                .unwrap(),
+        );
+        assert_eq!(keys.len(), 3);
+
+        let trees = |key| -> Vec<String> {
+            let found = viewer.locate(key).unwrap();
+            found
+                .iter()
+                .map(|info| bytes::escape(info.tree.as_deref().unwrap()))
+                .collect()
+        };
+        assert_eq!(trees("user_1"), vec!["users_a", "users_b"]);
+        assert_eq!(trees("test_key"), vec!["__sled__default"]);
+        assert!(trees("missing").is_empty());
     }
 
     #[test]
@@ -404,52 +2417,196 @@ mod tests {
     }
 
     #[test]
-    fn test_key_info_debug() {
-        let info = KeyInfo {
-            key: "test".to_string(),
-            value: "value".to_string(),
-            size: 5,
-            is_utf8: true,
-        };
-        let debug_str = format!("{:?}", info);
-        assert!(debug_str.contains("test"));
-        assert!(debug_str.contains("value"));
+    fn test_key_info_new() {
+        let info = KeyInfo::new(b"test", b"value");
+        assert_eq!(info.key, b"test");
+        assert_eq!(info.value, b"value");
+        assert_eq!(info.size, 5);
+        assert_eq!(info.content_type, ContentType::Text);
+
+        let info = KeyInfo::new(&[0, 1], &[0xff, 0xfe]);
+        assert_eq!(info.size, 2);
+        assert_eq!(info.content_type, ContentType::Binary);
+        assert_eq!(info.value_lossy(), "\u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn test_binary_keys_round_trip() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let key = [0u8, 0, 0, 42, 0xff];
+        viewer.set_key(key, [0xde, 0xad]).unwrap();
+
+        let keys = viewer.list_keys("*", false).unwrap();
+        assert!(keys.contains(&key.to_vec()));
+        // Listed keys can be fetched back exactly
+        for key in &keys {
+            assert!(viewer.get_key(key).is_ok());
+        }
+        assert_eq!(viewer.get_key(key).unwrap().value, [0xde, 0xad]);
+
+        let keys = viewer.list_keys(r"(?-u)^\x00\x00\x00\*", true).unwrap();
+        assert_eq!(keys, vec![key.to_vec()]);
+
+        let err = viewer.get_key([0xff, 0x00]).unwrap_err();
+        assert!(err.to_string().contains("\\xff\\x00"));
+    }
+
+    #[test]
+    fn test_binary_tree_names() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let tree = [b't', 0xff];
+        viewer.create_tree(tree).unwrap();
+        viewer.select_tree(tree).unwrap();
+        viewer.set_key("k", "v").unwrap();
+        viewer.unselect_tree().unwrap();
+
+        assert_eq!(viewer.list_trees("t?", false).unwrap(), vec![tree.to_vec()]);
+        viewer.select_tree(tree).unwrap();
+        assert_eq!(viewer.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_select_tree_applies_profile() {
+        use crate::codec::KeyCodec;
+        use crate::decode::{BincodeType, Decoder};
+
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        viewer.set_profile(
+            Profile::from_toml(
+                "[[tree]]\nname = \"ids_*\"\nkey = \"hex\"\nvalue = \"bincode:u64\"\n",
+            )
+            .unwrap(),
+        );
+        assert!(viewer.codecs().is_default());
+
+        viewer.create_tree("ids_users").unwrap();
+        viewer.select_tree("ids_users").unwrap();
+        viewer.set_key([0, 0, 0, 1], 7u64.to_le_bytes()).unwrap();
+        assert_eq!(viewer.codecs().key, KeyCodec::Hex);
+
+        let info = viewer.get_key([0, 0, 0, 1]).unwrap();
+        assert_eq!(info.display_key(), "0x00000001");
+        assert_eq!(info.codecs.value, Some(Decoder::Bincode(BincodeType::U64)));
+
+        viewer.unselect_tree().unwrap();
+        assert!(viewer.codecs().is_default());
+    }
+
+    #[test]
+    fn test_list_keys_with_typed_codec() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        viewer.set_profile(
+            Profile::from_toml("[[tree]]\nname = \"ids\"\nkey = \"u64be\"\n").unwrap(),
+        );
+        viewer.create_tree("ids").unwrap();
+        viewer.select_tree("ids").unwrap();
+        for id in [4u64, 42, 400, 7] {
+            viewer.set_key(id.to_be_bytes(), "v").unwrap();
+        }
+
+        let first: Vec<Vec<u8>> = viewer
+            .iter_keys()
+            .unwrap()
+            .take(2)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(first, [4u64.to_be_bytes(), 7u64.to_be_bytes()]);
+
+        // Patterns match the keys as displayed, results stay in byte order
+        let keys = viewer.list_keys("4*", false).unwrap();
+        let shown: Vec<String> = keys
+            .iter()
+            .map(|k| viewer.codecs().key.display(k))
+            .collect();
+        assert_eq!(shown, vec!["4", "42", "400"]);
+
+        let key = viewer.codecs().key.parse("42").unwrap();
+        assert!(viewer.get_key(key).is_ok());
     }
 
     #[test]
-    fn test_key_value_pair_debug() {
-        let pair = KeyValuePair {
-            key: "test".to_string(),
-            value: "value".to_string(),
+    fn test_range_by_value() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+        viewer.set_profile(
+            Profile::from_toml(
+                "[[tree]]\nname = \"le\"\nkey = \"u64le\"\n\
+                 [[tree]]\nname = \"pairs\"\nkey = \"tuple(u32be, i64be)\"\n",
+            )
+            .unwrap(),
+        );
+        let mut range = |tree: &str, keys: &[&str], start: &str, end: &str| {
+            viewer.create_tree(tree).unwrap();
+            viewer.select_tree(tree).unwrap();
+            let codec = viewer.codecs().key.clone();
+            for key in keys {
+                viewer.set_key(codec.parse(key).unwrap(), "v").unwrap();
+            }
+            let scan = Scan::Range {
+                start: codec.parse(start).unwrap(),
+                end: codec.parse(end).unwrap(),
+            };
+            let mut keys = page_keys(viewer.scan(scan, Page::default(), &mut ()).unwrap()).0;
+            keys.sort();
+            keys
+        };
+
+        // Byte order puts 256 before 1 in little-endian
+        assert_eq!(
+            range("le", &["1", "2", "256", "299", "300"], "1", "300"),
+            vec!["1", "2", "256", "299"]
+        );
+        assert_eq!(
+            range(
+                "pairs",
+                &["(7, -10)", "(7, 5)", "(7, -11)", "(8, 0)"],
+                "(7, -10)",
+                "(8, 0)"
+            ),
+            vec!["(7, -10)", "(7, 5)"]
+        );
+
+        // Bounds are ordered by value too
+        viewer.select_tree("le").unwrap();
+        let le_range = |start: u64, end: u64| Scan::Range {
+            start: start.to_le_bytes().to_vec(),
+            end: end.to_le_bytes().to_vec(),
         };
-        let debug_str = format!("{:?}", pair);
-        assert!(debug_str.contains("test"));
-        assert!(debug_str.contains("value"));
+        let page = viewer.scan(le_range(2, 256), Page::default(), &mut ());
+        assert_eq!(page_keys(page.unwrap()).0, vec!["2"]);
+        let err = viewer
+            .scan(le_range(300, 1), Page::default(), &mut ())
+            .unwrap_err();
+        assert!(err.to_string().contains("sorts after"));
     }
 
     #[test]
     fn test_set_key() {
         let temp_dir = create_test_db();
-        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
 
         // Test setting a new key
         assert!(viewer.set_key("new_key", "new_value").is_ok());
 
         // Verify the key was set
         let info = viewer.get_key("new_key").unwrap();
-        assert_eq!(info.key, "new_key");
-        assert_eq!(info.value, "new_value");
+        assert_eq!(info.key, b"new_key");
+        assert_eq!(info.value, b"new_value");
 
         // Test updating an existing key
         assert!(viewer.set_key("new_key", "updated_value").is_ok());
         let info = viewer.get_key("new_key").unwrap();
-        assert_eq!(info.value, "updated_value");
+        assert_eq!(info.value, b"updated_value");
     }
 
     #[test]
     fn test_delete_key() {
         let temp_dir = create_test_db();
-        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
 
         // First, set a key
         viewer.set_key("test_delete", "value").unwrap();
@@ -474,24 +2631,61 @@ mod tests {
 
         // Database should be writable in tests
         assert!(viewer.is_writable());
+        assert!(!viewer.is_read_only());
+
+        // Checking must not leave anything behind in the database
+        assert_eq!(viewer.count().unwrap(), 2);
+        assert!(viewer.list_keys("__sledoview*", false).unwrap().is_empty());
     }
 
     #[test]
-    fn test_set_with_special_characters() {
+    fn test_read_only_viewer() {
+        let temp_dir = create_test_db();
+        let viewer = SledViewer::new_read_only(temp_dir.path()).unwrap();
+
+        assert!(viewer.is_read_only());
+        assert!(!viewer.is_writable());
+        assert!(viewer.snapshot.is_none());
+        assert_eq!(viewer.get_key("test_key").unwrap().value, b"test_value");
+        drop(viewer);
+
+        // A snapshot is a read-only copy, reporting the original path
+        let viewer = SledViewer::new_snapshot(temp_dir.path()).unwrap();
+        assert!(viewer.is_read_only());
+        assert_eq!(viewer.path, temp_dir.path());
+        assert_ne!(viewer.snapshot.as_ref().unwrap().path(), temp_dir.path());
+        assert_eq!(viewer.get_key("test_key").unwrap().value, b"test_value");
+    }
+
+    #[test]
+    fn test_read_only_does_not_create_trees() {
         let temp_dir = create_test_db();
+        {
+            let mut viewer = SledViewer::new_read_only(temp_dir.path()).unwrap();
+            assert!(viewer.select_tree("missing_tree").is_err());
+            assert!(viewer.get_selected_tree().is_none());
+        }
+
         let viewer = SledViewer::new(temp_dir.path()).unwrap();
+        assert!(viewer.list_trees("*", false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_with_special_characters() {
+        let temp_dir = create_test_db();
+        let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
 
         // Test with spaces and special characters
         assert!(viewer
             .set_key("key with spaces", "value with spaces")
             .is_ok());
         let info = viewer.get_key("key with spaces").unwrap();
-        assert_eq!(info.value, "value with spaces");
+        assert_eq!(info.value, b"value with spaces");
 
         // Test with quotes and escapes
         assert!(viewer.set_key("quote_key", "value with \"quotes\"").is_ok());
         let info = viewer.get_key("quote_key").unwrap();
-        assert_eq!(info.value, "value with \"quotes\"");
+        assert_eq!(info.value, b"value with \"quotes\"");
     }
 
     #[test]
@@ -540,23 +2734,23 @@ mod tests {
 
         // Test listing all trees
         let trees = viewer.list_trees("*", false).unwrap();
-        assert!(trees.contains(&"settings".to_string()));
-        assert!(trees.contains(&"sessions".to_string()));
-        assert!(trees.contains(&"cache".to_string()));
-        assert!(trees.contains(&"my_tree_1".to_string()));
-        assert!(trees.contains(&"my_tree_2".to_string()));
+        assert!(trees.contains(&b"settings".to_vec()));
+        assert!(trees.contains(&b"sessions".to_vec()));
+        assert!(trees.contains(&b"cache".to_vec()));
+        assert!(trees.contains(&b"my_tree_1".to_vec()));
+        assert!(trees.contains(&b"my_tree_2".to_vec()));
 
         // Test pattern matching
         let trees = viewer.list_trees("my_tree_*", false).unwrap();
         assert_eq!(trees.len(), 2);
-        assert!(trees.contains(&"my_tree_1".to_string()));
-        assert!(trees.contains(&"my_tree_2".to_string()));
+        assert!(trees.contains(&b"my_tree_1".to_vec()));
+        assert!(trees.contains(&b"my_tree_2".to_vec()));
 
         // Test regex matching
         let trees = viewer.list_trees(r"my_tree_\d+", true).unwrap();
         assert_eq!(trees.len(), 2);
-        assert!(trees.contains(&"my_tree_1".to_string()));
-        assert!(trees.contains(&"my_tree_2".to_string()));
+        assert!(trees.contains(&b"my_tree_1".to_vec()));
+        assert!(trees.contains(&b"my_tree_2".to_vec()));
 
         // Test no matches
         let trees = viewer.list_trees("nonexistent_*", false).unwrap();
@@ -588,7 +2782,7 @@ mod tests {
 
         // Select a tree
         assert!(viewer.select_tree("test_tree").is_ok());
-        assert_eq!(viewer.get_selected_tree().unwrap(), "test_tree");
+        assert_eq!(viewer.get_selected_tree(), Some(&b"test_tree"[..]));
 
         // Unselect tree
         let was_selected = viewer.unselect_tree().unwrap();
@@ -630,24 +2824,24 @@ mod tests {
 
         // Test operations on default tree
         let keys = viewer.list_keys("*", false).unwrap();
-        assert!(keys.contains(&"key1".to_string()));
-        assert!(keys.contains(&"default_key".to_string()));
+        assert!(keys.contains(&b"key1".to_vec()));
+        assert!(keys.contains(&b"default_key".to_vec()));
         assert_eq!(keys.len(), 2);
 
         let info = viewer.get_key("key1").unwrap();
-        assert_eq!(info.value, "default_value1");
+        assert_eq!(info.value, b"default_value1");
 
         // Select tree1
         viewer.select_tree("tree1").unwrap();
 
         // Test operations on tree1
         let keys = viewer.list_keys("*", false).unwrap();
-        assert!(keys.contains(&"key1".to_string()));
-        assert!(keys.contains(&"key2".to_string()));
+        assert!(keys.contains(&b"key1".to_vec()));
+        assert!(keys.contains(&b"key2".to_vec()));
         assert_eq!(keys.len(), 2);
 
         let info = viewer.get_key("key1").unwrap();
-        assert_eq!(info.value, "tree1_value1");
+        assert_eq!(info.value, b"tree1_value1");
 
         // Test count
         assert_eq!(viewer.count().unwrap(), 2);
@@ -656,12 +2850,12 @@ mod tests {
         viewer.select_tree("tree2").unwrap();
 
         let keys = viewer.list_keys("*", false).unwrap();
-        assert!(keys.contains(&"key1".to_string()));
-        assert!(keys.contains(&"keyA".to_string()));
+        assert!(keys.contains(&b"key1".to_vec()));
+        assert!(keys.contains(&b"keyA".to_vec()));
         assert_eq!(keys.len(), 2);
 
         let info = viewer.get_key("key1").unwrap();
-        assert_eq!(info.value, "tree2_value1");
+        assert_eq!(info.value, b"tree2_value1");
 
         // Test count
         assert_eq!(viewer.count().unwrap(), 2);
@@ -685,13 +2879,13 @@ mod tests {
         // Test set on default tree
         viewer.set_key("default_key", "default_value").unwrap();
         let info = viewer.get_key("default_key").unwrap();
-        assert_eq!(info.value, "default_value");
+        assert_eq!(info.value, b"default_value");
 
         // Select tree and test set
         viewer.select_tree("test_tree").unwrap();
         viewer.set_key("tree_key", "tree_value").unwrap();
         let info = viewer.get_key("tree_key").unwrap();
-        assert_eq!(info.value, "tree_value");
+        assert_eq!(info.value, b"tree_value");
 
         // Key shouldn't exist in default tree
         viewer.unselect_tree().unwrap();
@@ -699,7 +2893,7 @@ mod tests {
 
         // But default key should still exist
         let info = viewer.get_key("default_key").unwrap();
-        assert_eq!(info.value, "default_value");
+        assert_eq!(info.value, b"default_value");
 
         // Test delete on tree
         viewer.select_tree("test_tree").unwrap();
@@ -739,26 +2933,26 @@ mod tests {
         // Search in default tree
         let results = viewer.search_values("*John*", false).unwrap();
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].key, "user_001");
-        assert_eq!(results[0].value, "Default John");
+        assert_eq!(results[0].key, b"user_001");
+        assert_eq!(results[0].value, b"Default John");
 
         // Select tree and search
         viewer.select_tree("search_tree").unwrap();
         let results = viewer.search_values("*John*", false).unwrap();
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].key, "user_001");
-        assert_eq!(results[0].value, "John Doe");
+        assert_eq!(results[0].key, b"user_001");
+        assert_eq!(results[0].value, b"John Doe");
 
         // Search with pattern
         let results = viewer.search_values("*Smith", false).unwrap();
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].value, "Jane Smith");
+        assert_eq!(results[0].value, b"Jane Smith");
 
         // Search keys
         let keys = viewer.list_keys("user_*", false).unwrap();
         assert_eq!(keys.len(), 2);
-        assert!(keys.contains(&"user_001".to_string()));
-        assert!(keys.contains(&"user_002".to_string()));
+        assert!(keys.contains(&b"user_001".to_vec()));
+        assert!(keys.contains(&b"user_002".to_vec()));
     }
 
     #[test]
@@ -767,9 +2961,15 @@ mod tests {
         let db_path = temp_dir.path().join("test_tree_errors");
         let mut viewer = SledViewer::new(&db_path).unwrap();
 
-        // Test selecting non-existent tree should still work (sled creates it)
+        // Selecting a missing tree fails instead of creating it
+        assert!(viewer.select_tree("nonexistent_tree").is_err());
+        assert!(viewer.get_selected_tree().is_none());
+        assert!(!viewer.tree_exists(b"nonexistent_tree"));
+
+        assert!(viewer.create_tree("nonexistent_tree").unwrap());
+        assert!(!viewer.create_tree("nonexistent_tree").unwrap());
         assert!(viewer.select_tree("nonexistent_tree").is_ok());
-        assert_eq!(viewer.get_selected_tree().unwrap(), "nonexistent_tree");
+        assert_eq!(viewer.get_selected_tree(), Some(&b"nonexistent_tree"[..]));
 
         // Test operations on empty tree
         let keys = viewer.list_keys("*", false).unwrap();
@@ -0,0 +1,425 @@
+use crate::bytes;
+use crate::error::SledoViewError;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// What a stored value appears to contain, detected from its bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    /// Valid UTF-8 without control characters other than whitespace
+    /// (including empty values)
+    Text,
+    /// UTF-8 text holding a JSON object or array
+    Json,
+    /// A complete MessagePack map or array
+    MessagePack,
+    /// A complete CBOR map or array
+    Cbor,
+    /// Anything else
+    Binary,
+}
+
+impl ContentType {
+    /// Detect the content type of a value.
+    ///
+    /// Only maps and arrays are detected as MessagePack or CBOR, and only
+    /// when they span the whole value, since almost any short byte string is
+    /// some valid scalar in both formats.
+    pub fn detect(value: &[u8]) -> Self {
+        let text = std::str::from_utf8(value)
+            .ok()
+            .filter(|text| !text.chars().any(|c| c.is_control() && !c.is_whitespace()));
+        if let Some(text) = text {
+            let trimmed = text.trim_start();
+            if (trimmed.starts_with('{') || trimmed.starts_with('['))
+                && serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok()
+            {
+                return ContentType::Json;
+            }
+            return ContentType::Text;
+        }
+
+        if let Some(rmpv::Value::Map(_) | rmpv::Value::Array(_)) = read_msgpack(value) {
+            return ContentType::MessagePack;
+        }
+        match read_cbor(value) {
+            Some(ciborium::Value::Map(_) | ciborium::Value::Array(_)) => ContentType::Cbor,
+            _ => ContentType::Binary,
+        }
+    }
+
+    /// The name shown to users and in machine-readable output
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentType::Text => "text",
+            ContentType::Json => "json",
+            ContentType::MessagePack => "msgpack",
+            ContentType::Cbor => "cbor",
+            ContentType::Binary => "binary",
+        }
+    }
+
+    /// The decoder used to show values of this type, if any
+    pub fn decoder(&self) -> Option<Decoder> {
+        match self {
+            ContentType::Json => Some(Decoder::Json),
+            ContentType::MessagePack => Some(Decoder::MessagePack),
+            ContentType::Cbor => Some(Decoder::Cbor),
+            ContentType::Text | ContentType::Binary => None,
+        }
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A way of decoding a value, chosen with `get <key> --as <decoder>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoder {
+    Text,
+    Json,
+    MessagePack,
+    Cbor,
+    /// A single primitive serialized with bincode's default (fixed-width,
+    /// little-endian) encoding
+    Bincode(BincodeType),
+}
+
+/// Primitive types that can be decoded with `--as bincode:<type>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BincodeType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    String,
+}
+
+impl BincodeType {
+    const ALL: [(&'static str, BincodeType); 12] = [
+        ("bool", BincodeType::Bool),
+        ("u8", BincodeType::U8),
+        ("u16", BincodeType::U16),
+        ("u32", BincodeType::U32),
+        ("u64", BincodeType::U64),
+        ("i8", BincodeType::I8),
+        ("i16", BincodeType::I16),
+        ("i32", BincodeType::I32),
+        ("i64", BincodeType::I64),
+        ("f32", BincodeType::F32),
+        ("f64", BincodeType::F64),
+        ("string", BincodeType::String),
+    ];
+
+    fn name(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, t)| t == self)
+            .map(|(name, _)| *name)
+            .expect("every type is listed")
+    }
+}
+
+impl FromStr for Decoder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        if let Some(name) = lower.strip_prefix("bincode:") {
+            return BincodeType::ALL
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, t)| Decoder::Bincode(*t))
+                .ok_or_else(|| {
+                    let names: Vec<&str> = BincodeType::ALL.iter().map(|(n, _)| *n).collect();
+                    format!(
+                        "Unknown bincode type '{}'. Use one of {}",
+                        name,
+                        names.join(", ")
+                    )
+                });
+        }
+
+        match lower.as_str() {
+            "text" | "utf8" => Ok(Decoder::Text),
+            "json" => Ok(Decoder::Json),
+            "msgpack" | "messagepack" => Ok(Decoder::MessagePack),
+            "cbor" => Ok(Decoder::Cbor),
+            _ => Err(format!(
+                "Unknown decoder '{}'. Use text, json, msgpack, cbor or bincode:<type>",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoder::Text => f.write_str("text"),
+            Decoder::Json => f.write_str("json"),
+            Decoder::MessagePack => f.write_str("msgpack"),
+            Decoder::Cbor => f.write_str("cbor"),
+            Decoder::Bincode(t) => write!(f, "bincode:{}", t.name()),
+        }
+    }
+}
+
+/// Decode a value into JSON.
+///
+/// Byte strings, which JSON cannot hold, become escaped strings as shown by
+/// `bytes::escape`; non-string map keys become their compact JSON text.
+pub fn decode(value: &[u8], decoder: Decoder) -> Result<Value, SledoViewError> {
+    let failed = |message: String| SledoViewError::Decode {
+        decoder: decoder.to_string(),
+        message,
+    };
+
+    match decoder {
+        Decoder::Text => std::str::from_utf8(value)
+            .map(|text| Value::String(text.to_string()))
+            .map_err(|e| failed(e.to_string())),
+        Decoder::Json => serde_json::from_slice(value).map_err(|e| failed(e.to_string())),
+        Decoder::MessagePack => read_msgpack(value)
+            .map(msgpack_to_json)
+            .ok_or_else(|| failed("not a single MessagePack value".to_string())),
+        Decoder::Cbor => read_cbor(value)
+            .map(cbor_to_json)
+            .ok_or_else(|| failed("not a single CBOR value".to_string())),
+        Decoder::Bincode(t) => match t {
+            BincodeType::Bool => read_bincode::<bool>(value),
+            BincodeType::U8 => read_bincode::<u8>(value),
+            BincodeType::U16 => read_bincode::<u16>(value),
+            BincodeType::U32 => read_bincode::<u32>(value),
+            BincodeType::U64 => read_bincode::<u64>(value),
+            BincodeType::I8 => read_bincode::<i8>(value),
+            BincodeType::I16 => read_bincode::<i16>(value),
+            BincodeType::I32 => read_bincode::<i32>(value),
+            BincodeType::I64 => read_bincode::<i64>(value),
+            BincodeType::F32 => read_bincode::<f32>(value),
+            BincodeType::F64 => read_bincode::<f64>(value),
+            BincodeType::String => read_bincode::<String>(value),
+        }
+        .map_err(|e| failed(e.to_string())),
+    }
+}
+
+/// Decode a value with the decoder for its detected content type
+pub fn decode_detected(value: &[u8], content_type: ContentType) -> Option<Value> {
+    decode(value, content_type.decoder()?).ok()
+}
+
+fn read_bincode<T: DeserializeOwned + Serialize>(value: &[u8]) -> bincode::Result<Value> {
+    let decoded: T = bincode::options()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(value)?;
+    Ok(serde_json::to_value(decoded).unwrap_or(Value::Null))
+}
+
+/// Read a MessagePack value that spans all of `bytes`
+fn read_msgpack(mut bytes: &[u8]) -> Option<rmpv::Value> {
+    let value = rmpv::decode::read_value(&mut bytes).ok()?;
+    bytes.is_empty().then_some(value)
+}
+
+/// Read a CBOR value that spans all of `bytes`
+fn read_cbor(mut bytes: &[u8]) -> Option<ciborium::Value> {
+    let value = ciborium::de::from_reader(&mut bytes).ok()?;
+    bytes.is_empty().then_some(value)
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+fn map_key(key: Value) -> String {
+    match key {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+fn msgpack_to_json(value: rmpv::Value) -> Value {
+    use rmpv::Value as M;
+    match value {
+        M::Nil => Value::Null,
+        M::Boolean(b) => Value::Bool(b),
+        M::Integer(i) => match (i.as_u64(), i.as_i64()) {
+            (Some(u), _) => Value::from(u),
+            (None, Some(i)) => Value::from(i),
+            _ => Value::Null,
+        },
+        M::F32(f) => float(f as f64),
+        M::F64(f) => float(f),
+        M::String(s) => match s.as_str() {
+            Some(text) => Value::String(text.to_string()),
+            None => Value::String(bytes::escape(s.as_bytes())),
+        },
+        M::Binary(b) => Value::String(bytes::escape(&b)),
+        M::Array(items) => Value::Array(items.into_iter().map(msgpack_to_json).collect()),
+        M::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (map_key(msgpack_to_json(k)), msgpack_to_json(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        M::Ext(kind, data) => serde_json::json!({ "ext": kind, "data": bytes::escape(&data) }),
+    }
+}
+
+fn cbor_to_json(value: ciborium::Value) -> Value {
+    use ciborium::Value as C;
+    match value {
+        C::Null => Value::Null,
+        C::Bool(b) => Value::Bool(b),
+        C::Integer(i) => {
+            let i = i128::from(i);
+            u64::try_from(i)
+                .map(Value::from)
+                .or_else(|_| i64::try_from(i).map(Value::from))
+                .unwrap_or_else(|_| Value::String(i.to_string()))
+        }
+        C::Float(f) => float(f),
+        C::Text(s) => Value::String(s),
+        C::Bytes(b) => Value::String(bytes::escape(&b)),
+        C::Array(items) => Value::Array(items.into_iter().map(cbor_to_json).collect()),
+        C::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (map_key(cbor_to_json(k)), cbor_to_json(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        C::Tag(tag, inner) => serde_json::json!({ "tag": tag, "value": cbor_to_json(*inner) }),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn msgpack(value: &rmpv::Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        rmpv::encode::write_value(&mut buf, value).unwrap();
+        buf
+    }
+
+    fn cbor(value: &ciborium::Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(value, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(ContentType::detect(b""), ContentType::Text);
+        assert_eq!(ContentType::detect(b"hello"), ContentType::Text);
+        assert_eq!(ContentType::detect(b"42"), ContentType::Text);
+        assert_eq!(ContentType::detect(b"{\"a\": 1}"), ContentType::Json);
+        assert_eq!(ContentType::detect(b"[1, 2"), ContentType::Text);
+        assert_eq!(ContentType::detect(&[0, 0, 0, 42]), ContentType::Binary);
+        assert_eq!(ContentType::detect(&[0, 1, 2, 255]), ContentType::Binary);
+
+        let map = rmpv::Value::Map(vec![
+            ("name".into(), "Alice".into()),
+            ("age".into(), 30.into()),
+        ]);
+        assert_eq!(
+            ContentType::detect(&msgpack(&map)),
+            ContentType::MessagePack
+        );
+
+        let map = ciborium::Value::Map(vec![
+            ("name".into(), "Alice".into()),
+            (
+                "tags".into(),
+                ciborium::Value::Array(vec![1.into(), 2.into()]),
+            ),
+        ]);
+        assert_eq!(ContentType::detect(&cbor(&map)), ContentType::Cbor);
+
+        // Trailing bytes mean the value is something else
+        let mut bytes = msgpack(&rmpv::Value::Array(vec![1.into()]));
+        bytes.push(0xff);
+        assert_eq!(ContentType::detect(&bytes), ContentType::Binary);
+    }
+
+    #[test]
+    fn test_decode() {
+        let map = rmpv::Value::Map(vec![
+            ("name".into(), "Alice".into()),
+            ("blob".into(), rmpv::Value::Binary(vec![0, 255])),
+            (1.into(), rmpv::Value::Nil),
+        ]);
+        assert_eq!(
+            decode(&msgpack(&map), Decoder::MessagePack).unwrap(),
+            json!({ "name": "Alice", "blob": "\\x00\\xff", "1": null })
+        );
+
+        let value = ciborium::Value::Array(vec![(-5).into(), 1.5.into(), true.into()]);
+        assert_eq!(
+            decode(&cbor(&value), Decoder::Cbor).unwrap(),
+            json!([-5, 1.5, true])
+        );
+
+        assert_eq!(
+            decode(b"{\"a\":[1,2]}", Decoder::Json).unwrap(),
+            json!({ "a": [1, 2] })
+        );
+        assert_eq!(decode(b"plain", Decoder::Text).unwrap(), json!("plain"));
+
+        assert!(decode(&[0xff], Decoder::Text).is_err());
+        assert!(decode(b"{", Decoder::Json).is_err());
+        assert!(decode(&[0x92, 0x01], Decoder::MessagePack).is_err());
+    }
+
+    #[test]
+    fn test_decode_bincode() {
+        let decoder = |name: &str| name.parse::<Decoder>().unwrap();
+
+        let bytes = bincode::serialize(&42u64).unwrap();
+        assert_eq!(decode(&bytes, decoder("bincode:u64")).unwrap(), json!(42));
+        assert!(decode(&bytes, decoder("bincode:u32")).is_err());
+
+        let bytes = bincode::serialize(&-7i32).unwrap();
+        assert_eq!(decode(&bytes, decoder("bincode:i32")).unwrap(), json!(-7));
+
+        let bytes = bincode::serialize("hi").unwrap();
+        assert_eq!(
+            decode(&bytes, decoder("bincode:string")).unwrap(),
+            json!("hi")
+        );
+    }
+
+    #[test]
+    fn test_decoder_from_str() {
+        assert_eq!("msgpack".parse(), Ok(Decoder::MessagePack));
+        assert_eq!("CBOR".parse(), Ok(Decoder::Cbor));
+        assert_eq!(
+            "bincode:u64".parse(),
+            Ok(Decoder::Bincode(BincodeType::U64))
+        );
+        assert_eq!(
+            Decoder::Bincode(BincodeType::I16).to_string(),
+            "bincode:i16"
+        );
+        assert!("bincode:u128".parse::<Decoder>().is_err());
+        assert!("xml".parse::<Decoder>().is_err());
+    }
+}
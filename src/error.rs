@@ -20,15 +20,83 @@ pub enum SledoViewError {
     #[error("Invalid regex pattern: {pattern}")]
     InvalidRegex { pattern: String },
 
+    #[error("Invalid literal '{literal}': {message}")]
+    InvalidLiteral { literal: String, message: String },
+
+    #[error("Cannot decode value as {decoder}: {message}")]
+    Decode { decoder: String, message: String },
+
+    #[error("Invalid profile {path}: {message}")]
+    InvalidProfile { path: String, message: String },
+
+    #[error("Invalid query '{query}': {message}")]
+    InvalidQuery { query: String, message: String },
+
+    #[error("Invalid SQL: {message}")]
+    InvalidSql { message: String },
+
+    #[error("Invalid range: '{start}' sorts after '{end}'")]
+    InvalidRange { start: String, end: String },
+
+    #[error("Cannot sort {listing} by {sort}")]
+    InvalidSort { listing: String, sort: String },
+
+    #[error("No more results to show")]
+    NoMoreResults,
+
+    #[error("A transaction is already in progress")]
+    TransactionInProgress,
+
+    #[error("No transaction in progress")]
+    NoTransaction,
+
+    #[error("Deleting {count} keys needs confirmation: add --yes, or --dry-run to only list them")]
+    ConfirmationRequired { count: usize },
+
+    #[error("{action} tree '{tree}' needs confirmation: add --yes")]
+    TreeConfirmationRequired { action: String, tree: String },
+
+    #[error("Interrupted")]
+    Interrupted,
+
     #[error("Key not found: {key}")]
     KeyNotFound { key: String },
 
+    #[error("Several keys would be written to {key}")]
+    DuplicateTarget { key: String },
+
+    #[error("Conflict on key {key}: expected {expected}, found {current}")]
+    Conflict {
+        key: String,
+        expected: String,
+        current: String,
+    },
+
     #[error("Database operation failed: {message}")]
     DatabaseOperation { message: String },
 
+    #[error("Tree '{tree}' does not exist")]
+    TreeNotFound { tree: String },
+
+    #[error("Tree '{tree}' already exists")]
+    TreeExists { tree: String },
+
     #[error("Tree operation failed: {message}")]
     TreeOperation { message: String },
 
+    #[error("Database is open in read-only mode, '{command}' is not allowed")]
+    ReadOnly { command: String },
+
+    #[error("Cannot read script {path}: {message}")]
+    ScriptNotReadable { path: String, message: String },
+
+    #[error("Script {path} failed: {failed} of {executed} commands failed")]
+    ScriptFailed {
+        path: String,
+        failed: usize,
+        executed: usize,
+    },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -39,6 +107,22 @@ pub enum SledoViewError {
     Regex(#[from] regex::Error),
 }
 
+impl SledoViewError {
+    /// Process exit code reported when this error ends a one-shot command
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            SledoViewError::KeyNotFound { .. } => 3,
+            SledoViewError::Conflict { .. } => 5,
+            SledoViewError::DatabaseNotFound { .. }
+            | SledoViewError::DatabaseNotReadable { .. }
+            | SledoViewError::InvalidSledDatabase { .. }
+            | SledoViewError::DatabaseLocked { .. }
+            | SledoViewError::PermissionDenied { .. } => 4,
+            _ => 1,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,10 +139,28 @@ mod tests {
         };
         assert_eq!(err.to_string(), "Invalid regex pattern: [invalid");
 
+        let err = SledoViewError::InvalidLiteral {
+            literal: "0xabc".to_string(),
+            message: "odd number of hex digits".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Invalid literal '0xabc': odd number of hex digits"
+        );
+
         let err = SledoViewError::KeyNotFound {
             key: "missing_key".to_string(),
         };
         assert_eq!(err.to_string(), "Key not found: missing_key");
+
+        let err = SledoViewError::TreeConfirmationRequired {
+            action: "Dropping".to_string(),
+            tree: "sessions".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Dropping tree 'sessions' needs confirmation: add --yes"
+        );
     }
 
     #[test]
@@ -70,4 +172,29 @@ mod tests {
         assert!(debug_str.contains("DatabaseLocked"));
         assert!(debug_str.contains("/test/path"));
     }
+
+    #[test]
+    fn test_error_exit_code() {
+        let err = SledoViewError::KeyNotFound {
+            key: "missing_key".to_string(),
+        };
+        assert_eq!(err.exit_code(), 3);
+
+        let err = SledoViewError::Conflict {
+            key: "balance".to_string(),
+            expected: "'50'".to_string(),
+            current: "'40'".to_string(),
+        };
+        assert_eq!(err.exit_code(), 5);
+
+        let err = SledoViewError::DatabaseNotFound {
+            path: "/test/path".to_string(),
+        };
+        assert_eq!(err.exit_code(), 4);
+
+        let err = SledoViewError::InvalidRegex {
+            pattern: "[invalid".to_string(),
+        };
+        assert_eq!(err.exit_code(), 1);
+    }
 }
@@ -0,0 +1,131 @@
+use crate::bytes;
+use crate::decode::ContentType;
+use crate::error::SledoViewError;
+
+/// Conditions an entry must meet, on top of the pattern of `list` or
+/// `search`. Every condition given must hold.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Filter {
+    /// Glob the key must match as well (`--key`)
+    pub key: Option<String>,
+    /// Regex the value must match as well (`--value`)
+    pub value: Option<String>,
+    /// Match all patterns, the command's own included, ignoring case (`-i`)
+    pub ignore_case: bool,
+    /// Smallest value size in bytes (`--min-size`)
+    pub min_size: Option<usize>,
+    /// Largest value size in bytes (`--max-size`)
+    pub max_size: Option<usize>,
+    /// Only text or only binary values (`--text`, `--binary`)
+    pub kind: Option<ValueKind>,
+    /// Only empty values (`--empty`)
+    pub empty: bool,
+}
+
+/// Whether a value reads as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// Text and JSON values
+    Text,
+    /// MessagePack, CBOR and other binary values
+    Binary,
+}
+
+impl ValueKind {
+    pub fn of(content_type: ContentType) -> Self {
+        match content_type {
+            ContentType::Text | ContentType::Json => ValueKind::Text,
+            ContentType::MessagePack | ContentType::Cbor | ContentType::Binary => ValueKind::Binary,
+        }
+    }
+}
+
+impl Filter {
+    /// The `--key` glob as bytes
+    pub fn key_glob(&self) -> Result<Option<Vec<u8>>, SledoViewError> {
+        self.key.as_deref().map(bytes::parse_literal).transpose()
+    }
+
+    /// Whether a value of `size` bytes passes the size conditions; checked
+    /// before the patterns since it is cheapest
+    pub fn accepts_size(&self, size: usize) -> bool {
+        (!self.empty || size == 0)
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    /// Whether a value of this content type passes `--text` / `--binary`
+    pub fn accepts_content(&self, content_type: ContentType) -> bool {
+        self.kind
+            .is_none_or(|kind| kind == ValueKind::of(content_type))
+    }
+}
+
+/// Parse a size in bytes, optionally with a `k`, `m` or `g` suffix (powers
+/// of 1024), e.g. `512`, `4k` or `10MB`
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let invalid = || {
+        format!(
+            "Invalid size '{}'. Use a number of bytes like 512, 4k or 10m",
+            s
+        )
+    };
+
+    let lower = s.trim().to_ascii_lowercase();
+    let number = lower.strip_suffix('b').unwrap_or(&lower);
+    let (digits, scale) = match number.char_indices().last() {
+        Some((i, 'k')) => (&number[..i], 1 << 10),
+        Some((i, 'm')) => (&number[..i], 1 << 20),
+        Some((i, 'g')) => (&number[..i], 1 << 30),
+        _ => (number, 1),
+    };
+
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4k"), Ok(4096));
+        assert_eq!(parse_size("10MB"), Ok(10 << 20));
+        assert_eq!(parse_size("1g"), Ok(1 << 30));
+        assert_eq!(parse_size("0"), Ok(0));
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("1.5k").is_err());
+    }
+
+    #[test]
+    fn test_filter_accepts() {
+        let filter = Filter {
+            min_size: Some(2),
+            max_size: Some(4),
+            ..Filter::default()
+        };
+        assert!(!filter.accepts_size(1));
+        assert!(filter.accepts_size(2) && filter.accepts_size(4));
+        assert!(!filter.accepts_size(5));
+
+        let empty = Filter {
+            empty: true,
+            ..Filter::default()
+        };
+        assert!(empty.accepts_size(0) && !empty.accepts_size(1));
+
+        let binary = Filter {
+            kind: Some(ValueKind::Binary),
+            ..Filter::default()
+        };
+        assert!(binary.accepts_content(ContentType::Cbor));
+        assert!(!binary.accepts_content(ContentType::Json));
+        assert!(Filter::default().accepts_content(ContentType::Binary));
+    }
+}
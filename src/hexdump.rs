@@ -0,0 +1,185 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Bytes per hexdump line unless `--width` is given
+pub const DEFAULT_WIDTH: usize = 16;
+
+/// Widest hexdump line accepted by `--width`
+pub const MAX_WIDTH: usize = 64;
+
+/// Bytes shown when a value is dumped without `--bytes`
+pub const DEFAULT_LIMIT: usize = 1024;
+
+/// A half-open byte range: `start..end`, `start..` or `..end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ByteRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl ByteRange {
+    /// Clamp the range to a value of `len` bytes
+    pub fn clamp(&self, len: usize) -> std::ops::Range<usize> {
+        let end = self.end.map_or(len, |end| end.min(len));
+        self.start.min(end)..end
+    }
+}
+
+impl FromStr for ByteRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid byte range '{}'. Use start..end, start.. or ..end",
+                s
+            )
+        };
+        let (start, end) = s.split_once("..").ok_or_else(invalid)?;
+
+        let start = if start.is_empty() {
+            0
+        } else {
+            start.parse().map_err(|_| invalid())?
+        };
+        let end = if end.is_empty() {
+            None
+        } else {
+            Some(end.parse().map_err(|_| invalid())?)
+        };
+
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+        Ok(ByteRange { start, end })
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}..{}", self.start, end),
+            None => write!(f, "{}..", self.start),
+        }
+    }
+}
+
+/// Parse and check a `--width` value
+pub fn parse_width(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(width) if (1..=MAX_WIDTH).contains(&width) => Ok(width),
+        _ => Err(format!(
+            "Invalid width '{}'. Use a number from 1 to {}",
+            s, MAX_WIDTH
+        )),
+    }
+}
+
+/// Format bytes as classic offset / hex / ASCII hexdump lines.
+///
+/// `offset` is the position of `bytes` within the whole value, so dumps of a
+/// range show the real offsets. Hex bytes are grouped in eights.
+pub fn format_lines(bytes: &[u8], offset: usize, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    // Room for every byte, plus a separator after each group of eight
+    let hex_width = width * 3 + (width - 1) / 8;
+
+    bytes
+        .chunks(width)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let mut hex = String::with_capacity(hex_width);
+            for (i, byte) in chunk.iter().enumerate() {
+                if i > 0 && i % 8 == 0 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x} ", byte));
+            }
+
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            format!(
+                "{:08x}  {:<hex_width$} |{}|",
+                offset + line * width,
+                hex,
+                ascii
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_range_from_str() {
+        assert_eq!(
+            "0..256".parse(),
+            Ok(ByteRange {
+                start: 0,
+                end: Some(256)
+            })
+        );
+        assert_eq!(
+            "16..".parse(),
+            Ok(ByteRange {
+                start: 16,
+                end: None
+            })
+        );
+        assert_eq!(
+            "..8".parse(),
+            Ok(ByteRange {
+                start: 0,
+                end: Some(8)
+            })
+        );
+        assert!("8..4".parse::<ByteRange>().is_err());
+        assert!("abc".parse::<ByteRange>().is_err());
+        assert!("1..x".parse::<ByteRange>().is_err());
+    }
+
+    #[test]
+    fn test_byte_range_clamp() {
+        let range: ByteRange = "4..100".parse().unwrap();
+        assert_eq!(range.clamp(10), 4..10);
+        assert_eq!(range.clamp(2), 2..2);
+        assert_eq!(ByteRange::default().clamp(5), 0..5);
+    }
+
+    #[test]
+    fn test_parse_width() {
+        assert_eq!(parse_width("8"), Ok(8));
+        assert!(parse_width("0").is_err());
+        assert!(parse_width("65").is_err());
+        assert!(parse_width("wide").is_err());
+    }
+
+    #[test]
+    fn test_format_lines() {
+        let bytes: Vec<u8> = (0x41..0x41 + 20).collect();
+        let lines = format_lines(&bytes, 0, 16);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  51 52 53 54                                       |QRST|"
+        );
+
+        let lines = format_lines(&[0, b'a', 0xff], 0x100, 2);
+        assert_eq!(lines, vec!["00000100  00 61  |.a|", "00000102  ff     |.|"]);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::error::SledoViewError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+/// Set by the Ctrl-C handler, cleared when the next command starts
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static INSTALL: Once = Once::new();
+
+/// Make Ctrl-C cancel the running command instead of ending the process.
+///
+/// Only the interactive session installs the handler: there Ctrl-C at the
+/// prompt is handled by the line editor, and while a command runs it sets a
+/// flag that long scans check between keys.
+pub fn install() {
+    INSTALL.call_once(|| {
+        if let Err(e) = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst)) {
+            eprintln!("Warning: Ctrl-C will not cancel running commands: {}", e);
+        }
+    });
+}
+
+/// Forget a Ctrl-C pressed before the command about to run
+pub fn clear() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Fail with `Interrupted` if Ctrl-C was pressed since the last `clear`
+pub fn check() -> Result<(), SledoViewError> {
+    if INTERRUPTED.load(Ordering::Relaxed) && INTERRUPTED.swap(false, Ordering::SeqCst) {
+        Err(SledoViewError::Interrupted)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_interrupt_once() {
+        clear();
+        assert!(check().is_ok());
+
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        assert!(matches!(check(), Err(SledoViewError::Interrupted)));
+        assert!(check().is_ok());
+    }
+}
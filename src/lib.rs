@@ -1,6 +1,19 @@
+pub mod bytes;
 pub mod cli;
+pub mod codec;
 pub mod commands;
 pub mod db;
+pub mod decode;
 pub mod error;
+pub mod filter;
+pub mod hexdump;
+pub mod interrupt;
+pub mod output;
+pub mod page;
+pub mod profile;
+pub mod query;
+pub mod render;
 pub mod repl;
+pub mod script;
+pub mod sql;
 pub mod validator;
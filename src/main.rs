@@ -1,49 +1,154 @@
+mod bytes;
 mod cli;
+mod codec;
 mod commands;
 mod db;
+mod decode;
 mod error;
+mod filter;
+mod hexdump;
+mod interrupt;
+mod output;
+mod page;
+mod profile;
+mod query;
+mod render;
 mod repl;
+mod script;
+mod sql;
 mod validator;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use colored::*;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::ExitCode;
 
-use cli::Args;
+use cli::{Args, CliCommand};
 use db::SledViewer;
+use error::SledoViewError;
+use profile::Profile;
 use repl::Repl;
 use validator::DatabaseValidator;
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
 
+    let result = match (&args.command, &args.script) {
+        // Special case for creating test data, whether or not input is a terminal
+        _ if args.database_path.to_string_lossy() == "create-test" => create_test_database(),
+        (Some(_), Some(_)) => Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--script cannot be combined with a subcommand",
+            )
+            .exit(),
+        (Some(command), _) => run_command(&args, command),
+        (None, Some(script)) => run_script(&args, script),
+        (None, None) if io::stdin().is_terminal() => run_interactive(&args),
+        (None, None) => run_stdin(&args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Map an error to the process exit code documented in `--help`
+fn exit_code(err: &anyhow::Error) -> u8 {
+    err.downcast_ref::<SledoViewError>()
+        .map(SledoViewError::exit_code)
+        .unwrap_or(1)
+}
+
+/// Open the database, read-only when requested or when its files are not
+/// writable, and as a temporary copy with `--snapshot`
+fn open_viewer(args: &Args, read_only: bool) -> Result<SledViewer> {
+    let mut viewer = if args.snapshot {
+        SledViewer::new_snapshot(&args.database_path)?
+    } else if read_only || !db::is_path_writable(&args.database_path) {
+        SledViewer::new_read_only(&args.database_path)?
+    } else {
+        SledViewer::new(&args.database_path)?
+    };
+    if let Some(profile) = &args.profile {
+        viewer.set_profile(Profile::load(profile)?);
+    }
+    if let Some(tree) = &args.tree {
+        viewer.select_tree(bytes::parse_literal(tree)?)?;
+    }
+    Ok(viewer)
+}
+
+/// Validate and open the database without printing progress messages
+fn open_quietly(args: &Args, read_only: bool) -> Result<SledViewer> {
+    DatabaseValidator::new(&args.database_path)
+        .quiet(true)
+        .validate()?;
+
+    open_viewer(args, read_only)
+}
+
+/// Run a single command non-interactively, printing only its result.
+///
+/// Commands that only inspect the database always open it read-only.
+fn run_command(args: &Args, command: &CliCommand) -> Result<()> {
+    let command = command.to_command();
+    let mut viewer = open_quietly(args, args.read_only || !command.is_write())?;
+    commands::execute_and_print(&command, &mut viewer, args.format)
+}
+
+/// Sessions are for inspection and open the database read-only unless
+/// `--write` is given
+fn session_read_only(args: &Args) -> bool {
+    args.read_only || !args.write
+}
+
+/// Run a script file non-interactively
+fn run_script(args: &Args, script: &Path) -> Result<()> {
+    let mut viewer = open_quietly(args, session_read_only(args))?;
+    let result = script::run_file(&mut viewer, script, args.continue_on_error, args.format);
+    commands::warn_uncommitted(&viewer);
+    result
+}
+
+/// Run commands piped through standard input, one per line.
+///
+/// No banner, prompt, colors or completion hints are printed, so the output
+/// is just one result block per input line.
+fn run_stdin(args: &Args) -> Result<()> {
+    colored::control::set_override(false);
+    let mut viewer = open_quietly(args, session_read_only(args))?;
+    let result = script::run_reader(
+        &mut viewer,
+        io::stdin().lock(),
+        "<stdin>",
+        args.continue_on_error,
+        args.format,
+    );
+    commands::warn_uncommitted(&viewer);
+    result
+}
+
+fn run_interactive(args: &Args) -> Result<()> {
     println!(
         "{}",
         "SledoView - SLED Database Viewer".bright_cyan().bold()
     );
     println!("{}", "═".repeat(35).bright_cyan());
 
-    // Special case for creating test data
-    if args.database_path.to_string_lossy() == "create-test" {
-        create_test_database()?;
-        return Ok(());
-    }
-
     // Validate the database
     let validator = DatabaseValidator::new(&args.database_path);
     validator.validate()?;
 
     // Open the database
-    let viewer = SledViewer::new(&args.database_path)?;
-
-    // Check if database is writable
-    if !viewer.is_writable() {
-        println!(
-            "{} {}",
-            "⚠".bright_yellow().bold(),
-            "Database opened in read-only mode (write operations will fail)".bright_yellow()
-        );
-    }
+    let viewer = open_viewer(args, session_read_only(args))?;
 
     println!(
         "{} {}",
@@ -55,8 +160,32 @@ fn main() -> Result<()> {
         .bright_green()
     );
 
+    if viewer.is_writable() {
+        println!(
+            "{} {}",
+            "✓".bright_green().bold(),
+            "Database is writable - modification commands available".bright_green()
+        );
+    } else {
+        println!(
+            "{} {}",
+            "⚠".bright_yellow().bold(),
+            "Database opened in read-only mode (write operations are disabled)".bright_yellow()
+        );
+        if !args.write
+            && !args.read_only
+            && !args.snapshot
+            && db::is_path_writable(&args.database_path)
+        {
+            println!(
+                "  {}",
+                "Start sledoview with --write to modify the database".bright_black()
+            );
+        }
+    }
+
     // Start the REPL
-    let mut repl = Repl::new(viewer);
+    let mut repl = Repl::new(viewer).with_format(args.format);
     repl.run()?;
 
     Ok(())
@@ -65,19 +194,23 @@ fn main() -> Result<()> {
 fn create_test_database() -> Result<()> {
     println!("Creating test database...");
     let db = sled::open("test.db")?;
-    
+
     db.insert("user_1", "Alice Smith")?;
     db.insert("user_2", "Bob Johnson")?;
     db.insert("config_timeout", "30")?;
     db.insert("config_debug", "true")?;
     db.insert("data_large", "This is a longer text value that should be truncated in the preview display to demonstrate the truncation feature")?;
     db.insert("empty_key", "")?;
-    
+
     // Add some binary data
     db.insert("binary_data", &[0u8, 1u8, 2u8, 255u8])?;
-    
+
     db.flush()?;
     println!("✓ Test database 'test.db' created successfully!");
-    println!("You can now run: {} {}", "cargo run test.db".bright_yellow(), "".bright_green());
+    println!(
+        "You can now run: {} {}",
+        "cargo run test.db".bright_yellow(),
+        "".bright_green()
+    );
     Ok(())
 }
@@ -0,0 +1,839 @@
+use crate::codec::TreeCodecs;
+use crate::db::{KeyInfo, ScanPage, StagedWrite};
+use crate::decode::Decoder;
+use crate::hexdump::ByteRange;
+use crate::page::Listing;
+use crate::query;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored, human-readable output
+    #[default]
+    Text,
+    /// A single JSON document per command
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+impl OutputFormat {
+    pub fn is_text(&self) -> bool {
+        *self == OutputFormat::Text
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!(
+                "Unknown output format '{}'. Use text, json, ndjson, csv or tsv",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The result of executing a command, independent of how it is displayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandOutput {
+    /// Number of records (`count`)
+    Count(usize),
+    /// Keys matching a pattern or in a range, with their values (`list`,
+    /// `range`, `prefix`, `head`, `tail`)
+    Keys(Listing<KeyInfo>),
+    /// A single key (`get`)
+    KeyInfo(KeyInfo),
+    /// A byte range of a single key's value (`get --bytes/--width`)
+    Bytes(ByteView),
+    /// A single key's value run through a decoder (`get --as`)
+    Decoded(DecodedValue),
+    /// Keys whose values matched a pattern (`search`) or a query (`query`)
+    SearchResults(Listing<KeyInfo>),
+    /// Fields of the documents that matched a query (`query --select`)
+    Projection(Projection),
+    /// The result of a SQL statement (`sql`)
+    Rows(Rows),
+    /// Tree names (`trees`)
+    Trees(Listing<Vec<u8>>),
+    /// The trees holding a key (`where`)
+    Located(Located),
+    /// Outcome of a command that changes state (`set`, `delete`, `select`,
+    /// `unselect`, `begin`, `commit`, `rollback`)
+    Ack(Ack),
+    /// Keys copied or moved, with their targets (`cp`, `mv`, `rename`)
+    Copied(Copied),
+    /// The keys a bulk delete removed or, when previewed, would remove
+    /// (`delete --pattern/--regex/--range`)
+    Deletion(Deletion),
+    /// The writes staged in the transaction in progress, `None` outside one
+    /// (`status`)
+    Status(Option<Vec<StagedWrite>>),
+    /// The session output format was changed (`format <name>`)
+    FormatChanged(OutputFormat),
+    /// The current session output format (`format`)
+    CurrentFormat(OutputFormat),
+    /// The command reference (`help`)
+    Help,
+    /// The command has nothing to report
+    Empty,
+}
+
+impl From<ScanPage> for CommandOutput {
+    fn from(page: ScanPage) -> Self {
+        match page {
+            ScanPage::Keys(keys) => CommandOutput::Keys(keys),
+            ScanPage::Values(results) => CommandOutput::SearchResults(results),
+            ScanPage::Projection { columns, entries } => {
+                CommandOutput::Projection(Projection { columns, entries })
+            }
+            ScanPage::Trees(trees) => CommandOutput::Trees(trees),
+        }
+    }
+}
+
+/// Query results as a table: a row per entry, with a column per path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    pub columns: Vec<query::Path>,
+    pub entries: Listing<KeyInfo>,
+}
+
+impl Projection {
+    /// The value at each path of an entry's document, `null` where missing
+    pub fn row(&self, info: &KeyInfo) -> Vec<serde_json::Value> {
+        let document = info.document().unwrap_or_default();
+        self.columns
+            .iter()
+            .map(|path| path.select(&document).cloned().unwrap_or_default())
+            .collect()
+    }
+}
+
+/// The result of a SQL statement: named columns and a row of values each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// The entries stored under a key, one per tree that holds it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Located {
+    /// The key as typed
+    pub key: String,
+    pub entries: Vec<KeyInfo>,
+}
+
+/// Outcome of a command that changes state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    /// Name of the command, as returned by `Command::name`
+    pub command: &'static str,
+    /// The key or tree the command acted on, escaped for display; the number
+    /// of writes, for `commit` and `rollback`
+    pub target: String,
+    /// The value written, for `set`, escaped for display; the codecs applied
+    /// by the profile, for `select`; the number of keys removed by `tree
+    /// drop` and `tree clear`; the new tree of `tree rename` and `tree copy`
+    pub value: Option<String>,
+    /// Whether anything changed (`false` when deleting a missing key, ...)
+    pub changed: bool,
+    /// Whether the change was staged in a transaction rather than applied
+    pub staged: bool,
+}
+
+/// Keys copied or moved with their values as stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Copied {
+    /// Name of the command, as returned by `Command::name`
+    pub command: &'static str,
+    /// Each source key with the key it was copied to
+    pub pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The tree copied to, when not the current tree
+    pub tree: Option<Vec<u8>>,
+    /// The codecs of the current tree and of the target tree, to display keys
+    pub codecs: TreeCodecs,
+    pub target_codecs: TreeCodecs,
+    /// Whether the writes were staged in a transaction rather than applied
+    pub staged: bool,
+}
+
+/// The keys selected by a bulk delete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deletion {
+    pub keys: Vec<Vec<u8>>,
+    /// The codecs of the tree, to display the keys
+    pub codecs: TreeCodecs,
+    /// Whether the keys were deleted, rather than only previewed
+    pub deleted: bool,
+    /// Whether the deletes were staged in a transaction rather than applied
+    pub staged: bool,
+}
+
+/// A byte range of a value, shown as a hexdump in text output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteView {
+    pub info: KeyInfo,
+    /// The requested range; it may extend past the end of the value
+    pub range: ByteRange,
+    /// Bytes per hexdump line
+    pub width: usize,
+}
+
+impl ByteView {
+    /// The bytes of the value that fall within the range
+    pub fn selected(&self) -> &[u8] {
+        &self.info.value[self.range.clamp(self.info.value.len())]
+    }
+}
+
+/// A value decoded with an explicitly chosen decoder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedValue {
+    pub info: KeyInfo,
+    pub decoder: Decoder,
+    pub value: serde_json::Value,
+}
+
+/// A row of machine-readable output
+pub trait Record: Serialize {
+    /// Column names, in the order returned by `fields`
+    fn headers() -> &'static [&'static str];
+    /// Column values for CSV/TSV output
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Encode bytes for machine-readable output.
+///
+/// Valid UTF-8 is emitted as-is with encoding `utf8`; anything else is
+/// emitted as standard, padded base64 with encoding `base64`.
+pub fn encode_bytes(bytes: &[u8]) -> (String, &'static str) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), "utf8"),
+        Err(_) => (BASE64.encode(bytes), "base64"),
+    }
+}
+
+/// A key with its value (`get`, `list` and `search`)
+#[derive(Debug, Serialize)]
+pub struct KeyRecord {
+    pub key: String,
+    pub key_encoding: &'static str,
+    pub size: usize,
+    pub content_type: &'static str,
+    pub value: String,
+    pub value_encoding: &'static str,
+}
+
+impl From<&KeyInfo> for KeyRecord {
+    fn from(info: &KeyInfo) -> Self {
+        let (key, key_encoding) = encode_bytes(&info.key);
+        let (value, value_encoding) = encode_bytes(&info.value);
+        Self {
+            key,
+            key_encoding,
+            size: info.size,
+            content_type: info.content_type.name(),
+            value,
+            value_encoding,
+        }
+    }
+}
+
+impl From<&ByteView> for KeyRecord {
+    /// `value` holds only the selected bytes; `size` is still the full size
+    fn from(view: &ByteView) -> Self {
+        let (key, key_encoding) = encode_bytes(&view.info.key);
+        let (value, value_encoding) = encode_bytes(view.selected());
+        Self {
+            key,
+            key_encoding,
+            size: view.info.size,
+            content_type: view.info.content_type.name(),
+            value,
+            value_encoding,
+        }
+    }
+}
+
+impl Record for KeyRecord {
+    fn headers() -> &'static [&'static str] {
+        &[
+            "key",
+            "key_encoding",
+            "size",
+            "content_type",
+            "value",
+            "value_encoding",
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.key.clone(),
+            self.key_encoding.to_string(),
+            self.size.to_string(),
+            self.content_type.to_string(),
+            self.value.clone(),
+            self.value_encoding.to_string(),
+        ]
+    }
+}
+
+/// A key with its value and the tree it was found in (`where`, and `list`
+/// and `search` across trees)
+#[derive(Debug, Serialize)]
+pub struct TreeKeyRecord {
+    pub tree: String,
+    pub tree_encoding: &'static str,
+    #[serde(flatten)]
+    pub entry: KeyRecord,
+}
+
+impl From<&KeyInfo> for TreeKeyRecord {
+    fn from(info: &KeyInfo) -> Self {
+        let (tree, tree_encoding) = encode_bytes(info.tree.as_deref().unwrap_or_default());
+        Self {
+            tree,
+            tree_encoding,
+            entry: KeyRecord::from(info),
+        }
+    }
+}
+
+impl Record for TreeKeyRecord {
+    fn headers() -> &'static [&'static str] {
+        &[
+            "tree",
+            "tree_encoding",
+            "key",
+            "key_encoding",
+            "size",
+            "content_type",
+            "value",
+            "value_encoding",
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = vec![self.tree.clone(), self.tree_encoding.to_string()];
+        fields.extend(self.entry.fields());
+        fields
+    }
+}
+
+/// A write staged in a transaction (`status`); `value` is empty for a delete
+#[derive(Debug, Serialize)]
+pub struct StagedRecord {
+    pub tree: String,
+    pub tree_encoding: &'static str,
+    pub operation: &'static str,
+    pub key: String,
+    pub key_encoding: &'static str,
+    pub value: String,
+    pub value_encoding: &'static str,
+}
+
+impl From<&StagedWrite> for StagedRecord {
+    fn from(write: &StagedWrite) -> Self {
+        let (tree, tree_encoding) = encode_bytes(&write.tree);
+        let (key, key_encoding) = encode_bytes(&write.key);
+        let (value, value_encoding) = encode_bytes(write.value.as_deref().unwrap_or_default());
+        Self {
+            tree,
+            tree_encoding,
+            operation: if write.value.is_some() {
+                "set"
+            } else {
+                "delete"
+            },
+            key,
+            key_encoding,
+            value,
+            value_encoding,
+        }
+    }
+}
+
+impl Record for StagedRecord {
+    fn headers() -> &'static [&'static str] {
+        &[
+            "tree",
+            "tree_encoding",
+            "operation",
+            "key",
+            "key_encoding",
+            "value",
+            "value_encoding",
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.tree.clone(),
+            self.tree_encoding.to_string(),
+            self.operation.to_string(),
+            self.key.clone(),
+            self.key_encoding.to_string(),
+            self.value.clone(),
+            self.value_encoding.to_string(),
+        ]
+    }
+}
+
+/// A key with its decoded value (`get --as`)
+#[derive(Debug, Serialize)]
+pub struct DecodedRecord {
+    pub key: String,
+    pub key_encoding: &'static str,
+    pub size: usize,
+    pub decoder: String,
+    pub decoded: serde_json::Value,
+}
+
+impl From<&DecodedValue> for DecodedRecord {
+    fn from(decoded: &DecodedValue) -> Self {
+        let (key, key_encoding) = encode_bytes(&decoded.info.key);
+        Self {
+            key,
+            key_encoding,
+            size: decoded.info.size,
+            decoder: decoded.decoder.to_string(),
+            decoded: decoded.value.clone(),
+        }
+    }
+}
+
+impl Record for DecodedRecord {
+    fn headers() -> &'static [&'static str] {
+        &["key", "key_encoding", "size", "decoder", "decoded"]
+    }
+
+    /// `decoded` is written as compact JSON
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.key.clone(),
+            self.key_encoding.to_string(),
+            self.size.to_string(),
+            self.decoder.clone(),
+            self.decoded.to_string(),
+        ]
+    }
+}
+
+/// A tree name (`trees`)
+#[derive(Debug, Serialize)]
+pub struct TreeRecord {
+    pub tree: String,
+    pub tree_encoding: &'static str,
+}
+
+impl From<&[u8]> for TreeRecord {
+    fn from(name: &[u8]) -> Self {
+        let (tree, tree_encoding) = encode_bytes(name);
+        Self {
+            tree,
+            tree_encoding,
+        }
+    }
+}
+
+impl Record for TreeRecord {
+    fn headers() -> &'static [&'static str] {
+        &["tree", "tree_encoding"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.tree.clone(), self.tree_encoding.to_string()]
+    }
+}
+
+/// A record count (`count`)
+#[derive(Debug, Serialize)]
+pub struct CountRecord {
+    pub count: usize,
+}
+
+impl Record for CountRecord {
+    fn headers() -> &'static [&'static str] {
+        &["count"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.count.to_string()]
+    }
+}
+
+/// The outcome of a command that changes state (`set`, `delete`, `select`, ...)
+#[derive(Debug, Serialize)]
+pub struct AckRecord {
+    pub command: &'static str,
+    pub target: String,
+    pub changed: bool,
+}
+
+impl From<&Ack> for AckRecord {
+    fn from(ack: &Ack) -> Self {
+        Self {
+            command: ack.command,
+            target: ack.target.clone(),
+            changed: ack.changed,
+        }
+    }
+}
+
+impl Record for AckRecord {
+    fn headers() -> &'static [&'static str] {
+        &["command", "target", "changed"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.command.to_string(),
+            self.target.clone(),
+            self.changed.to_string(),
+        ]
+    }
+}
+
+/// A key copied or moved (`cp`, `mv`, `rename`); `target_tree` is empty
+/// for the current tree
+#[derive(Debug, Serialize)]
+pub struct CopiedRecord {
+    pub key: String,
+    pub key_encoding: &'static str,
+    pub target_tree: String,
+    pub target_tree_encoding: &'static str,
+    pub target: String,
+    pub target_encoding: &'static str,
+}
+
+impl CopiedRecord {
+    pub fn new(from: &[u8], tree: Option<&[u8]>, to: &[u8]) -> Self {
+        let (key, key_encoding) = encode_bytes(from);
+        let (target_tree, target_tree_encoding) = encode_bytes(tree.unwrap_or_default());
+        let (target, target_encoding) = encode_bytes(to);
+        Self {
+            key,
+            key_encoding,
+            target_tree,
+            target_tree_encoding,
+            target,
+            target_encoding,
+        }
+    }
+}
+
+impl Record for CopiedRecord {
+    fn headers() -> &'static [&'static str] {
+        &[
+            "key",
+            "key_encoding",
+            "target_tree",
+            "target_tree_encoding",
+            "target",
+            "target_encoding",
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.key.clone(),
+            self.key_encoding.to_string(),
+            self.target_tree.clone(),
+            self.target_tree_encoding.to_string(),
+            self.target.clone(),
+            self.target_encoding.to_string(),
+        ]
+    }
+}
+
+/// A key selected by a bulk delete (`delete --pattern/--regex/--range`)
+#[derive(Debug, Serialize)]
+pub struct DeletedRecord {
+    pub key: String,
+    pub key_encoding: &'static str,
+    pub deleted: bool,
+}
+
+impl DeletedRecord {
+    pub fn new(key: &[u8], deleted: bool) -> Self {
+        let (key, key_encoding) = encode_bytes(key);
+        Self {
+            key,
+            key_encoding,
+            deleted,
+        }
+    }
+}
+
+impl Record for DeletedRecord {
+    fn headers() -> &'static [&'static str] {
+        &["key", "key_encoding", "deleted"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.key.clone(),
+            self.key_encoding.to_string(),
+            self.deleted.to_string(),
+        ]
+    }
+}
+
+/// Render a list of records in a machine-readable format
+pub fn render_records<T: Record>(format: OutputFormat, records: &[T]) -> String {
+    match format {
+        OutputFormat::Text | OutputFormat::Json => {
+            serde_json::to_string_pretty(records).expect("records serialize to JSON")
+        }
+        OutputFormat::Ndjson => records
+            .iter()
+            .map(|r| serde_json::to_string(r).expect("records serialize to JSON"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Csv => render_delimited(records, ',', escape_csv),
+        OutputFormat::Tsv => render_delimited(records, '\t', escape_tsv),
+    }
+}
+
+/// Render query results as records of the key, the tree across trees, and
+/// a field per column. In CSV and TSV, strings are written as is, `null` as
+/// an empty field and other values as compact JSON.
+pub fn render_projection(format: OutputFormat, projection: &Projection) -> String {
+    let across_trees = projection.entries.iter().any(|info| info.tree.is_some());
+    let records: Vec<serde_json::Map<String, serde_json::Value>> = projection
+        .entries
+        .iter()
+        .map(|info| {
+            let mut record = serde_json::Map::new();
+            if across_trees {
+                let (tree, tree_encoding) = encode_bytes(info.tree.as_deref().unwrap_or_default());
+                record.insert("tree".to_string(), tree.into());
+                record.insert("tree_encoding".to_string(), tree_encoding.into());
+            }
+            let (key, key_encoding) = encode_bytes(&info.key);
+            record.insert("key".to_string(), key.into());
+            record.insert("key_encoding".to_string(), key_encoding.into());
+            for (path, value) in projection.columns.iter().zip(projection.row(info)) {
+                record.insert(path.to_string(), value);
+            }
+            record
+        })
+        .collect();
+
+    let mut headers = Vec::new();
+    if across_trees {
+        headers.extend(["tree".to_string(), "tree_encoding".to_string()]);
+    }
+    headers.extend(["key".to_string(), "key_encoding".to_string()]);
+    headers.extend(projection.columns.iter().map(ToString::to_string));
+    render_json_records(format, &headers, &records)
+}
+
+/// Render the result of a SQL statement as records of a field per column,
+/// written in CSV and TSV as query results are
+pub fn render_rows(format: OutputFormat, rows: &Rows) -> String {
+    let records: Vec<serde_json::Map<String, serde_json::Value>> = rows
+        .rows
+        .iter()
+        .map(|row| {
+            rows.columns
+                .iter()
+                .cloned()
+                .zip(row.iter().cloned())
+                .collect()
+        })
+        .collect();
+    render_json_records(format, &rows.columns, &records)
+}
+
+/// Render records of JSON values, with `headers` naming their fields in order
+fn render_json_records(
+    format: OutputFormat,
+    headers: &[String],
+    records: &[serde_json::Map<String, serde_json::Value>],
+) -> String {
+    let delimited = |separator: char, escape: fn(&str) -> String| {
+        let rows = records.iter().map(|record| {
+            record
+                .values()
+                .map(|value| match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    serde_json::Value::Null => String::new(),
+                    other => other.to_string(),
+                })
+                .collect()
+        });
+        delimited_lines(headers, rows, separator, escape)
+    };
+
+    match format {
+        OutputFormat::Text | OutputFormat::Json => {
+            serde_json::to_string_pretty(&records).expect("records serialize to JSON")
+        }
+        OutputFormat::Ndjson => records
+            .iter()
+            .map(|r| serde_json::to_string(r).expect("records serialize to JSON"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Csv => delimited(',', escape_csv),
+        OutputFormat::Tsv => delimited('\t', escape_tsv),
+    }
+}
+
+/// Render a single record; JSON output is an object rather than an array
+pub fn render_record<T: Record>(format: OutputFormat, record: &T) -> String {
+    match format {
+        OutputFormat::Text | OutputFormat::Json => {
+            serde_json::to_string_pretty(record).expect("records serialize to JSON")
+        }
+        _ => render_records(format, std::slice::from_ref(record)),
+    }
+}
+
+fn render_delimited<T: Record>(
+    records: &[T],
+    separator: char,
+    escape: fn(&str) -> String,
+) -> String {
+    let rows = records.iter().map(Record::fields);
+    delimited_lines(T::headers(), rows, separator, escape)
+}
+
+/// A header row and a row per record, with every field escaped
+fn delimited_lines<H: AsRef<str>>(
+    headers: &[H],
+    rows: impl Iterator<Item = Vec<String>>,
+    separator: char,
+    escape: fn(&str) -> String,
+) -> String {
+    let separator = separator.to_string();
+    let headers: Vec<&str> = headers.iter().map(AsRef::as_ref).collect();
+    let mut lines = vec![headers.join(&separator)];
+    for fields in rows {
+        let fields: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+        lines.push(fields.join(&separator));
+    }
+    lines.join("\n")
+}
+
+/// Quote a CSV field when needed (RFC 4180)
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escape a TSV field with backslash sequences so every record stays on one line
+fn escape_tsv(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for ch in field.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_record(key: &str, value: &[u8]) -> KeyRecord {
+        KeyRecord::from(&KeyInfo::new(key.as_bytes(), value))
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("NDJSON".parse::<OutputFormat>(), Ok(OutputFormat::Ndjson));
+        assert_eq!("tsv".parse::<OutputFormat>(), Ok(OutputFormat::Tsv));
+        assert!("xml".parse::<OutputFormat>().is_err());
+        assert_eq!(OutputFormat::Csv.to_string(), "csv");
+    }
+
+    #[test]
+    fn test_encode_bytes() {
+        assert_eq!(encode_bytes(b"hello"), ("hello".to_string(), "utf8"));
+        assert_eq!(
+            encode_bytes(&[0, 1, 2, 255]),
+            ("AAEC/w==".to_string(), "base64")
+        );
+    }
+
+    #[test]
+    fn test_render_json_and_ndjson() {
+        let records = vec![key_record("a", b"1"), key_record("b", b"2")];
+
+        let json = render_records(OutputFormat::Json, &records);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[1]["key"], "b");
+        assert_eq!(parsed[1]["value_encoding"], "utf8");
+
+        let ndjson = render_records(OutputFormat::Ndjson, &records);
+        assert_eq!(ndjson.lines().count(), 2);
+        assert!(ndjson.starts_with("{\"key\":\"a\""));
+
+        let single = render_record(OutputFormat::Json, &CountRecord { count: 3 });
+        let parsed: serde_json::Value = serde_json::from_str(&single).unwrap();
+        assert_eq!(parsed["count"], 3);
+    }
+
+    #[test]
+    fn test_render_csv_and_tsv() {
+        let records = vec![key_record("a,b", b"say \"hi\""), key_record("c", b"x\ty\n")];
+
+        let csv = render_records(OutputFormat::Csv, &records);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "key,key_encoding,size,content_type,value,value_encoding"
+        );
+        assert_eq!(lines[1], "\"a,b\",utf8,8,text,\"say \"\"hi\"\"\",utf8");
+
+        let tsv = render_records(OutputFormat::Tsv, &records);
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "key\tkey_encoding\tsize\tcontent_type\tvalue\tvalue_encoding"
+        );
+        assert_eq!(lines[2], "c\tutf8\t4\ttext\tx\\ty\\n\tutf8");
+    }
+}
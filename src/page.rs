@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Which part of a listing to return (`--offset`, `--limit`), and in what
+/// order (`--sort`, `--reverse`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Page {
+    /// Matching items to skip
+    pub offset: usize,
+    /// Most items to return; all of them when `None`
+    pub limit: Option<usize>,
+    pub sort: Sort,
+    /// List from the end of the order
+    pub reverse: bool,
+}
+
+/// What a listing of entries is ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Sort {
+    /// Keys as stored, byte by byte, as SLED iterates them
+    #[default]
+    Bytes,
+    /// Keys as displayed, with runs of digits compared by value
+    Key,
+    /// Value size
+    Size,
+    /// Values as stored, byte by byte
+    Value,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bytes" => Ok(Sort::Bytes),
+            "key" => Ok(Sort::Key),
+            "size" => Ok(Sort::Size),
+            "value" => Ok(Sort::Value),
+            _ => Err(format!(
+                "Unknown sort '{}'. Use bytes, key, size or value",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Sort::Bytes => "bytes",
+            Sort::Key => "key",
+            Sort::Size => "size",
+            Sort::Value => "value",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Compare text with runs of digits compared by value, so `user_2` sorts
+/// before `user_10`. Texts that differ only in leading zeros are equal.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            // The shorter text, once the other runs out, sorts first
+            return b.is_empty().cmp(&a.is_empty());
+        };
+        let ordering = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (x, rest_a) = split_digits(a);
+            let (y, rest_b) = split_digits(b);
+            (a, b) = (rest_a, rest_b);
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            a = &a[x.len_utf8()..];
+            b = &b[y.len_utf8()..];
+            x.cmp(&y)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// The leading digits of `text` without their leading zeros, and the rest
+fn split_digits(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    (text[..end].trim_start_matches('0'), &text[end..])
+}
+
+impl Page {
+    /// Use `limit` unless a limit was given explicitly
+    pub fn or_limit(self, limit: Option<usize>) -> Self {
+        Self {
+            limit: self.limit.or(limit),
+            ..self
+        }
+    }
+}
+
+/// One page of a listing, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing<T> {
+    pub items: Vec<T>,
+    /// Number of items before this page in the whole listing
+    pub start: usize,
+    /// Whether the listing continues after this page
+    pub more: bool,
+}
+
+impl<T> Listing<T> {
+    /// Whether this page is the whole listing
+    pub fn is_complete(&self) -> bool {
+        self.start == 0 && !self.more
+    }
+}
+
+/// A whole listing as a single page
+impl<T> From<Vec<T>> for Listing<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self {
+            items,
+            start: 0,
+            more: false,
+        }
+    }
+}
+
+impl<T> Deref for Listing<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+/// Collect a page of `items`: skip `offset` of them, then take up to `limit`.
+///
+/// One item past the limit is read to find out whether there are more, so an
+/// unbounded source is never read to the end. `start` is the position of the
+/// first of `items` in the whole listing. `on_item` sees each item taken, with
+/// its position, as soon as it is read.
+pub fn paginate<T, E>(
+    items: impl IntoIterator<Item = Result<T, E>>,
+    page: Page,
+    start: usize,
+    mut on_item: impl FnMut(usize, &T),
+) -> Result<Listing<T>, E> {
+    let mut items = items.into_iter().skip(page.offset);
+    let mut listing = Listing {
+        items: Vec::new(),
+        start: start + page.offset,
+        more: false,
+    };
+
+    for item in items.by_ref() {
+        if page.limit == Some(listing.items.len()) {
+            listing.more = true;
+            break;
+        }
+        let item = item?;
+        on_item(listing.start + listing.items.len(), &item);
+        listing.items.push(item);
+    }
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(count: u32) -> impl Iterator<Item = Result<u32, ()>> {
+        (0..count).map(Ok)
+    }
+
+    #[test]
+    fn test_paginate() {
+        let page = Page {
+            offset: 2,
+            limit: Some(3),
+            ..Page::default()
+        };
+        let mut seen = Vec::new();
+        let listing = paginate(numbers(10), page, 0, |position, &n| {
+            seen.push((position, n))
+        })
+        .unwrap();
+        assert_eq!(listing.items, vec![2, 3, 4]);
+        assert_eq!(seen, vec![(2, 2), (3, 3), (4, 4)]);
+        assert_eq!(listing.start, 2);
+        assert!(listing.more);
+
+        // A page that ends exactly at the end of the listing has no more
+        let listing = paginate(numbers(5), page, 10, |_, _| ()).unwrap();
+        assert_eq!(listing.items, vec![2, 3, 4]);
+        assert_eq!(listing.start, 12);
+        assert!(!listing.more);
+
+        let listing = paginate(numbers(4), Page::default(), 0, |_, _| ()).unwrap();
+        assert!(listing.is_complete());
+        assert_eq!(listing.len(), 4);
+
+        let errors = [Ok(1), Err("broken"), Ok(3)];
+        assert_eq!(
+            paginate(errors, Page::default(), 0, |_, _| ()),
+            Err("broken")
+        );
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut keys = vec!["user_10", "user_9", "user_1", "user", "item_2", "user_02b"];
+        keys.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            keys,
+            ["item_2", "user", "user_1", "user_02b", "user_9", "user_10"]
+        );
+        assert_eq!(natural_cmp("v007", "v7"), Ordering::Equal);
+        assert_eq!("Size".parse::<Sort>(), Ok(Sort::Size));
+        assert!("random".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn test_page_or_limit() {
+        let page = Page {
+            offset: 5,
+            limit: None,
+            ..Page::default()
+        };
+        assert_eq!(page.or_limit(Some(50)).limit, Some(50));
+        assert_eq!(page.or_limit(Some(50)).offset, 5);
+
+        let page = Page {
+            offset: 0,
+            limit: Some(10),
+            ..Page::default()
+        };
+        assert_eq!(page.or_limit(Some(50)).limit, Some(10));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::bytes;
+use crate::codec::{KeyCodec, TreeCodecs};
+use crate::db;
+use crate::decode::Decoder;
+use crate::error::SledoViewError;
+use regex::bytes::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Codecs for the trees of a database, read from a TOML or YAML file.
+///
+/// ```toml
+/// [[tree]]
+/// name = "users_*"
+/// key = "hex"
+/// value = "msgpack"
+/// ```
+///
+/// Rules are tried in order and the first whose glob matches the tree name
+/// applies. The default tree is matched as `__sled__default`.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    rules: Vec<TreeRule>,
+}
+
+#[derive(Debug, Clone)]
+struct TreeRule {
+    matcher: Regex,
+    codecs: TreeCodecs,
+}
+
+/// A rule as written in the profile file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    name: String,
+    key: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    #[serde(default)]
+    tree: Vec<RawRule>,
+}
+
+impl Profile {
+    /// Load a profile; files ending in `.yaml` or `.yml` are read as YAML,
+    /// anything else as TOML
+    pub fn load(path: &Path) -> Result<Self, SledoViewError> {
+        let invalid = |message: String| SledoViewError::InvalidProfile {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let content = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let is_yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+
+        let parsed = if is_yaml {
+            Self::from_yaml(&content)
+        } else {
+            Self::from_toml(&content)
+        };
+        parsed.map_err(invalid)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        let raw: RawProfile = toml::from_str(content).map_err(|e| e.to_string())?;
+        Self::from_raw(raw)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self, String> {
+        let raw: RawProfile = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        Self::from_raw(raw)
+    }
+
+    fn from_raw(raw: RawProfile) -> Result<Self, String> {
+        let mut rules = Vec::with_capacity(raw.tree.len());
+        for rule in raw.tree {
+            let context = |message: String| format!("tree '{}': {}", rule.name, message);
+
+            let pattern = bytes::parse_literal(&rule.name).map_err(|e| context(e.to_string()))?;
+            let matcher =
+                db::compile_pattern(&pattern, false, false).map_err(|e| context(e.to_string()))?;
+            let key = match &rule.key {
+                Some(key) => key.parse::<KeyCodec>().map_err(context)?,
+                None => KeyCodec::default(),
+            };
+            let value = match &rule.value {
+                Some(value) => Some(value.parse::<Decoder>().map_err(context)?),
+                None => None,
+            };
+
+            rules.push(TreeRule {
+                matcher,
+                codecs: TreeCodecs { key, value },
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// The codecs for a tree; the defaults when no rule matches
+    pub fn codecs_for(&self, tree: &[u8]) -> TreeCodecs {
+        self.rules
+            .iter()
+            .find(|rule| rule.matcher.is_match(tree))
+            .map(|rule| rule.codecs.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::BincodeType;
+
+    #[test]
+    fn test_profile_from_toml() {
+        let profile = Profile::from_toml(
+            r#"
+            [[tree]]
+            name = "users"
+            key = "hex"
+            value = "msgpack"
+
+            [[tree]]
+            name = "counter_*"
+            value = "bincode:u64"
+
+            [[tree]]
+            name = "*"
+            key = "base64"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profile.codecs_for(b"users"),
+            TreeCodecs {
+                key: KeyCodec::Hex,
+                value: Some(Decoder::MessagePack)
+            }
+        );
+        assert_eq!(
+            profile.codecs_for(b"counter_daily"),
+            TreeCodecs {
+                key: KeyCodec::Text,
+                value: Some(Decoder::Bincode(BincodeType::U64))
+            }
+        );
+        // First match wins, so "users_archive" falls through to "*"
+        assert_eq!(profile.codecs_for(b"users_archive").key, KeyCodec::Base64);
+        assert!(Profile::default().codecs_for(b"users").is_default());
+    }
+
+    #[test]
+    fn test_profile_from_yaml() {
+        let profile = Profile::from_yaml(
+            "tree:\n  - name: \"\\\\x00*\"\n    key: hex\n  - name: events\n    value: cbor\n",
+        )
+        .unwrap();
+        assert_eq!(profile.codecs_for(b"\x00ids").key, KeyCodec::Hex);
+        assert_eq!(profile.codecs_for(b"events").value, Some(Decoder::Cbor));
+    }
+
+    #[test]
+    fn test_profile_errors() {
+        let err = Profile::from_toml("[[tree]]\nname = \"users\"\nvalue = \"xml\"\n").unwrap_err();
+        assert!(err.contains("tree 'users'"));
+        assert!(err.contains("Unknown decoder 'xml'"));
+
+        assert!(Profile::from_toml("[[tree]]\nname = \"users\"\ncolor = \"red\"\n").is_err());
+        assert!(Profile::from_yaml("tree: [").is_err());
+
+        let err = Profile::load(Path::new("/nonexistent/profile.toml")).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid profile /nonexistent/profile.toml"));
+    }
+}
@@ -0,0 +1,481 @@
+use crate::error::SledoViewError;
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A condition on JSON documents, like `$.status == "failed" && $.retries > 3`.
+///
+/// Operands are paths into the document (`$`, `$.name`, `$["a key"]`,
+/// `$.items[0]`, `$.items[-1]`) or JSON literals, with strings in double or
+/// single quotes. Operands are compared with `==`, `!=`, `<`, `<=`, `>` and
+/// `>=`, strings matched against a regex with `=~`, and conditions combined
+/// with `&&`, `||`, `!` and parentheses. A path on its own holds when it
+/// exists and is neither `null` nor `false`; a missing path reads as `null`.
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Comparison, Operand),
+    Matches(Operand, Regex),
+    Holds(Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Path(Path),
+    Literal(Value),
+}
+
+/// A path into a JSON document, like `$.items[0].name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Field(String),
+    /// An array index, counted from the end when negative
+    Index(i64),
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, SledoViewError> {
+        let mut parser = Parser::new(query);
+        let expr = parser.or()?;
+        parser.end()?;
+        Ok(Self { expr })
+    }
+
+    /// Whether the document meets the condition
+    pub fn matches(&self, document: &Value) -> bool {
+        self.expr.eval(document)
+    }
+}
+
+impl Path {
+    pub fn parse(path: &str) -> Result<Self, SledoViewError> {
+        let mut parser = Parser::new(path);
+        let path = parser.path()?;
+        parser.end()?;
+        Ok(path)
+    }
+
+    /// The value at this path, if the document has one
+    pub fn select<'a>(&self, document: &'a Value) -> Option<&'a Value> {
+        self.steps
+            .iter()
+            .try_fold(document, |value, step| match (step, value) {
+                (Step::Field(name), Value::Object(map)) => map.get(name),
+                (Step::Index(index), Value::Array(items)) => {
+                    let index = if *index < 0 {
+                        items.len().checked_sub(index.unsigned_abs() as usize)?
+                    } else {
+                        *index as usize
+                    };
+                    items.get(index)
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Paths are shown without the leading `$.`, as in `items[0].name`
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.steps.is_empty() {
+            return f.write_str("$");
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Field(name) if is_plain_name(name) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(name)?;
+                }
+                Step::Field(name) => write!(f, "[{}]", Value::String(name.clone()))?,
+                Step::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_name_char)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+impl Expr {
+    fn eval(&self, document: &Value) -> bool {
+        match self {
+            Expr::Or(left, right) => left.eval(document) || right.eval(document),
+            Expr::And(left, right) => left.eval(document) && right.eval(document),
+            Expr::Not(expr) => !expr.eval(document),
+            Expr::Compare(left, comparison, right) => {
+                let (left, right) = (left.eval(document), right.eval(document));
+                match comparison {
+                    Comparison::Eq => json_eq(left, right),
+                    Comparison::Ne => !json_eq(left, right),
+                    Comparison::Lt => json_cmp(left, right) == Some(Ordering::Less),
+                    Comparison::Le => json_cmp(left, right).is_some_and(Ordering::is_le),
+                    Comparison::Gt => json_cmp(left, right) == Some(Ordering::Greater),
+                    Comparison::Ge => json_cmp(left, right).is_some_and(Ordering::is_ge),
+                }
+            }
+            Expr::Matches(operand, regex) => match operand.eval(document) {
+                Value::String(text) => regex.is_match(text),
+                _ => false,
+            },
+            Expr::Holds(operand) => {
+                !matches!(operand.eval(document), Value::Null | Value::Bool(false))
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn eval<'a>(&'a self, document: &'a Value) -> &'a Value {
+        match self {
+            Operand::Path(path) => path.select(document).unwrap_or(&Value::Null),
+            Operand::Literal(value) => value,
+        }
+    }
+}
+
+/// Equality where `1` and `1.0` are the same number
+pub(crate) fn json_eq(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => left == right,
+    }
+}
+
+/// Numbers and strings are ordered among themselves; nothing else is
+pub(crate) fn json_cmp(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Recursive descent over the characters of a query:
+///
+/// ```text
+/// or      := and ("||" and)*
+/// and     := unary ("&&" unary)*
+/// unary   := "!" unary | "(" or ")" | operand (op operand)?
+/// operand := path | string | number | true | false | null
+/// ```
+struct Parser<'a> {
+    source: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            rest: source,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> SledoViewError {
+        SledoViewError::InvalidQuery {
+            query: self.source.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Describe where parsing stopped, for errors
+    fn here(&self) -> String {
+        match self.rest.chars().next() {
+            Some(_) => format!("at '{}'", self.rest),
+            None => "at the end".to_string(),
+        }
+    }
+
+    fn skip_space(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// Consume `token` if the input continues with it
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn end(&mut self) -> Result<(), SledoViewError> {
+        self.skip_space();
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(format!("unexpected {}", self.here())))
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, SledoViewError> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, SledoViewError> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, SledoViewError> {
+        self.skip_space();
+        if self.rest.starts_with('!') && !self.rest.starts_with("!=") {
+            self.rest = &self.rest[1..];
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error(format!("expected ')' {}", self.here())));
+            }
+            return Ok(expr);
+        }
+
+        let left = self.operand()?;
+        if self.eat("=~") {
+            let pattern = match self.operand()? {
+                Operand::Literal(Value::String(pattern)) => pattern,
+                _ => return Err(self.error("=~ takes a regex in quotes")),
+            };
+            let regex = Regex::new(&pattern)
+                .map_err(|_| self.error(format!("invalid regex '{}'", pattern)))?;
+            return Ok(Expr::Matches(left, regex));
+        }
+        // Two-character operators first, so `<=` is not read as `<`
+        let comparisons = [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+        ];
+        for (token, comparison) in comparisons {
+            if self.eat(token) {
+                return Ok(Expr::Compare(left, comparison, self.operand()?));
+            }
+        }
+        Ok(Expr::Holds(left))
+    }
+
+    fn operand(&mut self) -> Result<Operand, SledoViewError> {
+        self.skip_space();
+        match self.rest.chars().next() {
+            Some('$') => Ok(Operand::Path(self.path()?)),
+            Some(quote @ ('"' | '\'')) => Ok(Operand::Literal(Value::String(self.string(quote)?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(Operand::Literal(self.number()?)),
+            Some(c) if is_name_char(c) => {
+                let end = self
+                    .rest
+                    .find(|c| !is_name_char(c))
+                    .unwrap_or(self.rest.len());
+                let value = match &self.rest[..end] {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "null" => Value::Null,
+                    word => {
+                        return Err(self.error(format!(
+                            "unknown word '{}', quote strings as \"{}\" or '{}'",
+                            word, word, word
+                        )))
+                    }
+                };
+                self.rest = &self.rest[end..];
+                Ok(Operand::Literal(value))
+            }
+            _ => Err(self.error(format!("expected a path or a value {}", self.here()))),
+        }
+    }
+
+    fn path(&mut self) -> Result<Path, SledoViewError> {
+        self.skip_space();
+        self.rest = self
+            .rest
+            .strip_prefix('$')
+            .ok_or_else(|| self.error("paths start with '$'"))?;
+
+        let mut steps = Vec::new();
+        loop {
+            if let Some(rest) = self.rest.strip_prefix('.') {
+                let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                if end == 0 {
+                    self.rest = rest;
+                    return Err(self.error(format!("expected a field name {}", self.here())));
+                }
+                steps.push(Step::Field(rest[..end].to_string()));
+                self.rest = &rest[end..];
+            } else if let Some(rest) = self.rest.strip_prefix('[') {
+                self.rest = rest;
+                self.skip_space();
+                let step = match self.rest.chars().next() {
+                    Some(quote @ ('"' | '\'')) => Step::Field(self.string(quote)?),
+                    _ => match self.number()? {
+                        Value::Number(n) if n.is_i64() => Step::Index(n.as_i64().unwrap_or(0)),
+                        _ => return Err(self.error("array indexes are whole numbers")),
+                    },
+                };
+                if !self.eat("]") {
+                    return Err(self.error(format!("expected ']' {}", self.here())));
+                }
+                steps.push(step);
+            } else {
+                return Ok(Path { steps });
+            }
+        }
+    }
+
+    /// A quoted string with JSON escapes, plus `\'` in single quotes
+    fn string(&mut self, quote: char) -> Result<String, SledoViewError> {
+        let mut text = String::new();
+        let mut chars = self.rest.char_indices().skip(1);
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(text);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some(c @ ('"' | '\'' | '\\' | '/')) => text.push(c),
+                    _ => return Err(self.error("unsupported escape in string")),
+                },
+                c => text.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn number(&mut self) -> Result<Value, SledoViewError> {
+        self.skip_space();
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+            .unwrap_or(self.rest.len());
+        let number = &self.rest[..end];
+        let value = serde_json::from_str::<serde_json::Number>(number)
+            .map_err(|_| self.error(format!("expected a number {}", self.here())))?;
+        self.rest = &self.rest[end..];
+        Ok(Value::Number(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_query_matches() {
+        let job = json!({
+            "status": "failed",
+            "retries": 4,
+            "tags": ["nightly", "db"],
+            "owner": {"name": "Jane", "team lead": true},
+            "done": false,
+            "note": null
+        });
+        let matches = |query: &str| Query::parse(query).unwrap().matches(&job);
+
+        assert!(matches(r#"$.status == "failed" && $.retries > 3"#));
+        assert!(matches("$.status == 'failed'"));
+        assert!(!matches("$.retries > 4"));
+        assert!(matches("$.retries >= 4.0 && $.retries == 4.0"));
+        assert!(matches("$.tags[0] == 'nightly' && $.tags[-1] == 'db'"));
+        assert!(matches(r#"$.owner["team lead"] && $.owner.name =~ "^J""#));
+        assert!(matches("!$.done && !$.note && !$.missing"));
+        assert!(matches("$.missing == null && $.missing != 1"));
+        assert!(!matches("$.status > 3"));
+        assert!(matches(
+            "$.retries < 2 || ($.status != 'ok' && !($.retries < 4))"
+        ));
+        assert!(matches("$.owner"));
+    }
+
+    #[test]
+    fn test_query_parse_errors() {
+        for query in [
+            "",
+            "$.status ==",
+            "$.status == failed",
+            "($.a",
+            "$.a == 'x",
+            "$.",
+            "$.a[1.5]",
+            "$.a =~ 1",
+            "$.a =~ '('",
+            "$.a $.b",
+        ] {
+            assert!(
+                matches!(
+                    Query::parse(query),
+                    Err(SledoViewError::InvalidQuery { .. })
+                ),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_path_select() {
+        let doc = json!({"a": {"b": [1, {"c": "x"}]}, "odd key": 2});
+        let select = |path: &str| Path::parse(path).unwrap().select(&doc).cloned();
+
+        assert_eq!(select("$.a.b[1].c"), Some(json!("x")));
+        assert_eq!(select("$['odd key']"), Some(json!(2)));
+        assert_eq!(select("$.a.b[-2]"), Some(json!(1)));
+        assert_eq!(select("$.a.b[2]"), None);
+        assert_eq!(select("$"), Some(doc.clone()));
+        assert!(Path::parse("status").is_err());
+
+        assert_eq!(Path::parse("$.a.b[1].c").unwrap().to_string(), "a.b[1].c");
+        assert_eq!(
+            Path::parse("$['odd key']").unwrap().to_string(),
+            "[\"odd key\"]"
+        );
+        assert_eq!(Path::parse("$").unwrap().to_string(), "$");
+    }
+}
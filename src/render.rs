@@ -0,0 +1,1989 @@
+use crate::bytes;
+use crate::db::{KeyInfo, Scan, ScanObserver, StagedWrite};
+use crate::decode::{self, ContentType, Decoder};
+use crate::error::SledoViewError;
+use crate::hexdump;
+use crate::interrupt;
+use crate::output::{
+    self, Ack, AckRecord, ByteView, CommandOutput, Copied, CopiedRecord, CountRecord,
+    DecodedRecord, DecodedValue, DeletedRecord, Deletion, KeyRecord, Located, OutputFormat,
+    Projection, Rows, StagedRecord, TreeKeyRecord, TreeRecord,
+};
+use crate::page::Listing;
+use colored::*;
+use std::fmt::{self, Write};
+use std::io::{self, IsTerminal};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Keys, matches or trees listed per page in text output, unless `--limit` is given
+pub const TEXT_PAGE_SIZE: usize = 50;
+
+/// Keys listed when previewing a bulk delete, or after a bulk copy or move
+const SAMPLE_SIZE: usize = 10;
+
+/// How long a scan runs before its progress is shown, and how often it is updated
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Turns the result of a command into text
+pub trait Renderer {
+    /// Write `output` to `out`, one newline-terminated line at a time
+    fn render(&self, output: &CommandOutput, out: &mut dyn Write) -> fmt::Result;
+}
+
+/// Colored, human-readable output for the terminal
+pub struct TextRenderer;
+
+/// Machine-readable output in one of the record formats
+pub struct RecordRenderer {
+    format: OutputFormat,
+}
+
+impl RecordRenderer {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+}
+
+/// The renderer for an output format
+pub fn renderer_for(format: OutputFormat) -> Box<dyn Renderer> {
+    if format.is_text() {
+        Box::new(TextRenderer)
+    } else {
+        Box::new(RecordRenderer::new(format))
+    }
+}
+
+/// Render command output to a string in the given format
+pub fn render(output: &CommandOutput, format: OutputFormat) -> String {
+    let mut rendered = String::new();
+    renderer_for(format)
+        .render(output, &mut rendered)
+        .expect("writing to a String cannot fail");
+    rendered
+}
+
+/// Render command output to standard output in the given format
+pub fn print(output: &CommandOutput, format: OutputFormat) {
+    print!("{}", render(output, format));
+}
+
+/// Prints the entries of a text listing as a scan finds them.
+///
+/// While a scan runs for long, the number of keys read is shown on standard
+/// error (when it is a terminal) and Ctrl-C is checked between keys.
+pub struct TextStream {
+    /// Whether any entry has been printed, leaving only the summary to print
+    streamed: bool,
+    started: Instant,
+    last_progress: Option<Instant>,
+    show_progress: bool,
+    found: usize,
+    /// The tree of the last entry printed, in a listing across trees
+    tree: Option<Vec<u8>>,
+}
+
+impl TextStream {
+    pub fn new() -> Self {
+        Self {
+            streamed: false,
+            started: Instant::now(),
+            last_progress: None,
+            show_progress: io::stderr().is_terminal(),
+            found: 0,
+            tree: None,
+        }
+    }
+
+    /// Print what the scan did not already print
+    pub fn finish(mut self, output: &CommandOutput) {
+        self.clear_progress();
+        if !self.streamed {
+            print(output, OutputFormat::Text);
+            return;
+        }
+
+        let mut rendered = String::new();
+        let summary = match output {
+            CommandOutput::Keys(keys) => write_listing_summary(keys, "keys", &mut rendered),
+            CommandOutput::SearchResults(results) => {
+                write_listing_summary(results, "matches", &mut rendered)
+            }
+            _ => TextRenderer.render(output, &mut rendered),
+        };
+        summary.expect("writing to a String cannot fail");
+        print!("{}", rendered);
+    }
+
+    fn clear_progress(&mut self) {
+        if self.last_progress.take().is_some() {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+impl Default for TextStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanObserver for TextStream {
+    fn scanned(&mut self, count: usize) -> Result<(), SledoViewError> {
+        interrupt::check()?;
+
+        if self.show_progress && count.is_multiple_of(1024) {
+            let since = self.last_progress.unwrap_or(self.started);
+            if since.elapsed() >= PROGRESS_INTERVAL {
+                eprint!(
+                    "\r\x1b[2K{}",
+                    format!("Scanning... {} keys read, {} found", count, self.found).bright_black()
+                );
+                self.last_progress = Some(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    fn matched(&mut self, scan: &Scan, position: usize, info: &KeyInfo) {
+        // Columns are aligned over the whole page, so a table is printed
+        // once complete
+        if matches!(scan, Scan::Query { select, .. } if !select.is_empty()) {
+            self.found += 1;
+            return;
+        }
+
+        self.clear_progress();
+        let mut line = String::new();
+        let written =
+            write_tree_heading(info, &mut self.tree, &mut line).and_then(|()| match scan {
+                Scan::Values { .. } | Scan::Query { .. } => {
+                    write_match_line(position, info, &mut line)
+                }
+                _ => write_key_line(position, info, &mut line),
+            });
+        written.expect("writing to a String cannot fail");
+        print!("{}", line);
+
+        self.streamed = true;
+        self.found += 1;
+    }
+}
+
+impl Drop for TextStream {
+    /// A cancelled or failed scan must not leave its progress on screen
+    fn drop(&mut self) {
+        self.clear_progress();
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render(&self, output: &CommandOutput, out: &mut dyn Write) -> fmt::Result {
+        match output {
+            CommandOutput::Count(count) => writeln!(
+                out,
+                "{} {}",
+                "Total records:".bright_blue().bold(),
+                count.to_string().bright_yellow().bold()
+            ),
+            CommandOutput::Keys(keys) => write_keys(keys, out),
+            CommandOutput::KeyInfo(info) => write_key_info(info, out),
+            CommandOutput::Bytes(view) => write_byte_view(view, out),
+            CommandOutput::Decoded(decoded) => write_decoded(decoded, out),
+            CommandOutput::SearchResults(results) => write_search_results(results, out),
+            CommandOutput::Projection(projection) => write_projection(projection, out),
+            CommandOutput::Rows(rows) => write_rows(rows, out),
+            CommandOutput::Trees(trees) => write_trees(trees, out),
+            CommandOutput::Located(located) => write_located(located, out),
+            CommandOutput::Ack(ack) => write_ack(ack, out),
+            CommandOutput::Copied(copied) => write_copied(copied, out),
+            CommandOutput::Deletion(deletion) => write_deletion(deletion, out),
+            CommandOutput::Status(staged) => write_status(staged.as_deref(), out),
+            CommandOutput::FormatChanged(format) => writeln!(
+                out,
+                "{} {}",
+                "✓".bright_green().bold(),
+                format!("Output format set to {}", format).bright_green()
+            ),
+            CommandOutput::CurrentFormat(format) => writeln!(out, "Output format: {}", format),
+            CommandOutput::Help => write_help(out),
+            CommandOutput::Empty => Ok(()),
+        }
+    }
+}
+
+impl Renderer for RecordRenderer {
+    fn render(&self, output: &CommandOutput, out: &mut dyn Write) -> fmt::Result {
+        let format = self.format;
+        let rendered = match output {
+            CommandOutput::Count(count) => {
+                output::render_record(format, &CountRecord { count: *count })
+            }
+            CommandOutput::Keys(keys) | CommandOutput::SearchResults(keys)
+                if keys.iter().any(|info| info.tree.is_some()) =>
+            {
+                let records: Vec<TreeKeyRecord> = keys.iter().map(TreeKeyRecord::from).collect();
+                output::render_records(format, &records)
+            }
+            CommandOutput::Keys(keys) | CommandOutput::SearchResults(keys) => {
+                let records: Vec<KeyRecord> = keys.iter().map(KeyRecord::from).collect();
+                output::render_records(format, &records)
+            }
+            CommandOutput::KeyInfo(info) => output::render_record(format, &KeyRecord::from(info)),
+            CommandOutput::Bytes(view) => output::render_record(format, &KeyRecord::from(view)),
+            CommandOutput::Decoded(decoded) => {
+                output::render_record(format, &DecodedRecord::from(decoded))
+            }
+            CommandOutput::Projection(projection) => output::render_projection(format, projection),
+            CommandOutput::Rows(rows) => output::render_rows(format, rows),
+            CommandOutput::Trees(trees) => {
+                let records: Vec<TreeRecord> = trees
+                    .iter()
+                    .map(|tree| TreeRecord::from(&tree[..]))
+                    .collect();
+                output::render_records(format, &records)
+            }
+            CommandOutput::Located(located) => {
+                let records: Vec<TreeKeyRecord> =
+                    located.entries.iter().map(TreeKeyRecord::from).collect();
+                output::render_records(format, &records)
+            }
+            CommandOutput::Ack(ack) => output::render_record(format, &AckRecord::from(ack)),
+            CommandOutput::Copied(copied) => {
+                let records: Vec<CopiedRecord> = copied
+                    .pairs
+                    .iter()
+                    .map(|(from, to)| CopiedRecord::new(from, copied.tree.as_deref(), to))
+                    .collect();
+                output::render_records(format, &records)
+            }
+            CommandOutput::Deletion(deletion) => {
+                let records: Vec<DeletedRecord> = deletion
+                    .keys
+                    .iter()
+                    .map(|key| DeletedRecord::new(key, deletion.deleted))
+                    .collect();
+                output::render_records(format, &records)
+            }
+            CommandOutput::Status(staged) => {
+                let records: Vec<StagedRecord> =
+                    staged.iter().flatten().map(StagedRecord::from).collect();
+                output::render_records(format, &records)
+            }
+            // Output that is not data is shown as text, so scripts can still ask for help
+            CommandOutput::CurrentFormat(_) | CommandOutput::Help => {
+                return TextRenderer.render(output, out)
+            }
+            // Confirmations would corrupt the stream of records
+            CommandOutput::FormatChanged(_) | CommandOutput::Empty => return Ok(()),
+        };
+
+        if rendered.is_empty() {
+            Ok(())
+        } else {
+            writeln!(out, "{}", rendered)
+        }
+    }
+}
+
+fn write_keys(keys: &Listing<KeyInfo>, out: &mut dyn Write) -> fmt::Result {
+    if keys.is_empty() && keys.start == 0 {
+        return writeln!(out, "{}", "No keys found matching the pattern.".yellow());
+    }
+
+    let mut tree = None;
+    for (i, info) in keys.iter().enumerate() {
+        write_tree_heading(info, &mut tree, out)?;
+        write_key_line(keys.start + i, info, out)?;
+    }
+    write_listing_summary(keys, "keys", out)
+}
+
+/// In a listing across trees, the tree's name above its first entry.
+/// `current` is the tree of the entry written before.
+fn write_tree_heading(
+    info: &KeyInfo,
+    current: &mut Option<Vec<u8>>,
+    out: &mut dyn Write,
+) -> fmt::Result {
+    match &info.tree {
+        Some(tree) if current.as_ref() != Some(tree) => {
+            *current = Some(tree.clone());
+            writeln!(
+                out,
+                "{}",
+                format!("[{}]", bytes::escape(tree)).bright_magenta().bold()
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+fn write_key_line(position: usize, info: &KeyInfo, out: &mut dyn Write) -> fmt::Result {
+    writeln!(
+        out,
+        "  {}: {} = {}",
+        (position + 1).to_string().bright_black(),
+        highlight_key(info, |text| text.bright_white()),
+        format_value_preview(info)
+    )
+}
+
+fn write_search_results(results: &Listing<KeyInfo>, out: &mut dyn Write) -> fmt::Result {
+    if results.is_empty() && results.start == 0 {
+        return writeln!(out, "{}", "No values found matching the pattern.".yellow());
+    }
+
+    let mut tree = None;
+    for (i, info) in results.iter().enumerate() {
+        write_tree_heading(info, &mut tree, out)?;
+        write_match_line(results.start + i, info, out)?;
+    }
+    write_listing_summary(results, "matches", out)
+}
+
+fn write_match_line(position: usize, info: &KeyInfo, out: &mut dyn Write) -> fmt::Result {
+    let value = display_value(info);
+    let shown = truncate_value(&value, 100);
+    // The match can only be shown in a value displayed as stored, and not
+    // where it was cut off
+    let visible = if shown == value {
+        value.len()
+    } else {
+        shown.len() - "...".len()
+    };
+    let span = info
+        .matched
+        .value
+        .as_ref()
+        .filter(|span| value.as_bytes() == info.value && span.end <= visible);
+
+    writeln!(
+        out,
+        "  {}: {} {} {}",
+        (position + 1).to_string().bright_black(),
+        highlight_key(info, |text| text.bright_cyan().bold()),
+        "=>".bright_black(),
+        highlight(&shown, span, |text| text.bright_white())
+    )
+}
+
+/// Query results as a table of the key and a column per path, aligned over
+/// the page
+fn write_projection(projection: &Projection, out: &mut dyn Write) -> fmt::Result {
+    let entries = &projection.entries;
+    if entries.is_empty() && entries.start == 0 {
+        return writeln!(out, "{}", "No values found matching the query.".yellow());
+    }
+
+    let mut headers = vec!["key".to_string()];
+    headers.extend(projection.columns.iter().map(ToString::to_string));
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|info| {
+            let mut cells = vec![truncate_value(&info.display_key(), 40)];
+            cells.extend(projection.row(info).iter().map(table_cell));
+            cells
+        })
+        .collect();
+
+    let widths = column_widths(&headers, &rows);
+    let number_width = (entries.start + entries.len()).to_string().len() + 1;
+    write_table_header(&headers, &widths, number_width, out)?;
+
+    let mut tree = None;
+    for (i, (info, cells)) in entries.iter().zip(&rows).enumerate() {
+        write_tree_heading(info, &mut tree, out)?;
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (text, width))| match column {
+                0 => pad(text, *width).bright_cyan().bold().to_string(),
+                _ => pad(text, *width).bright_white().to_string(),
+            })
+            .collect();
+        writeln!(
+            out,
+            "  {} {}",
+            pad(&format!("{}:", entries.start + i + 1), number_width).bright_black(),
+            cells.join("  ").trim_end()
+        )?;
+    }
+    write_listing_summary(entries, "matches", out)
+}
+
+/// The result of a SQL statement as a table aligned over its rows
+fn write_rows(rows: &Rows, out: &mut dyn Write) -> fmt::Result {
+    if rows.rows.is_empty() {
+        return writeln!(out, "{}", "No rows.".yellow());
+    }
+
+    let cells: Vec<Vec<String>> = rows
+        .rows
+        .iter()
+        .map(|row| row.iter().map(table_cell).collect())
+        .collect();
+    let widths = column_widths(&rows.columns, &cells);
+    let number_width = rows.rows.len().to_string().len() + 1;
+    write_table_header(&rows.columns, &widths, number_width, out)?;
+
+    for (i, row) in cells.iter().enumerate() {
+        let row: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(text, width)| pad(text, *width).bright_white().to_string())
+            .collect();
+        writeln!(
+            out,
+            "  {} {}",
+            pad(&format!("{}:", i + 1), number_width).bright_black(),
+            row.join("  ").trim_end()
+        )?;
+    }
+    writeln!(
+        out,
+        "{} {} {}",
+        "Found".bright_blue(),
+        rows.rows.len().to_string().bright_yellow().bold(),
+        if rows.rows.len() == 1 { "row" } else { "rows" }.bright_blue()
+    )
+}
+
+/// A JSON value in a table: strings as they are, anything else as JSON
+fn table_cell(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    truncate_value(&text, 40)
+}
+
+/// The width of each column, to fit its header and every cell
+fn column_widths(headers: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for cells in rows {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    widths
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{:<width$}", text, width = width)
+}
+
+/// The column headers, after room for the row numbers
+fn write_table_header(
+    headers: &[String],
+    widths: &[usize],
+    number_width: usize,
+    out: &mut dyn Write,
+) -> fmt::Result {
+    let header: Vec<String> = headers
+        .iter()
+        .zip(widths)
+        .map(|(text, width)| pad(text, *width).bright_blue().bold().to_string())
+        .collect();
+    writeln!(out, "  {} {}", " ".repeat(number_width), header.join("  "))
+}
+
+/// The key as displayed, with the part a pattern matched highlighted
+fn highlight_key(info: &KeyInfo, style: fn(&str) -> ColoredString) -> String {
+    let key = info.display_key();
+    // A typed key is matched as displayed, any other key as stored
+    let span = info
+        .matched
+        .key
+        .as_ref()
+        .filter(|_| info.codecs.key.is_typed() || key.as_bytes() == info.key);
+    highlight(&key, span, style)
+}
+
+/// `text` in `style`, with `span` underlined when a pattern matched only
+/// part of it
+fn highlight(text: &str, span: Option<&Range<usize>>, style: fn(&str) -> ColoredString) -> String {
+    let partial = span.filter(|span| !span.is_empty() && span.len() < text.len());
+    match partial.and_then(|span| Some((span, text.get(span.clone())?))) {
+        Some((span, matched)) => format!(
+            "{}{}{}",
+            style(&text[..span.start]),
+            style(matched).underline(),
+            style(&text[span.end..])
+        ),
+        None => style(text).to_string(),
+    }
+}
+
+fn write_trees(trees: &Listing<Vec<u8>>, out: &mut dyn Write) -> fmt::Result {
+    if trees.is_empty() && trees.start == 0 {
+        return writeln!(out, "{}", "No trees found matching the pattern.".yellow());
+    }
+
+    for tree_name in trees.iter() {
+        writeln!(out, "  {}", bytes::escape(tree_name).bright_cyan())?;
+    }
+    write_listing_summary(trees, "trees", out)
+}
+
+fn write_located(located: &Located, out: &mut dyn Write) -> fmt::Result {
+    if located.entries.is_empty() {
+        return writeln!(
+            out,
+            "{}",
+            format!("Key '{}' not found in any tree.", located.key).yellow()
+        );
+    }
+
+    for info in &located.entries {
+        let tree = info.tree.as_deref().unwrap_or_default();
+        writeln!(
+            out,
+            "  {} {} = {}",
+            format!("[{}]", bytes::escape(tree)).bright_magenta().bold(),
+            info.display_key().bright_white(),
+            format_value_preview(info)
+        )?;
+    }
+    writeln!(
+        out,
+        "{} {} {}",
+        "Found in".bright_blue(),
+        located.entries.len().to_string().bright_yellow().bold(),
+        "trees".bright_blue()
+    )
+}
+
+/// The line after a listing: how many items were found, or which of them a
+/// page holds and how to see the next page
+fn write_listing_summary<T>(listing: &Listing<T>, noun: &str, out: &mut dyn Write) -> fmt::Result {
+    if listing.is_complete() {
+        return writeln!(
+            out,
+            "{} {} {}",
+            "Found".bright_blue(),
+            listing.len().to_string().bright_yellow().bold(),
+            noun.bright_blue()
+        );
+    }
+    if listing.is_empty() {
+        return writeln!(out, "{}", format!("No more {noun}.").yellow());
+    }
+
+    writeln!(
+        out,
+        "{} {}",
+        format!("Showing {noun}").bright_blue(),
+        format!("{}-{}", listing.start + 1, listing.start + listing.len())
+            .bright_yellow()
+            .bold()
+    )?;
+    if listing.more {
+        writeln!(
+            out,
+            "{}",
+            format!("... more {noun} follow, type 'more' to see the next page").bright_yellow()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_ack(ack: &Ack, out: &mut dyn Write) -> fmt::Result {
+    match (ack.command, ack.changed) {
+        ("set" | "cas", _) if ack.staged => {
+            writeln!(
+                out,
+                "{} {} {}",
+                "✓".bright_green().bold(),
+                "Staged set of key".bright_green(),
+                ack.target.bright_cyan().bold()
+            )?;
+            let value = ack.value.as_deref().unwrap_or_default();
+            writeln!(
+                out,
+                "  {} {}",
+                "Value:".bright_blue(),
+                truncate_value(value, 50).bright_white()
+            )
+        }
+        ("delete", true) if ack.staged => writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            "Staged delete of key".bright_green(),
+            ack.target.bright_cyan().bold()
+        ),
+        ("set", _) => {
+            writeln!(
+                out,
+                "{} {} {} {}",
+                "✓".bright_green().bold(),
+                "Successfully set key".bright_green(),
+                ack.target.bright_cyan().bold(),
+                "with value".bright_green()
+            )?;
+            let value = ack.value.as_deref().unwrap_or_default();
+            writeln!(
+                out,
+                "  {} {}",
+                "Value:".bright_blue(),
+                truncate_value(value, 50).bright_white()
+            )
+        }
+        ("cas", _) => {
+            writeln!(
+                out,
+                "{} {} {}",
+                "✓".bright_green().bold(),
+                "Successfully swapped value of key".bright_green(),
+                ack.target.bright_cyan().bold()
+            )?;
+            let value = ack.value.as_deref().unwrap_or_default();
+            writeln!(
+                out,
+                "  {} {}",
+                "Value:".bright_blue(),
+                truncate_value(value, 50).bright_white()
+            )
+        }
+        ("delete", true) => writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            "Successfully deleted key".bright_green(),
+            ack.target.bright_cyan().bold()
+        ),
+        ("delete", false) => writeln!(
+            out,
+            "{} {} {}",
+            "!".bright_yellow().bold(),
+            "Key not found:".bright_yellow(),
+            ack.target.bright_cyan().bold()
+        ),
+        ("select", _) => {
+            writeln!(
+                out,
+                "{} {} {}",
+                "✓".bright_green().bold(),
+                "Selected tree:".bright_green(),
+                ack.target.bright_cyan().bold()
+            )?;
+            match &ack.value {
+                Some(codecs) => writeln!(
+                    out,
+                    "  {} {}",
+                    "Profile:".bright_blue(),
+                    codecs.bright_white()
+                ),
+                None => Ok(()),
+            }
+        }
+        ("unselect", true) => writeln!(
+            out,
+            "{} {}",
+            "✓".bright_green().bold(),
+            "Tree unselected. Now working with the default tree.".bright_green()
+        ),
+        ("unselect", false) => writeln!(
+            out,
+            "{} {}",
+            "!".bright_yellow().bold(),
+            "No tree was previously selected.".bright_yellow()
+        ),
+        ("tree create", true) => writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            "Created tree".bright_green(),
+            ack.target.bright_cyan().bold()
+        ),
+        ("tree create", false) => writeln!(
+            out,
+            "{} {} {}",
+            "!".bright_yellow().bold(),
+            "Tree already exists:".bright_yellow(),
+            ack.target.bright_cyan().bold()
+        ),
+        ("tree drop", _) => writeln!(
+            out,
+            "{} {} {} {}",
+            "✓".bright_green().bold(),
+            "Dropped tree".bright_green(),
+            ack.target.bright_cyan().bold(),
+            format!("and {}", plural(ack.value.as_deref().unwrap_or("0"), "key")).bright_green()
+        ),
+        ("tree clear", _) => writeln!(
+            out,
+            "{} {} {} {}",
+            "✓".bright_green().bold(),
+            format!(
+                "Deleted {} of tree",
+                plural(ack.value.as_deref().unwrap_or("0"), "key")
+            )
+            .bright_green(),
+            ack.target.bright_cyan().bold(),
+            "(the tree is kept)".bright_green()
+        ),
+        ("tree rename" | "tree copy", _) => writeln!(
+            out,
+            "{} {} {} {} {}",
+            "✓".bright_green().bold(),
+            if ack.command == "tree rename" {
+                "Renamed tree"
+            } else {
+                "Copied tree"
+            }
+            .bright_green(),
+            ack.target.bright_cyan().bold(),
+            "to".bright_green(),
+            ack.value
+                .as_deref()
+                .unwrap_or_default()
+                .bright_cyan()
+                .bold()
+        ),
+        ("begin", _) => {
+            writeln!(
+                out,
+                "{} {}",
+                "✓".bright_green().bold(),
+                "Transaction started. Writes are staged until 'commit' or 'rollback'."
+                    .bright_green()
+            )?;
+            writeln!(
+                out,
+                "  {}",
+                "Only 'get' sees staged writes; listings, count, search, query and sql show committed data."
+                    .bright_yellow()
+            )
+        }
+        ("commit", _) => writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            "Committed".bright_green(),
+            plural(&ack.target, "write").bright_green()
+        ),
+        ("rollback", _) => writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            "Rolled back".bright_green(),
+            plural(&ack.target, "staged write").bright_green()
+        ),
+        (command, _) => writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            command.bright_green(),
+            ack.target.bright_cyan().bold()
+        ),
+    }
+}
+
+/// `count noun`, with the noun in the plural unless the count is 1
+fn plural(count: &str, noun: &str) -> String {
+    match count {
+        "1" => format!("1 {}", noun),
+        count => format!("{} {}s", count, noun),
+    }
+}
+
+/// The keys copied or moved: the key and its target, or for several keys
+/// their count and a sample
+fn write_copied(copied: &Copied, out: &mut dyn Write) -> fmt::Result {
+    if copied.pairs.is_empty() {
+        return writeln!(
+            out,
+            "{} {}",
+            "!".bright_yellow().bold(),
+            "No keys match.".bright_yellow()
+        );
+    }
+    let done = match (copied.command, copied.staged) {
+        ("cp", false) => "Copied",
+        ("mv", false) => "Moved",
+        (_, false) => "Renamed",
+        ("cp", true) => "Staged copy of",
+        ("mv", true) => "Staged move of",
+        (_, true) => "Staged rename of",
+    };
+    let target = |key: &[u8]| {
+        let key = copied.target_codecs.key.display(key);
+        match &copied.tree {
+            Some(tree) => format!("{}:{}", bytes::escape(tree), key),
+            None => key,
+        }
+    };
+
+    if let [(from, to)] = &copied.pairs[..] {
+        return writeln!(
+            out,
+            "{} {} {} {} {}",
+            "✓".bright_green().bold(),
+            done.bright_green(),
+            copied.codecs.key.display(from).bright_cyan().bold(),
+            "to".bright_green(),
+            target(to).bright_cyan().bold()
+        );
+    }
+    writeln!(
+        out,
+        "{} {} {}",
+        "✓".bright_green().bold(),
+        done.bright_green(),
+        plural(&copied.pairs.len().to_string(), "key")
+            .bright_yellow()
+            .bold()
+    )?;
+    for (i, (from, to)) in copied.pairs.iter().take(SAMPLE_SIZE).enumerate() {
+        writeln!(
+            out,
+            "  {} {} {} {}",
+            format!("{}:", i + 1).bright_black(),
+            copied.codecs.key.display(from).bright_cyan(),
+            "=>".bright_black(),
+            target(to).bright_cyan()
+        )?;
+    }
+    if copied.pairs.len() > SAMPLE_SIZE {
+        writeln!(
+            out,
+            "  {}",
+            format!("... and {} more", copied.pairs.len() - SAMPLE_SIZE).bright_black()
+        )?;
+    }
+    Ok(())
+}
+
+/// The outcome of a bulk delete, or a sample of the keys it would delete
+fn write_deletion(deletion: &Deletion, out: &mut dyn Write) -> fmt::Result {
+    let count = plural(&deletion.keys.len().to_string(), "key");
+    if deletion.keys.is_empty() {
+        return writeln!(
+            out,
+            "{} {}",
+            "!".bright_yellow().bold(),
+            "No keys match.".bright_yellow()
+        );
+    }
+    if deletion.deleted {
+        let done = if deletion.staged {
+            "Staged delete of"
+        } else {
+            "Deleted"
+        };
+        return writeln!(
+            out,
+            "{} {} {}",
+            "✓".bright_green().bold(),
+            done.bright_green(),
+            count.bright_yellow().bold()
+        );
+    }
+
+    writeln!(
+        out,
+        "{} {}:",
+        "Would delete".bright_blue(),
+        count.bright_yellow().bold()
+    )?;
+    for (i, key) in deletion.keys.iter().take(SAMPLE_SIZE).enumerate() {
+        writeln!(
+            out,
+            "  {} {}",
+            format!("{}:", i + 1).bright_black(),
+            deletion.codecs.key.display(key).bright_cyan()
+        )?;
+    }
+    if deletion.keys.len() > SAMPLE_SIZE {
+        writeln!(
+            out,
+            "  {}",
+            format!("... and {} more", deletion.keys.len() - SAMPLE_SIZE).bright_black()
+        )?;
+    }
+    Ok(())
+}
+
+/// The writes staged in the transaction in progress, in the order staged
+fn write_status(staged: Option<&[StagedWrite]>, out: &mut dyn Write) -> fmt::Result {
+    let Some(staged) = staged else {
+        return writeln!(out, "{}", "No transaction in progress.".yellow());
+    };
+    writeln!(
+        out,
+        "{} {}",
+        "Transaction in progress:".bright_blue(),
+        plural(&staged.len().to_string(), "staged write")
+            .bright_yellow()
+            .bold()
+    )?;
+    for (i, write) in staged.iter().enumerate() {
+        let key = write.codecs.key.display(&write.key).bright_cyan().bold();
+        let tree = format!("[{}]", bytes::escape(&write.tree)).bright_black();
+        match &write.value {
+            Some(value) => writeln!(
+                out,
+                "  {} {} {} {} {} {}",
+                format!("{}:", i + 1).bright_black(),
+                tree,
+                "set".bright_green(),
+                key,
+                "=>".bright_black(),
+                truncate_value(&bytes::escape(value), 50).bright_white()
+            )?,
+            None => writeln!(
+                out,
+                "  {} {} {} {}",
+                format!("{}:", i + 1).bright_black(),
+                tree,
+                "delete".bright_red(),
+                key
+            )?,
+        }
+    }
+    Ok(())
+}
+
+fn format_value_preview(info: &KeyInfo) -> String {
+    let profiled = profile_value(info).is_some();
+    if info.content_type == ContentType::Binary && !profiled {
+        return "(binary data)".red().to_string();
+    }
+
+    if info.value.is_empty() {
+        return "(empty)".bright_black().to_string();
+    }
+
+    // Detected formats are tagged; a profile's decoder is the same for every key
+    let tag = match info.content_type {
+        ContentType::MessagePack | ContentType::Cbor if !profiled => {
+            format!("{} ", format!("[{}]", info.content_type).bright_magenta())
+        }
+        _ => String::new(),
+    };
+
+    // For short values, show them fully
+    let value = display_value(info);
+    if value.chars().count() <= 50 {
+        return format!("{}{}", tag, value.bright_green());
+    }
+
+    // For longer values, show a preview with truncation
+    let preview = value.chars().take(47).collect::<String>();
+    format!("{}{}", tag, format!("{}...", preview).bright_green())
+}
+
+fn write_key_info(info: &KeyInfo, out: &mut dyn Write) -> fmt::Result {
+    write_key_header(info, out)?;
+
+    if let (Some(decoder), Some(value)) = (info.codecs.value, profile_value(info)) {
+        writeln!(
+            out,
+            "{}",
+            format!("(decoded as {} by profile)", decoder).bright_black()
+        )?;
+        write_text(&decoded_text(&value, decoder, true), out)?;
+        return write_key_footer(out);
+    }
+
+    match info.content_type {
+        ContentType::Binary => write_binary_value(info, out)?,
+        ContentType::Text => write_text(&display_value(info), out)?,
+        // Structured values are pretty-printed
+        ContentType::Json | ContentType::MessagePack | ContentType::Cbor => {
+            match decode::decode_detected(&info.value, info.content_type) {
+                Some(value) => write_text(&pretty_json(&value), out)?,
+                None => write_text(&display_value(info), out)?,
+            }
+        }
+    }
+
+    write_key_footer(out)
+}
+
+/// Binary values are dumped; `get <key> --bytes` shows the rest
+fn write_binary_value(info: &KeyInfo, out: &mut dyn Write) -> fmt::Result {
+    let view = ByteView {
+        info: info.clone(),
+        range: hexdump::ByteRange {
+            start: 0,
+            end: Some(hexdump::DEFAULT_LIMIT),
+        },
+        width: hexdump::DEFAULT_WIDTH,
+    };
+    write_hexdump(&view, out)?;
+    if info.size > hexdump::DEFAULT_LIMIT {
+        writeln!(
+            out,
+            "{}",
+            format!(
+                "(showing first {} of {} bytes, use --bytes to see more)",
+                hexdump::DEFAULT_LIMIT,
+                info.size
+            )
+            .bright_black()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_text(value: &str, out: &mut dyn Write) -> fmt::Result {
+    let length = value.chars().count();
+    if length > 1000 {
+        writeln!(out, "{}", truncate_value(value, 1000).bright_white())?;
+        writeln!(
+            out,
+            "{}",
+            format!("(truncated, showing first 1000 characters of {})", length).bright_black()
+        )
+    } else {
+        writeln!(out, "{}", value.bright_white())
+    }
+}
+
+fn write_decoded(decoded: &DecodedValue, out: &mut dyn Write) -> fmt::Result {
+    write_key_header(&decoded.info, out)?;
+    writeln!(
+        out,
+        "{}",
+        format!("(decoded as {})", decoded.decoder).bright_black()
+    )?;
+    write_text(&decoded_text(&decoded.value, decoded.decoder, true), out)?;
+    write_key_footer(out)
+}
+
+/// The value decoded with the tree profile's decoder, when it has one that
+/// fits the value
+fn profile_value(info: &KeyInfo) -> Option<serde_json::Value> {
+    decode::decode(&info.value, info.codecs.value?).ok()
+}
+
+fn decoded_text(value: &serde_json::Value, decoder: Decoder, pretty: bool) -> String {
+    match value {
+        // Decoded text is shown as is, not as a quoted JSON string
+        serde_json::Value::String(text) if decoder == Decoder::Text => text.clone(),
+        value if pretty => pretty_json(value),
+        value => value.to_string(),
+    }
+}
+
+fn pretty_json(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).expect("JSON values serialize")
+}
+
+fn write_byte_view(view: &ByteView, out: &mut dyn Write) -> fmt::Result {
+    write_key_header(&view.info, out)?;
+    let range = view.range.clamp(view.info.size);
+    writeln!(
+        out,
+        "{}",
+        format!(
+            "(bytes {}..{} of {})",
+            range.start, range.end, view.info.size
+        )
+        .bright_black()
+    )?;
+    write_hexdump(view, out)?;
+    write_key_footer(out)
+}
+
+fn write_hexdump(view: &ByteView, out: &mut dyn Write) -> fmt::Result {
+    let start = view.range.clamp(view.info.size).start;
+    let selected = view.selected();
+    if selected.is_empty() {
+        return writeln!(out, "{}", "(no bytes)".bright_black());
+    }
+    for line in hexdump::format_lines(selected, start, view.width) {
+        writeln!(out, "{}", line.bright_white())?;
+    }
+    Ok(())
+}
+
+fn write_key_header(info: &KeyInfo, out: &mut dyn Write) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(out, "{}", "═".repeat(50).bright_cyan())?;
+    writeln!(
+        out,
+        "{} {}",
+        "Key:".bright_blue().bold(),
+        info.display_key().bright_cyan().bold()
+    )?;
+    writeln!(
+        out,
+        "{} {} bytes",
+        "Size:".bright_blue().bold(),
+        info.size.to_string().bright_yellow()
+    )?;
+    writeln!(
+        out,
+        "{} {}",
+        "Content:".bright_blue().bold(),
+        if info.content_type == ContentType::Binary {
+            info.content_type.name().bright_red()
+        } else {
+            info.content_type.name().bright_green()
+        }
+    )?;
+    writeln!(out, "{}", "Value:".bright_blue().bold())?;
+    writeln!(out, "{}", "─".repeat(50).bright_black())
+}
+
+fn write_key_footer(out: &mut dyn Write) -> fmt::Result {
+    writeln!(out, "{}", "═".repeat(50).bright_cyan())?;
+    writeln!(out)
+}
+
+/// Values decoded by a profile and detected MessagePack and CBOR are shown as
+/// compact JSON, text as stored, anything else escaped
+fn display_value(info: &KeyInfo) -> String {
+    if let (Some(decoder), Some(value)) = (info.codecs.value, profile_value(info)) {
+        return decoded_text(&value, decoder, false);
+    }
+
+    match info.content_type {
+        ContentType::Text | ContentType::Json => info.value_lossy().into_owned(),
+        ContentType::MessagePack | ContentType::Cbor => {
+            match decode::decode_detected(&info.value, info.content_type) {
+                Some(value) => value.to_string(),
+                None => bytes::escape(&info.value),
+            }
+        }
+        ContentType::Binary => bytes::escape(&info.value),
+    }
+}
+
+fn truncate_value(value: &str, max_len: usize) -> String {
+    match value.char_indices().nth(max_len) {
+        None => value.to_string(),
+        Some((end, _)) => format!("{}...", &value[..end]),
+    }
+}
+
+fn write_help(out: &mut dyn Write) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(out, "{}", "Available Commands:".bright_cyan().bold())?;
+    writeln!(out, "{}", "═".repeat(50).bright_cyan())?;
+
+    writeln!(
+        out,
+        "{:<25} Show total number of records",
+        "count".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List keys matching pattern (default: *)",
+        "list [pattern]".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List keys matching regex pattern",
+        "list regex <regex>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Show N results of list, search, trees, range or prefix",
+        "--limit N / --offset N".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Order by bytes, key, size or value",
+        "--sort S / --reverse".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Continue the last list, search or trees",
+        "more [n] / next [n]".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List the first or last N keys (default: 10)",
+        "head [n] / tail [n]".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List keys from start up to (not including) end",
+        "range <start> <end>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List keys starting with prefix",
+        "prefix <prefix>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Get value and info for a specific key",
+        "get <key>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Hexdump a byte range of a value",
+        "get <key> --bytes 0..256".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Hexdump with N bytes per line (1-64)",
+        "get <key> --width N".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Decode as text, json, msgpack, cbor or bincode:<type>",
+        "get <key> --as <decoder>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Set/update a key-value pair",
+        "set <key> <value>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Only set a key that does not exist",
+        "set ... --if-absent".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Only set a key whose value is <v>",
+        "set ... --if-equals <v>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Replace a value only if it is still <old>",
+        "cas <key> <old> <new>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Delete a key",
+        "delete <key>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Copy a key, to another tree with <tree>:<key>",
+        "cp <key> <target>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Move a key, to another tree with <tree>:<key>",
+        "mv <key> <target>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Rename a key within the current tree",
+        "rename <key> <new>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Copy/move matching keys; $1, ${{name}} in target",
+        "cp/mv --pattern/--regex".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Overwrite target keys that exist",
+        "cp/mv ... --force".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Delete the keys matching a glob, after confirming",
+        "delete --pattern <glob>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Delete the keys matching a regex, after confirming",
+        "delete --regex <re>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Delete the keys from <s> up to <e>, after confirming",
+        "delete --range <s> <e>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Delete without asking for confirmation",
+        "delete ... --yes".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Only list the keys that would be deleted",
+        "delete ... --dry-run".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Search values matching pattern",
+        "search <pattern>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Search values matching regex pattern",
+        "search regex <regex>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List values whose JSON matches a query",
+        "query <query>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Show these fields of each as a table",
+        "query ... --select $.a,$.b".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Run a SELECT over trees as tables",
+        "sql <statement>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Also match keys (glob G) and values (regex RE)",
+        "--key G / --value RE".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Ignore case in all patterns",
+        "-i".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Only values of a size (e.g. 512, 4k, 10m)",
+        "--min-size / --max-size".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Only binary, text or empty values",
+        "--binary/--text/--empty".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List or search every tree, grouped by tree",
+        "--all-trees".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List or search the trees matching a glob",
+        "--trees <glob>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Show this help message",
+        "help".bright_green().bold()
+    )?;
+    writeln!(out)?;
+    writeln!(out, "{}", "Tree Management:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "{:<25} List all trees in the database",
+        "trees".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List trees matching glob pattern",
+        "trees <pattern>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List trees matching regex pattern",
+        "trees regex <pattern>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Select a tree to work with",
+        "select <tree>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Create the tree if it does not exist, and select it",
+        "select <tree> --create".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Create an empty tree",
+        "tree create <tree>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Remove a tree and its keys (--yes: don't ask)",
+        "tree drop <tree>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Remove all keys of a tree (--yes: don't ask)",
+        "tree clear <tree>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Copy a tree's keys to a new tree, then drop it; not atomic (--yes: don't ask)",
+        "tree rename <tree> <new>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Copy a tree's keys to a new tree (--yes: don't ask)",
+        "tree copy <tree> <new>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Unselect current tree (return to default)",
+        "unselect".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} List the trees that contain a key",
+        "where <key>".bright_green().bold()
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Tree Usage:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "  {} Trees provide data isolation - keys in different trees are separate",
+        "•".bright_blue()
+    )?;
+    writeln!(
+        out,
+        "  {} When a tree is selected, the prompt shows: {}",
+        "•".bright_blue(),
+        "[tree_name]>".bright_cyan()
+    )?;
+    writeln!(
+        out,
+        "  {} All CRUD operations work on the selected tree",
+        "•".bright_blue()
+    )?;
+    writeln!(
+        out,
+        "  {} Use 'unselect' to return to the default tree",
+        "•".bright_blue()
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Transactions:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "{:<25} Stage set and delete, in any tree, until commit",
+        "begin".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Show the staged writes",
+        "status".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Apply the staged writes atomically",
+        "commit".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Discard the staged writes",
+        "rollback".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "  {} Only 'get' sees staged writes; list, range, prefix, head, tail, count,",
+        "•".bright_blue()
+    )?;
+    writeln!(
+        out,
+        "    search, query, sql, trees and where show the committed data until 'commit'"
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Output Formats:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "{:<25} Show or set the output format (text, json, ndjson, csv, tsv)",
+        "format [name]".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Use a format for a single command",
+        "<command> --format <name>".bright_green().bold()
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Scripts:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "{:<25} Run the commands in a file (stops at first error)",
+        "source <file>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Run a file, continuing past failed commands",
+        "source <file> --continue".bright_green().bold()
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Advanced Usage:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "{:<25} Show key completions for a command",
+        "complete <cmd>".bright_green().bold()
+    )?;
+    writeln!(
+        out,
+        "{:<25} Exit the application",
+        "exit".bright_green().bold()
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Examples:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "list user_*".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "list regex user_[0-9]+".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "range user_100 user_200".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "get user_123".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "set user_123 \"John Doe\"".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "set \"user name\" value".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "delete user_123".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "search *@example.com".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "search regex \\d{4}-\\d{2}-\\d{2}".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "query $.status == 'failed' && $.retries > 3 --select $.status".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "sql SELECT prefix(key, '_'), count(*) FROM sessions GROUP BY 1".bright_white()
+    )?;
+    writeln!(out, "  {} {}", ">".bright_black(), "trees".bright_white())?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "trees *_data".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        ">".bright_black(),
+        "select settings".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        "[settings]>".bright_white(),
+        "list".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} {}",
+        "[settings]>".bright_white(),
+        "unselect".bright_white()
+    )?;
+
+    writeln!(out)?;
+    writeln!(out, "{}", "Note:".bright_blue().bold())?;
+    writeln!(
+        out,
+        "  {} Use quotes for keys/values with spaces: {} or {}",
+        "•".bright_blue(),
+        "\"key name\"".bright_yellow(),
+        "\"value with spaces\"".bright_yellow()
+    )?;
+    writeln!(
+        out,
+        "  {} Escape quotes in values: {} → {}",
+        "•".bright_blue(),
+        "\"He said \\\"hello\\\"\"".bright_yellow(),
+        "He said \"hello\"".bright_white()
+    )?;
+    writeln!(
+        out,
+        "  {} Binary keys and values: {}, {} or {}",
+        "•".bright_blue(),
+        "0x0001ff".bright_yellow(),
+        "b64:AAE=".bright_yellow(),
+        "\"\\x00\\xff\"".bright_yellow()
+    )?;
+    writeln!(
+        out,
+        "  {} Typed keys: {}, {} or {}",
+        "•".bright_blue(),
+        "u64:42".bright_yellow(),
+        "i64le:-1".bright_yellow(),
+        "uuid:<uuid>".bright_yellow()
+    )?;
+    writeln!(
+        out,
+        "  {} Keys are auto-completed with TAB",
+        "•".bright_blue()
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_info(key: &str, value: &[u8]) -> KeyInfo {
+        KeyInfo::new(key.as_bytes(), value)
+    }
+
+    #[test]
+    fn test_text_renderer() {
+        let rendered = render(&CommandOutput::Count(42), OutputFormat::Text);
+        assert!(rendered.contains("Total records:"));
+        assert!(rendered.contains("42"));
+
+        let keys: Vec<KeyInfo> = (0..60)
+            .map(|i| key_info(&format!("key_{:02}", i), b"value"))
+            .collect();
+        let rendered = render(
+            &CommandOutput::Keys(keys.clone().into()),
+            OutputFormat::Text,
+        );
+        assert!(rendered.contains("Found 60 keys"));
+        assert!(rendered.contains("key_59"));
+        assert!(!rendered.contains("more keys"));
+
+        // A page is numbered from its place in the whole listing
+        let page = Listing {
+            items: keys[50..].to_vec(),
+            start: 50,
+            more: true,
+        };
+        let rendered = render(&CommandOutput::Keys(page), OutputFormat::Text);
+        assert!(rendered.contains("Showing keys 51-60"));
+        assert!(rendered.contains("51: key_50"));
+        assert!(rendered.contains("type 'more' to see the next page"));
+
+        let rest = Listing {
+            items: Vec::new(),
+            start: 60,
+            more: false,
+        };
+        let rendered = render(&CommandOutput::Trees(rest), OutputFormat::Text);
+        assert!(rendered.contains("No more trees."));
+
+        let rendered = render(
+            &CommandOutput::KeyInfo(key_info("bin", &[0, 255])),
+            OutputFormat::Text,
+        );
+        assert!(rendered.contains("bin"));
+        assert!(rendered.contains("Content: binary"));
+
+        assert_eq!(render(&CommandOutput::Empty, OutputFormat::Text), "");
+    }
+
+    #[test]
+    fn test_text_renderer_hexdump() {
+        let value: Vec<u8> = (0..=255)
+            .chain(0..=255)
+            .chain(0..=255)
+            .chain(0..=255)
+            .chain(0..10)
+            .collect();
+        let rendered = render(
+            &CommandOutput::KeyInfo(key_info("blob", &value)),
+            OutputFormat::Text,
+        );
+        assert!(rendered.contains(
+            "00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|"
+        ));
+        assert!(rendered.contains("000003f0"));
+        assert!(!rendered.contains("00000400"));
+        assert!(rendered.contains("showing first 1024 of 1034 bytes"));
+
+        let view = ByteView {
+            info: key_info("blob", &value),
+            range: "1030..2000".parse().unwrap(),
+            width: 4,
+        };
+        let rendered = render(&CommandOutput::Bytes(view.clone()), OutputFormat::Text);
+        assert!(rendered.contains("(bytes 1030..1034 of 1034)"));
+        assert!(rendered.contains("00000406  06 07 08 09  |....|"));
+
+        let rendered = render(&CommandOutput::Bytes(view), OutputFormat::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed["value"], "\u{6}\u{7}\u{8}\t");
+        assert_eq!(parsed["size"], 1034);
+    }
+
+    #[test]
+    fn test_text_renderer_decoded() {
+        // {"name": "Alice", "age": 30} as MessagePack
+        let mut value = vec![0x82, 0xa4];
+        value.extend_from_slice(b"name");
+        value.push(0xa5);
+        value.extend_from_slice(b"Alice");
+        value.push(0xa3);
+        value.extend_from_slice(b"age");
+        value.push(30);
+        let info = key_info("user", &value);
+
+        let rendered = render(&CommandOutput::KeyInfo(info.clone()), OutputFormat::Text);
+        assert!(rendered.contains("Content: msgpack"));
+        assert!(rendered.contains("\"name\": \"Alice\""));
+
+        let rendered = render(
+            &CommandOutput::Keys(vec![info.clone()].into()),
+            OutputFormat::Text,
+        );
+        assert!(rendered.contains("[msgpack] {\"name\":\"Alice\",\"age\":30}"));
+
+        let decoded = CommandOutput::Decoded(DecodedValue {
+            info,
+            decoder: Decoder::MessagePack,
+            value: decode::decode(&value, Decoder::MessagePack).unwrap(),
+        });
+        assert!(render(&decoded, OutputFormat::Text).contains("(decoded as msgpack)"));
+        let parsed: serde_json::Value =
+            serde_json::from_str(&render(&decoded, OutputFormat::Json)).unwrap();
+        assert_eq!(parsed["decoder"], "msgpack");
+        assert_eq!(parsed["decoded"]["age"], 30);
+    }
+
+    #[test]
+    fn test_text_renderer_acks() {
+        let ack = |command, changed| {
+            CommandOutput::Ack(Ack {
+                command,
+                target: "target".to_string(),
+                value: None,
+                changed,
+                staged: false,
+            })
+        };
+
+        assert!(render(&ack("delete", true), OutputFormat::Text).contains("Successfully deleted"));
+        assert!(render(&ack("delete", false), OutputFormat::Text).contains("Key not found:"));
+        assert!(render(&ack("unselect", false), OutputFormat::Text)
+            .contains("No tree was previously selected."));
+
+        let staged = CommandOutput::Ack(Ack {
+            command: "delete",
+            target: "target".to_string(),
+            value: None,
+            changed: true,
+            staged: true,
+        });
+        assert!(render(&staged, OutputFormat::Text).contains("Staged delete of key"));
+        assert!(render(&ack("cas", true), OutputFormat::Text)
+            .contains("Successfully swapped value of key"));
+        let commit = CommandOutput::Ack(Ack {
+            command: "commit",
+            target: "1".to_string(),
+            value: None,
+            changed: true,
+            staged: false,
+        });
+        assert!(render(&commit, OutputFormat::Text).contains("Committed 1 write"));
+    }
+
+    #[test]
+    fn test_render_deletion() {
+        let deletion = |count: usize, deleted: bool| {
+            CommandOutput::Deletion(Deletion {
+                keys: (0..count)
+                    .map(|i| format!("session_{:02}", i).into_bytes())
+                    .collect(),
+                codecs: Default::default(),
+                deleted,
+                staged: false,
+            })
+        };
+
+        let rendered = render(&deletion(12, false), OutputFormat::Text);
+        assert!(rendered.contains("Would delete 12 keys:"));
+        assert!(rendered.contains("10: session_09"));
+        assert!(!rendered.contains("session_10"));
+        assert!(rendered.contains("... and 2 more"));
+        assert!(render(&deletion(1, true), OutputFormat::Text).contains("Deleted 1 key"));
+        assert!(render(&deletion(0, false), OutputFormat::Text).contains("No keys match."));
+
+        assert_eq!(
+            render(&deletion(2, false), OutputFormat::Csv),
+            "key,key_encoding,deleted\nsession_00,utf8,false\nsession_01,utf8,false\n"
+        );
+    }
+
+    #[test]
+    fn test_render_status() {
+        let staged = vec![
+            StagedWrite {
+                tree: b"users".to_vec(),
+                key: b"user_1".to_vec(),
+                value: Some(b"Ann".to_vec()),
+                expected: None,
+                codecs: Default::default(),
+            },
+            StagedWrite {
+                tree: b"__sled__default".to_vec(),
+                key: b"old".to_vec(),
+                value: None,
+                expected: None,
+                codecs: Default::default(),
+            },
+        ];
+        let output = CommandOutput::Status(Some(staged));
+
+        let rendered = render(&output, OutputFormat::Text);
+        assert!(rendered.contains("Transaction in progress: 2 staged writes"));
+        assert!(rendered.contains("1: [users] set user_1 => Ann"));
+        assert!(rendered.contains("2: [__sled__default] delete old"));
+
+        assert_eq!(
+            render(&output, OutputFormat::Csv),
+            "tree,tree_encoding,operation,key,key_encoding,value,value_encoding\n\
+             users,utf8,set,user_1,utf8,Ann,utf8\n\
+             __sled__default,utf8,delete,old,utf8,,utf8\n"
+        );
+        assert!(render(&CommandOutput::Status(None), OutputFormat::Text)
+            .contains("No transaction in progress."));
+    }
+
+    #[test]
+    fn test_render_across_trees() {
+        let entries = vec![
+            key_info("a", b"1").in_tree(b"t1".to_vec()),
+            key_info("b", b"2").in_tree(b"t1".to_vec()),
+            key_info("a", b"3").in_tree(b"t2".to_vec()),
+        ];
+        let rendered = render(
+            &CommandOutput::Keys(entries.clone().into()),
+            OutputFormat::Text,
+        );
+        assert_eq!(rendered.matches("[t1]").count(), 1);
+        assert!(rendered.find("[t2]") > rendered.find("2: b"));
+        assert!(rendered.contains("3: a"));
+
+        let rendered = render(
+            &CommandOutput::Keys(entries.clone().into()),
+            OutputFormat::Csv,
+        );
+        assert!(rendered.starts_with("tree,tree_encoding,key,key_encoding,"));
+        assert!(rendered.contains("\nt2,utf8,a,utf8,"));
+
+        let located = |entries| {
+            CommandOutput::Located(Located {
+                key: "a".to_string(),
+                entries,
+            })
+        };
+        let rendered = render(&located(entries[..1].to_vec()), OutputFormat::Ndjson);
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed["tree"], "t1");
+        assert_eq!(parsed["value"], "1");
+        assert!(render(&located(Vec::new()), OutputFormat::Text)
+            .contains("Key 'a' not found in any tree."));
+    }
+
+    #[test]
+    fn test_render_projection() {
+        let projection = Projection {
+            columns: vec![
+                crate::query::Path::parse("$.status").unwrap(),
+                crate::query::Path::parse("$.retries").unwrap(),
+            ],
+            entries: vec![
+                key_info("job_1", br#"{"status":"failed","retries":4}"#),
+                key_info("job_22", br#"{"status":"done, finally"}"#),
+            ]
+            .into(),
+        };
+        let output = CommandOutput::Projection(projection);
+
+        let rendered = render(&output, OutputFormat::Text);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[0].contains("key     status         retries"));
+        assert!(lines[1].ends_with("1: job_1   failed         4"));
+        assert!(lines[2].ends_with("2: job_22  done, finally  null"));
+        assert!(rendered.contains("Found 2 matches"));
+
+        assert_eq!(
+            render(&output, OutputFormat::Csv),
+            "key,key_encoding,status,retries\n\
+             job_1,utf8,failed,4\n\
+             job_22,utf8,\"done, finally\",\n"
+        );
+        let rendered = render(&output, OutputFormat::Ndjson);
+        let first: serde_json::Value =
+            serde_json::from_str(rendered.lines().next().unwrap()).unwrap();
+        assert_eq!(
+            first,
+            serde_json::json!({"key": "job_1", "key_encoding": "utf8", "status": "failed", "retries": 4})
+        );
+    }
+
+    #[test]
+    fn test_record_renderer() {
+        let output =
+            CommandOutput::SearchResults(vec![key_info("a", b"1"), key_info("b", &[255])].into());
+        let rendered = render(&output, OutputFormat::Ndjson);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["value"], "/w==");
+        assert_eq!(second["value_encoding"], "base64");
+
+        let output = CommandOutput::Ack(Ack {
+            command: "set",
+            target: "key".to_string(),
+            value: Some("value".to_string()),
+            changed: true,
+            staged: false,
+        });
+        assert_eq!(
+            render(&output, OutputFormat::Csv),
+            "command,target,changed\nset,key,true\n"
+        );
+
+        assert_eq!(
+            render(
+                &CommandOutput::FormatChanged(OutputFormat::Json),
+                OutputFormat::Json
+            ),
+            ""
+        );
+        assert!(render(&CommandOutput::Help, OutputFormat::Json).contains("Available Commands:"));
+    }
+}
@@ -1,5 +1,8 @@
-use crate::commands::Command;
+use crate::bytes;
+use crate::commands::{self, Command};
 use crate::db::SledViewer;
+use crate::interrupt;
+use crate::output::OutputFormat;
 use anyhow::Result;
 use colored::*;
 use rustyline::error::ReadlineError;
@@ -7,6 +10,10 @@ use rustyline::history::FileHistory;
 use rustyline::{Context, Editor};
 use rustyline_derive::{Helper, Highlighter, Hinter, Validator};
 
+/// At most this many keys of the current tree are offered for completion, so
+/// that loading them does not stall the prompt on a large tree
+const COMPLETION_KEY_LIMIT: usize = 10_000;
+
 #[derive(Helper, Highlighter, Hinter, Validator)]
 struct SledCompleter {
     keys: Vec<String>,
@@ -47,9 +54,14 @@ impl rustyline::completion::Completer for SledCompleter {
         if parts.len() >= 2 {
             let command = parts[0].to_lowercase();
             if command == "get"
+                || command == "range"
+                || command == "prefix"
+                || command == "where"
                 || command == "delete"
                 || command == "del"
                 || (command == "set" && parts.len() == 2)
+                || (command == "cas" && parts.len() == 2)
+                || (matches!(command.as_str(), "cp" | "mv" | "rename") && parts.len() == 2)
                 || (command == "list" && parts.len() >= 2 && parts[1] != "regex")
                 || (command == "search" && parts.len() >= 2 && parts[1] != "regex")
             {
@@ -79,6 +91,7 @@ impl rustyline::completion::Completer for SledCompleter {
 
                 return Ok((start, candidates));
             } else if (command == "select"
+                || (command == "tree" && parts.len() == 3 && parts[1] != "create")
                 || (command == "trees" && parts.len() >= 2 && parts[1] != "regex"))
                 && parts.len() >= 2
             {
@@ -112,8 +125,10 @@ impl rustyline::completion::Completer for SledCompleter {
 
         // Fallback to command completion
         let commands = vec![
-            "count", "list", "get", "set", "delete", "del", "search", "trees", "select",
-            "unselect", "help", "exit", "quit",
+            "count", "list", "range", "prefix", "head", "tail", "more", "next", "get", "set",
+            "cas", "cp", "mv", "rename", "delete", "del", "search", "query", "sql", "trees",
+            "select", "unselect", "tree", "where", "source", "format", "begin", "commit",
+            "rollback", "status", "help", "exit", "quit",
         ];
         let mut candidates = Vec::new();
 
@@ -147,6 +162,7 @@ pub struct Repl {
     viewer: SledViewer,
     keys: Vec<String>,
     trees: Vec<String>,
+    format: OutputFormat,
 }
 
 impl Repl {
@@ -161,29 +177,85 @@ impl Repl {
             viewer,
             keys: Vec::new(),
             trees: Vec::new(),
+            format: OutputFormat::default(),
         }
     }
 
-    fn load_keys(&mut self) -> Result<()> {
-        match self.viewer.list_keys("*", false) {
-            Ok(keys) => {
-                self.keys = keys.clone();
-                // Update the completer with new keys
-                if let Some(helper) = self.editor.helper_mut() {
-                    helper.update_keys(keys);
+    /// Set the output format the session starts with
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Execute one command line, returning `true` when the session should end
+    fn execute_line(&mut self, line: &str) -> Result<bool> {
+        match Command::parse_with_format(line) {
+            Some((Command::Exit, _)) => {
+                println!("{}", "Goodbye!".bright_green());
+                return Ok(true);
+            }
+            Some((command, format_override)) => {
+                if let Err(e) = commands::execute_in_session(
+                    &command,
+                    format_override,
+                    &mut self.viewer,
+                    &mut self.format,
+                ) {
+                    println!("{} {}", "Error:".bright_red().bold(), e.to_string().red());
+                }
+                // Reload keys and trees when the command may have changed them
+                if command.is_write()
+                    || matches!(
+                        command,
+                        Command::Select { .. } | Command::Unselect | Command::Source { .. }
+                    )
+                {
+                    self.load_keys()?;
+                    self.load_trees()?;
                 }
-                Ok(())
             }
-            Err(e) => {
-                eprintln!("Warning: Failed to load keys for completion: {}", e);
-                Ok(())
+            None => {
+                println!(
+                    "{} Unknown command: '{}'. Type 'help' for available commands.",
+                    "Error:".bright_red().bold(),
+                    line.bright_yellow()
+                );
             }
         }
+        Ok(false)
+    }
+
+    /// Load the first `COMPLETION_KEY_LIMIT` keys of the current tree for
+    /// completion. Ctrl-C stops the load, keeping the keys read so far.
+    fn load_keys(&mut self) -> Result<()> {
+        interrupt::clear();
+        // Completion works on the form that is displayed
+        let codec = &self.viewer.codecs().key;
+        let mut keys = Vec::new();
+        let loaded = self.viewer.iter_keys().and_then(|iter| {
+            for key in iter.take(COMPLETION_KEY_LIMIT) {
+                if interrupt::check().is_err() {
+                    break;
+                }
+                keys.push(codec.display(&key?));
+            }
+            Ok(())
+        });
+        if let Err(e) = loaded {
+            eprintln!("Warning: Failed to load keys for completion: {}", e);
+        }
+        self.keys = keys.clone();
+        // Update the completer with new keys
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.update_keys(keys);
+        }
+        Ok(())
     }
 
     fn load_trees(&mut self) -> Result<()> {
         match self.viewer.list_trees("*", false) {
             Ok(trees) => {
+                let trees: Vec<String> = trees.iter().map(|tree| bytes::escape(tree)).collect();
                 self.trees = trees.clone();
                 // Update the completer with new trees
                 if let Some(helper) = self.editor.helper_mut() {
@@ -204,9 +276,14 @@ impl Repl {
         if parts.len() >= 2 {
             let command = parts[0].to_lowercase();
             if command == "get"
+                || command == "range"
+                || command == "prefix"
+                || command == "where"
                 || command == "delete"
                 || command == "del"
                 || (command == "set" && parts.len() == 2)
+                || (command == "cas" && parts.len() == 2)
+                || (matches!(command.as_str(), "cp" | "mv" | "rename") && parts.len() == 2)
                 || (command == "list" && parts.len() >= 2 && parts[1] != "regex")
                 || (command == "search" && parts.len() >= 2 && parts[1] != "regex")
             {
@@ -222,6 +299,7 @@ impl Repl {
 
                 return candidates;
             } else if (command == "select"
+                || (command == "tree" && parts.len() == 3 && parts[1] != "create")
                 || (command == "trees" && parts.len() >= 2 && parts[1] != "regex"))
                 && parts.len() >= 2
             {
@@ -267,11 +345,16 @@ impl Repl {
         if parts.len() >= 2 {
             let command = parts[0].to_lowercase();
             if command == "get"
+                || command == "range"
+                || command == "prefix"
+                || command == "where"
                 || command == "delete"
                 || command == "del"
                 || command == "list"
                 || command == "search"
                 || (command == "set" && parts.len() == 2)
+                || (command == "cas" && parts.len() == 2)
+                || (matches!(command.as_str(), "cp" | "mv" | "rename") && parts.len() == 2)
             {
                 let prefix = parts.last().copied().unwrap_or("");
                 // Show hint if we have a partial key that could be completed
@@ -302,15 +385,24 @@ impl Repl {
         );
         println!();
 
+        // Ctrl-C while a command runs cancels it instead of ending the session,
+        // and stops loading completions
+        interrupt::install();
+
         // Load keys and trees for completion
         self.load_keys()?;
         self.load_trees()?;
 
         loop {
-            // Create prompt that shows selected tree
+            // Create prompt that shows selected tree, and a `*` in a transaction
+            let marker = if self.viewer.staged().is_some() {
+                "*"
+            } else {
+                ""
+            };
             let prompt = match self.viewer.get_selected_tree() {
-                Some(tree) => format!("[{}]> ", tree),
-                None => "> ".to_string(),
+                Some(tree) => format!("[{}]{}> ", bytes::escape(tree), marker),
+                None => format!("{}> ", marker),
             };
 
             let readline = self.editor.readline(&prompt);
@@ -334,8 +426,7 @@ impl Repl {
                     }
 
                     // Check for completion command (keep this for manual completion)
-                    if line.starts_with("complete ") {
-                        let completion_line = &line[9..]; // Remove "complete "
+                    if let Some(completion_line) = line.strip_prefix("complete ") {
                         self.show_completions(completion_line);
                         continue;
                     }
@@ -349,30 +440,8 @@ impl Repl {
                                 completed.bright_white()
                             );
                             // Automatically execute the completed command
-                            match Command::parse(&completed) {
-                                Some(Command::Exit) => {
-                                    println!("{}", "Goodbye!".bright_green());
-                                    break;
-                                }
-                                Some(command) => {
-                                    if let Err(e) = command.execute(&mut self.viewer) {
-                                        println!(
-                                            "{} {}",
-                                            "Error:".bright_red().bold(),
-                                            e.to_string().red()
-                                        );
-                                    }
-                                    // Reload keys and trees after any command in case database changed
-                                    self.load_keys()?;
-                                    self.load_trees()?;
-                                }
-                                None => {
-                                    println!(
-                                        "{} Unknown command: '{}'. Type 'help' for available commands.",
-                                        "Error:".bright_red().bold(),
-                                        completed.bright_yellow()
-                                    );
-                                }
+                            if self.execute_line(&completed)? {
+                                break;
                             }
                             continue;
                         } else {
@@ -390,30 +459,8 @@ impl Repl {
                         }
                     }
 
-                    match Command::parse(line) {
-                        Some(Command::Exit) => {
-                            println!("{}", "Goodbye!".bright_green());
-                            break;
-                        }
-                        Some(command) => {
-                            if let Err(e) = command.execute(&mut self.viewer) {
-                                println!(
-                                    "{} {}",
-                                    "Error:".bright_red().bold(),
-                                    e.to_string().red()
-                                );
-                            }
-                            // Reload keys and trees after any command in case database changed
-                            self.load_keys()?;
-                            self.load_trees()?;
-                        }
-                        None => {
-                            println!(
-                                "{} Unknown command: '{}'. Type 'help' for available commands.",
-                                "Error:".bright_red().bold(),
-                                line.bright_yellow()
-                            );
-                        }
+                    if self.execute_line(line)? {
+                        break;
                     }
                 }
                 Err(ReadlineError::Interrupted) => {
@@ -435,6 +482,7 @@ impl Repl {
             }
         }
 
+        commands::warn_uncommitted(&self.viewer);
         Ok(())
     }
 
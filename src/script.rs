@@ -0,0 +1,242 @@
+use crate::commands::{self, Command};
+use crate::db::SledViewer;
+use crate::error::SledoViewError;
+use crate::output::OutputFormat;
+use anyhow::Result;
+use colored::*;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Maximum nesting of `source` commands, guards against scripts sourcing themselves
+const MAX_SOURCE_DEPTH: usize = 16;
+
+thread_local! {
+    static SOURCE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Outcome of running a script
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScriptReport {
+    /// Number of commands that were executed (successfully or not)
+    pub executed: usize,
+    /// Number of commands that failed
+    pub failed: usize,
+}
+
+impl ScriptReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+/// Run every command in a script file against the viewer.
+///
+/// Returns an error if the file cannot be read or if any command failed, so
+/// callers can surface a non-zero exit code.
+pub fn run_file(
+    viewer: &mut SledViewer,
+    path: &Path,
+    continue_on_error: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let file = File::open(path).map_err(|e| SledoViewError::ScriptNotReadable {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    let depth = SOURCE_DEPTH.with(|d| d.get());
+    if depth >= MAX_SOURCE_DEPTH {
+        return Err(SledoViewError::ScriptNotReadable {
+            path: path.display().to_string(),
+            message: format!("scripts nested deeper than {MAX_SOURCE_DEPTH} levels"),
+        }
+        .into());
+    }
+
+    SOURCE_DEPTH.with(|d| d.set(depth + 1));
+    let source = path.display().to_string();
+    let result = run_reader(
+        viewer,
+        BufReader::new(file),
+        &source,
+        continue_on_error,
+        format,
+    );
+    SOURCE_DEPTH.with(|d| d.set(depth));
+
+    result
+}
+
+/// Run every command read from `reader`, failing if any command failed.
+///
+/// `source` names the input in error messages (a file path or `<stdin>`).
+pub fn run_reader<R: BufRead>(
+    viewer: &mut SledViewer,
+    reader: R,
+    source: &str,
+    continue_on_error: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let report = run_lines(viewer, reader, source, continue_on_error, format)?;
+    if report.is_success() {
+        Ok(())
+    } else {
+        Err(SledoViewError::ScriptFailed {
+            path: source.to_string(),
+            failed: report.failed,
+            executed: report.executed,
+        }
+        .into())
+    }
+}
+
+/// Run commands read line by line, one `Command::parse`-able command per line.
+///
+/// Blank lines and lines starting with `#` are skipped. Failures are reported
+/// with their source name and line number; execution stops at the first
+/// failure unless `continue_on_error` is set. An `exit` command ends the script.
+/// Results are printed in `format` until a `format` command changes it.
+pub fn run_lines<R: BufRead>(
+    viewer: &mut SledViewer,
+    reader: R,
+    source: &str,
+    continue_on_error: bool,
+    format: OutputFormat,
+) -> Result<ScriptReport> {
+    let mut report = ScriptReport::default();
+    let mut format = format;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let result = match Command::parse_with_format(line) {
+            Some((Command::Exit, _)) => break,
+            Some((command, format_override)) => {
+                commands::execute_in_session(&command, format_override, viewer, &mut format)
+            }
+            None => Err(anyhow::anyhow!("Unknown command: '{}'", line)),
+        };
+
+        report.executed += 1;
+        if let Err(e) = result {
+            report.failed += 1;
+            eprintln!(
+                "{} {}",
+                "Error:".bright_red().bold(),
+                format!("{}:{}: {}", source, index + 1, e).red()
+            );
+            if !continue_on_error {
+                break;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn create_viewer() -> (tempfile::TempDir, SledViewer) {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp directory");
+        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+        (temp_dir, viewer)
+    }
+
+    #[test]
+    fn test_run_lines_skips_comments_and_blanks() {
+        let (_temp_dir, mut viewer) = create_viewer();
+        let script = "# create some keys\n\nset a 1\n  # indented comment\nset b 2\ncount\n";
+
+        let report = run_lines(
+            &mut viewer,
+            Cursor::new(script),
+            "test",
+            false,
+            OutputFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            ScriptReport {
+                executed: 3,
+                failed: 0
+            }
+        );
+        assert_eq!(viewer.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_run_lines_stops_on_first_error() {
+        let (_temp_dir, mut viewer) = create_viewer();
+        let script = "set a 1\nget missing\nset b 2\n";
+
+        let report = run_lines(
+            &mut viewer,
+            Cursor::new(script),
+            "test",
+            false,
+            OutputFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(report.executed, 2);
+        assert_eq!(report.failed, 1);
+        assert!(viewer.get_key("b").is_err());
+    }
+
+    #[test]
+    fn test_run_lines_continue_on_error() {
+        let (_temp_dir, mut viewer) = create_viewer();
+        let script = "bogus command\nget missing\nset b 2\n";
+
+        let report = run_lines(
+            &mut viewer,
+            Cursor::new(script),
+            "test",
+            true,
+            OutputFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(report.executed, 3);
+        assert_eq!(report.failed, 2);
+        assert!(viewer.get_key("b").is_ok());
+    }
+
+    #[test]
+    fn test_run_lines_exit_ends_script() {
+        let (_temp_dir, mut viewer) = create_viewer();
+        let script = "set a 1\nexit\nset b 2\n";
+
+        let report = run_lines(
+            &mut viewer,
+            Cursor::new(script),
+            "test",
+            false,
+            OutputFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(report.executed, 1);
+        assert!(viewer.get_key("b").is_err());
+    }
+
+    #[test]
+    fn test_run_file_reports_failure() {
+        let (temp_dir, mut viewer) = create_viewer();
+        let script_path = temp_dir.path().join("fix.sled");
+        std::fs::write(&script_path, "set a 1\nget missing\n").unwrap();
+
+        let err = run_file(&mut viewer, &script_path, true, OutputFormat::Text).unwrap_err();
+        assert!(err.to_string().contains("1 of 2 commands failed"));
+
+        let missing = temp_dir.path().join("missing.sled");
+        assert!(run_file(&mut viewer, &missing, false, OutputFormat::Text).is_err());
+    }
+}
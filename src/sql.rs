@@ -0,0 +1,1508 @@
+use crate::bytes;
+use crate::codec::{KeyCodec, TreeCodecs};
+use crate::db::{KeyInfo, ScanObserver, SledViewer};
+use crate::error::SledoViewError;
+use crate::output::Rows;
+use crate::query::{self, Path};
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{Number, Value};
+use sled::Tree;
+use sqlparser::ast;
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// Run a `SELECT` statement over the trees of the database.
+///
+/// Every tree is a table with the columns `key` (as displayed), `value` (as
+/// text, or escaped when binary) and `size`. Any other column is a field of
+/// the value as a JSON document, and `json_extract(value, '$.a.b')` reads
+/// deeper. Unqualified columns belong to the first table of `FROM`.
+///
+/// Trees can be joined, and a join on `a.key = b.key` looks each key up
+/// instead of reading the whole joined tree. `WHERE key LIKE 'prefix%'` on
+/// the first table only reads the keys with that prefix.
+pub fn execute(
+    viewer: &SledViewer,
+    statement: &str,
+    observer: &mut dyn ScanObserver,
+) -> Result<Rows> {
+    let select = parse(statement)?;
+    let plan = Plan::new(viewer, &select)?;
+    plan.run(observer)
+}
+
+fn invalid(message: impl Into<String>) -> SledoViewError {
+    SledoViewError::InvalidSql {
+        message: message.into(),
+    }
+}
+
+fn unsupported(what: impl fmt::Display) -> SledoViewError {
+    invalid(format!("{} is not supported", what))
+}
+
+/// Parse a single `SELECT` statement
+fn parse(statement: &str) -> Result<ast::Query, SledoViewError> {
+    let mut statements =
+        Parser::parse_sql(&GenericDialect {}, statement).map_err(|e| invalid(e.to_string()))?;
+    if statements.len() != 1 {
+        return Err(invalid("expected a single SELECT statement"));
+    }
+    match statements.remove(0) {
+        ast::Statement::Query(query) => Ok(*query),
+        _ => Err(invalid("only SELECT statements are supported")),
+    }
+}
+
+/// A tree a statement reads, under the name the statement refers to it by
+struct Table {
+    name: String,
+    tree: Tree,
+    codecs: TreeCodecs,
+}
+
+/// The columns of a table
+#[derive(Debug, Clone)]
+enum Column {
+    Key,
+    Value,
+    Size,
+    /// A top-level field of the value as a JSON document
+    Field(String),
+    /// A path into the value as a JSON document (`json_extract`)
+    Path(Path),
+}
+
+/// An entry of a table, decoded once
+struct Entry {
+    info: KeyInfo,
+    document: Option<Value>,
+}
+
+impl Entry {
+    fn new(key: &[u8], value: &[u8], codecs: &TreeCodecs) -> Rc<Self> {
+        let info = KeyInfo::new(key, value).with_codecs(codecs.clone());
+        let document = info.document();
+        Rc::new(Self { info, document })
+    }
+
+    fn column(&self, column: &Column) -> Value {
+        match column {
+            Column::Key => Value::String(self.info.display_key()),
+            Column::Value => Value::String(self.value_text()),
+            Column::Size => Value::from(self.info.size),
+            Column::Field(name) => self
+                .document
+                .as_ref()
+                .and_then(|document| document.get(name))
+                .cloned()
+                .unwrap_or_default(),
+            Column::Path(path) => self
+                .document
+                .as_ref()
+                .and_then(|document| path.select(document))
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Values decoded by a profile as their decoded text, text as stored and
+    /// anything else escaped
+    fn value_text(&self) -> String {
+        match (&self.info.codecs.value, &self.document) {
+            (Some(_), Some(Value::String(text))) => text.clone(),
+            (Some(_), Some(document)) => document.to_string(),
+            _ => match std::str::from_utf8(&self.info.value) {
+                Ok(text) => text.to_string(),
+                Err(_) => bytes::escape(&self.info.value),
+            },
+        }
+    }
+}
+
+/// The entries joined into one row, by table; `None` where a left join found
+/// nothing
+type Row = Vec<Option<Rc<Entry>>>;
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    /// A column of the table at this index
+    Column(usize, Column),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    IsNull(Box<Expr>, bool),
+    Like {
+        expr: Box<Expr>,
+        pattern: Regex,
+        /// The pattern up to its first wildcard
+        prefix: String,
+        negated: bool,
+    },
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    Function(Function, Vec<Expr>),
+    /// The result of the aggregate at this index, for the current group
+    Aggregate(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Length,
+    Lower,
+    Upper,
+    Substr,
+    /// The text before a separator, or the first N characters
+    Prefix,
+    SplitPart,
+    Coalesce,
+    Abs,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Self, std::ops::RangeInclusive<usize>)> {
+        let function = match name {
+            "len" | "length" => (Function::Length, 1..=1),
+            "lower" => (Function::Lower, 1..=1),
+            "upper" => (Function::Upper, 1..=1),
+            "substr" | "substring" => (Function::Substr, 2..=3),
+            "prefix" => (Function::Prefix, 2..=2),
+            "split_part" => (Function::SplitPart, 3..=3),
+            "coalesce" => (Function::Coalesce, 1..=usize::MAX),
+            "abs" => (Function::Abs, 1..=1),
+            _ => return None,
+        };
+        Some(function)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Aggregate {
+    function: AggregateFunction,
+    /// `None` for `count(*)`
+    arg: Option<Expr>,
+    distinct: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// Turns SQL expressions into `Expr`s over the tables of a statement
+struct Binder<'a> {
+    tables: &'a [Table],
+    aggregates: Vec<Aggregate>,
+}
+
+impl Binder<'_> {
+    fn bind(&mut self, expr: &ast::Expr, aggregates: bool) -> Result<Expr, SledoViewError> {
+        let mut bind = |expr: &ast::Expr| self.bind(expr, aggregates).map(Box::new);
+        let bound = match expr {
+            ast::Expr::Identifier(ident) => self.column(std::slice::from_ref(ident))?,
+            ast::Expr::CompoundIdentifier(idents) => self.column(idents)?,
+            ast::Expr::Value(value) => Expr::Literal(literal(value)?),
+            ast::Expr::Nested(expr) => return self.bind(expr, aggregates),
+            ast::Expr::UnaryOp { op, expr } => match op {
+                ast::UnaryOperator::Not => Expr::Not(bind(expr)?),
+                ast::UnaryOperator::Minus => Expr::Negate(bind(expr)?),
+                ast::UnaryOperator::Plus => return self.bind(expr, aggregates),
+                op => return Err(unsupported(format!("operator {}", op))),
+            },
+            ast::Expr::BinaryOp { left, op, right } => {
+                let op = match op {
+                    ast::BinaryOperator::Eq => BinaryOp::Eq,
+                    ast::BinaryOperator::NotEq => BinaryOp::NotEq,
+                    ast::BinaryOperator::Lt => BinaryOp::Lt,
+                    ast::BinaryOperator::LtEq => BinaryOp::LtEq,
+                    ast::BinaryOperator::Gt => BinaryOp::Gt,
+                    ast::BinaryOperator::GtEq => BinaryOp::GtEq,
+                    ast::BinaryOperator::And => BinaryOp::And,
+                    ast::BinaryOperator::Or => BinaryOp::Or,
+                    ast::BinaryOperator::Plus => BinaryOp::Plus,
+                    ast::BinaryOperator::Minus => BinaryOp::Minus,
+                    ast::BinaryOperator::Multiply => BinaryOp::Multiply,
+                    ast::BinaryOperator::Divide => BinaryOp::Divide,
+                    ast::BinaryOperator::Modulo => BinaryOp::Modulo,
+                    ast::BinaryOperator::StringConcat => BinaryOp::Concat,
+                    op => return Err(unsupported(format!("operator {}", op))),
+                };
+                Expr::Binary(bind(left)?, op, bind(right)?)
+            }
+            ast::Expr::IsNull(expr) => Expr::IsNull(bind(expr)?, false),
+            ast::Expr::IsNotNull(expr) => Expr::IsNull(bind(expr)?, true),
+            ast::Expr::Like {
+                negated,
+                any: false,
+                expr,
+                pattern,
+                escape_char: None,
+            } => like(bind(expr)?, pattern, *negated, false)?,
+            ast::Expr::ILike {
+                negated,
+                any: false,
+                expr,
+                pattern,
+                escape_char: None,
+            } => like(bind(expr)?, pattern, *negated, true)?,
+            ast::Expr::InList {
+                expr,
+                list,
+                negated,
+            } => Expr::InList {
+                expr: bind(expr)?,
+                list: list
+                    .iter()
+                    .map(|item| self.bind(item, aggregates))
+                    .collect::<Result<_, _>>()?,
+                negated: *negated,
+            },
+            ast::Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => Expr::Between {
+                expr: bind(expr)?,
+                low: bind(low)?,
+                high: bind(high)?,
+                negated: *negated,
+            },
+            ast::Expr::Substring {
+                expr,
+                substring_from,
+                substring_for,
+                ..
+            } => {
+                let mut args = vec![self.bind(expr, aggregates)?];
+                let from = substring_from.as_deref().ok_or_else(|| unsupported(expr))?;
+                args.push(self.bind(from, aggregates)?);
+                if let Some(length) = substring_for {
+                    args.push(self.bind(length, aggregates)?);
+                }
+                Expr::Function(Function::Substr, args)
+            }
+            ast::Expr::Function(function) => self.function(function, aggregates)?,
+            expr => return Err(unsupported(expr)),
+        };
+        Ok(bound)
+    }
+
+    /// A column, qualified by its table or of the first table
+    fn column(&self, idents: &[ast::Ident]) -> Result<Expr, SledoViewError> {
+        let (table, name) = match idents {
+            [name] => (0, name),
+            [table, name] => {
+                let index = self
+                    .tables
+                    .iter()
+                    .position(|t| t.name == table.value)
+                    .ok_or_else(|| invalid(format!("no table '{}' in FROM", table.value)))?;
+                (index, name)
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "cannot read column '{}'; use json_extract for nested fields",
+                    ast::ObjectName(idents.to_vec())
+                )))
+            }
+        };
+        let column = match name.value.to_lowercase().as_str() {
+            "key" => Column::Key,
+            "value" => Column::Value,
+            "size" => Column::Size,
+            _ => Column::Field(name.value.clone()),
+        };
+        Ok(Expr::Column(table, column))
+    }
+
+    fn function(
+        &mut self,
+        function: &ast::Function,
+        aggregates: bool,
+    ) -> Result<Expr, SledoViewError> {
+        let name = function.name.to_string().to_lowercase();
+        let (args, distinct) = match &function.args {
+            ast::FunctionArguments::None => (Vec::new(), false),
+            ast::FunctionArguments::List(list) if list.clauses.is_empty() => (
+                list.args.iter().collect::<Vec<_>>(),
+                list.duplicate_treatment == Some(ast::DuplicateTreatment::Distinct),
+            ),
+            _ => return Err(unsupported(function)),
+        };
+        if function.over.is_some() || function.filter.is_some() {
+            return Err(unsupported(function));
+        }
+
+        let aggregate = match name.as_str() {
+            "count" => Some(AggregateFunction::Count),
+            "sum" => Some(AggregateFunction::Sum),
+            "avg" => Some(AggregateFunction::Avg),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            _ => None,
+        };
+        if let Some(aggregate) = aggregate {
+            if !aggregates {
+                return Err(invalid(format!("{}() is not allowed here", name)));
+            }
+            let arg = match args[..] {
+                [ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Wildcard)]
+                    if aggregate == AggregateFunction::Count && !distinct =>
+                {
+                    None
+                }
+                [ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg))] => {
+                    Some(self.bind(arg, false)?)
+                }
+                _ => return Err(invalid(format!("{}() takes one argument", name))),
+            };
+            self.aggregates.push(Aggregate {
+                function: aggregate,
+                arg,
+                distinct,
+            });
+            return Ok(Expr::Aggregate(self.aggregates.len() - 1));
+        }
+
+        if distinct {
+            return Err(unsupported(function));
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| match arg {
+                ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg)) => {
+                    self.bind(arg, aggregates)
+                }
+                _ => Err(unsupported(function)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if name == "json_extract" {
+            return match &args[..] {
+                [Expr::Column(table, Column::Value), Expr::Literal(Value::String(path))] => {
+                    Ok(Expr::Column(*table, Column::Path(Path::parse(path)?)))
+                }
+                _ => Err(invalid(
+                    "json_extract takes a value column and a path like '$.a.b'",
+                )),
+            };
+        }
+        let (function, arity) =
+            Function::from_name(&name).ok_or_else(|| invalid(format!("no function {}()", name)))?;
+        if !arity.contains(&args.len()) {
+            return Err(invalid(format!("wrong number of arguments for {}()", name)));
+        }
+        Ok(Expr::Function(function, args))
+    }
+}
+
+fn literal(value: &ast::Value) -> Result<Value, SledoViewError> {
+    match value {
+        ast::Value::Number(number, _) => serde_json::from_str::<Number>(number)
+            .map(Value::Number)
+            .map_err(|_| invalid(format!("invalid number {}", number))),
+        ast::Value::SingleQuotedString(text) | ast::Value::DoubleQuotedString(text) => {
+            Ok(Value::String(text.clone()))
+        }
+        ast::Value::Boolean(b) => Ok(Value::Bool(*b)),
+        ast::Value::Null => Ok(Value::Null),
+        value => Err(unsupported(value)),
+    }
+}
+
+/// `LIKE` with `%` for any text and `_` for any character; the pattern must
+/// be a string
+fn like(
+    expr: Box<Expr>,
+    pattern: &ast::Expr,
+    negated: bool,
+    ignore_case: bool,
+) -> Result<Expr, SledoViewError> {
+    let ast::Expr::Value(ast::Value::SingleQuotedString(pattern)) = pattern else {
+        return Err(invalid("LIKE takes a quoted pattern"));
+    };
+    let mut regex = String::from(if ignore_case { "(?is)^" } else { "(?s)^" });
+    for c in pattern.chars() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    let prefix = if ignore_case {
+        String::new()
+    } else {
+        pattern
+            .chars()
+            .take_while(|c| !matches!(c, '%' | '_'))
+            .collect()
+    };
+    Ok(Expr::Like {
+        expr,
+        pattern: Regex::new(&regex).map_err(|e| invalid(e.to_string()))?,
+        prefix,
+        negated,
+    })
+}
+
+impl Expr {
+    fn eval(&self, row: &Row, aggregates: &[Value]) -> Value {
+        let eval = |expr: &Expr| expr.eval(row, aggregates);
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Column(table, column) => match &row[*table] {
+                Some(entry) => entry.column(column),
+                None => Value::Null,
+            },
+            Expr::Not(expr) => truth(&eval(expr)).map_or(Value::Null, |b| Value::Bool(!b)),
+            Expr::Negate(expr) => arithmetic(&Value::from(0), BinaryOp::Minus, &eval(expr)),
+            Expr::Binary(left, BinaryOp::And, right) => {
+                match (truth(&eval(left)), truth(&eval(right))) {
+                    (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+                    (Some(true), Some(true)) => Value::Bool(true),
+                    _ => Value::Null,
+                }
+            }
+            Expr::Binary(left, BinaryOp::Or, right) => {
+                match (truth(&eval(left)), truth(&eval(right))) {
+                    (Some(true), _) | (_, Some(true)) => Value::Bool(true),
+                    (Some(false), Some(false)) => Value::Bool(false),
+                    _ => Value::Null,
+                }
+            }
+            Expr::Binary(left, op, right) => binary(&eval(left), *op, &eval(right)),
+            Expr::IsNull(expr, negated) => Value::Bool(eval(expr).is_null() != *negated),
+            Expr::Like {
+                expr,
+                pattern,
+                negated,
+                ..
+            } => match eval(expr) {
+                Value::Null => Value::Null,
+                value => Value::Bool(pattern.is_match(&text(&value)) != *negated),
+            },
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => match eval(expr) {
+                Value::Null => Value::Null,
+                value => {
+                    let found = list.iter().any(|item| query::json_eq(&value, &eval(item)));
+                    Value::Bool(found != *negated)
+                }
+            },
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let value = eval(expr);
+                match (compare(&eval(low), &value), compare(&value, &eval(high))) {
+                    (Some(low), Some(high)) => Value::Bool(
+                        (low != Ordering::Greater && high != Ordering::Greater) != *negated,
+                    ),
+                    _ => Value::Null,
+                }
+            }
+            Expr::Function(function, args) => {
+                let args: Vec<Value> = args.iter().map(eval).collect();
+                call(*function, &args)
+            }
+            Expr::Aggregate(index) => aggregates[*index].clone(),
+        }
+    }
+
+    /// Whether the expression uses an aggregate
+    fn has_aggregate(&self) -> bool {
+        match self {
+            Expr::Aggregate(_) => true,
+            Expr::Literal(_) | Expr::Column(..) => false,
+            Expr::Not(expr) | Expr::Negate(expr) | Expr::IsNull(expr, _) => expr.has_aggregate(),
+            Expr::Like { expr, .. } => expr.has_aggregate(),
+            Expr::Binary(left, _, right) => left.has_aggregate() || right.has_aggregate(),
+            Expr::InList { expr, list, .. } => {
+                expr.has_aggregate() || list.iter().any(Expr::has_aggregate)
+            }
+            Expr::Between {
+                expr, low, high, ..
+            } => expr.has_aggregate() || low.has_aggregate() || high.has_aggregate(),
+            Expr::Function(_, args) => args.iter().any(Expr::has_aggregate),
+        }
+    }
+
+    /// The key prefix every row meeting this condition has in the first
+    /// table, from a `key LIKE 'prefix%'` on its own or among `AND`s
+    fn key_prefix(&self) -> Option<&str> {
+        match self {
+            Expr::Like {
+                expr,
+                prefix,
+                negated: false,
+                ..
+            } if matches!(**expr, Expr::Column(0, Column::Key)) && !prefix.is_empty() => {
+                Some(prefix)
+            }
+            Expr::Binary(left, BinaryOp::And, right) => {
+                match (left.key_prefix(), right.key_prefix()) {
+                    (Some(a), Some(b)) => Some(if a.len() >= b.len() { a } else { b }),
+                    (a, b) => a.or(b),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A condition's outcome; `None` for `NULL`
+fn truth(value: &Value) -> Option<bool> {
+    match value {
+        Value::Null => None,
+        Value::Bool(b) => Some(*b),
+        Value::Number(n) => Some(n.as_f64() != Some(0.0)),
+        _ => Some(true),
+    }
+}
+
+/// Strings as they are, anything else as JSON
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Numbers and strings are ordered among themselves, as are booleans
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => query::json_cmp(left, right),
+    }
+}
+
+/// The order of `ORDER BY`, `min` and `max`: `NULL` first, then booleans,
+/// numbers, strings, arrays and objects
+fn sort_order(left: &Value, right: &Value) -> Ordering {
+    let rank = |value: &Value| match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    };
+    rank(left)
+        .cmp(&rank(right))
+        .then_with(|| compare(left, right).unwrap_or(Ordering::Equal))
+        .then_with(|| left.to_string().cmp(&right.to_string()))
+}
+
+fn binary(left: &Value, op: BinaryOp, right: &Value) -> Value {
+    if left.is_null() || right.is_null() {
+        return Value::Null;
+    }
+    let ordering = |accept: fn(Ordering) -> bool| {
+        compare(left, right).map_or(Value::Null, |ordering| Value::Bool(accept(ordering)))
+    };
+    match op {
+        BinaryOp::Eq => Value::Bool(query::json_eq(left, right)),
+        BinaryOp::NotEq => Value::Bool(!query::json_eq(left, right)),
+        BinaryOp::Lt => ordering(Ordering::is_lt),
+        BinaryOp::LtEq => ordering(Ordering::is_le),
+        BinaryOp::Gt => ordering(Ordering::is_gt),
+        BinaryOp::GtEq => ordering(Ordering::is_ge),
+        BinaryOp::Concat => Value::String(text(left) + &text(right)),
+        op => arithmetic(left, op, right),
+    }
+}
+
+/// Whole numbers stay whole unless they overflow; division by zero is `NULL`
+fn arithmetic(left: &Value, op: BinaryOp, right: &Value) -> Value {
+    let (Value::Number(a), Value::Number(b)) = (left, right) else {
+        return Value::Null;
+    };
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        let whole = match op {
+            BinaryOp::Plus => a.checked_add(b),
+            BinaryOp::Minus => a.checked_sub(b),
+            BinaryOp::Multiply => a.checked_mul(b),
+            BinaryOp::Divide if b == 0 => return Value::Null,
+            BinaryOp::Divide => a.checked_div(b),
+            BinaryOp::Modulo if b == 0 => return Value::Null,
+            BinaryOp::Modulo => a.checked_rem(b),
+            _ => None,
+        };
+        if let Some(whole) = whole {
+            return Value::from(whole);
+        }
+    }
+
+    let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) else {
+        return Value::Null;
+    };
+    let result = match op {
+        BinaryOp::Plus => a + b,
+        BinaryOp::Minus => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide => a / b,
+        BinaryOp::Modulo => a % b,
+        _ => return Value::Null,
+    };
+    Number::from_f64(result).map_or(Value::Null, Value::Number)
+}
+
+fn call(function: Function, args: &[Value]) -> Value {
+    if function == Function::Coalesce {
+        return args
+            .iter()
+            .find(|arg| !arg.is_null())
+            .cloned()
+            .unwrap_or_default();
+    }
+    if args.iter().any(Value::is_null) {
+        return Value::Null;
+    }
+    let count = |value: &Value| value.as_i64().map(|n| n.max(0) as usize);
+
+    match function {
+        // Strings are measured in bytes, as values are
+        Function::Length => match &args[0] {
+            Value::String(text) => Value::from(text.len()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(fields) => Value::from(fields.len()),
+            value => Value::from(text(value).len()),
+        },
+        Function::Lower => Value::String(text(&args[0]).to_lowercase()),
+        Function::Upper => Value::String(text(&args[0]).to_uppercase()),
+        Function::Substr => {
+            let (Some(start), length) = (args[1].as_i64(), args.get(2).map(count)) else {
+                return Value::Null;
+            };
+            let text = text(&args[0]);
+            let chars = text.chars().skip(start.max(1) as usize - 1);
+            Value::String(match length {
+                Some(Some(length)) => chars.take(length).collect(),
+                Some(None) => return Value::Null,
+                None => chars.collect(),
+            })
+        }
+        Function::Prefix => {
+            let text = text(&args[0]);
+            match &args[1] {
+                Value::String(separator) => match text.find(separator.as_str()) {
+                    Some(end) if !separator.is_empty() => Value::String(text[..end].to_string()),
+                    _ => Value::String(text),
+                },
+                length => match count(length) {
+                    Some(length) => Value::String(text.chars().take(length).collect()),
+                    None => Value::Null,
+                },
+            }
+        }
+        Function::SplitPart => {
+            let (Value::String(separator), Some(n)) = (&args[1], args[2].as_i64()) else {
+                return Value::Null;
+            };
+            let text = text(&args[0]);
+            let part = match n {
+                1.. if !separator.is_empty() => text.split(separator.as_str()).nth(n as usize - 1),
+                1 => Some(text.as_str()),
+                _ => None,
+            };
+            Value::String(part.unwrap_or_default().to_string())
+        }
+        Function::Abs => match &args[0] {
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => n.checked_abs().map_or(Value::Null, Value::from),
+                None => n
+                    .as_f64()
+                    .and_then(|n| Number::from_f64(n.abs()))
+                    .map_or(Value::Null, Value::Number),
+            },
+            _ => Value::Null,
+        },
+        Function::Coalesce => unreachable!("handled above"),
+    }
+}
+
+/// The running result of an aggregate over a group
+#[derive(Default, Clone)]
+struct Accumulator {
+    count: usize,
+    sum: Option<Value>,
+    best: Option<Value>,
+    seen: HashSet<String>,
+}
+
+impl Accumulator {
+    fn add(&mut self, aggregate: &Aggregate, value: Value) {
+        if aggregate.arg.is_some() && value.is_null() {
+            return;
+        }
+        if aggregate.distinct && !self.seen.insert(value.to_string()) {
+            return;
+        }
+        self.count += 1;
+
+        match aggregate.function {
+            AggregateFunction::Count => {}
+            AggregateFunction::Sum | AggregateFunction::Avg => {
+                if value.is_number() {
+                    let sum = self.sum.take().unwrap_or(Value::from(0));
+                    self.sum = Some(arithmetic(&sum, BinaryOp::Plus, &value));
+                }
+            }
+            AggregateFunction::Min | AggregateFunction::Max => {
+                let better = self.best.as_ref().is_none_or(|best| {
+                    let ordering = sort_order(&value, best);
+                    match aggregate.function {
+                        AggregateFunction::Min => ordering.is_lt(),
+                        _ => ordering.is_gt(),
+                    }
+                });
+                if better {
+                    self.best = Some(value);
+                }
+            }
+        }
+    }
+
+    fn finish(&self, aggregate: &Aggregate) -> Value {
+        match aggregate.function {
+            AggregateFunction::Count => Value::from(self.count),
+            AggregateFunction::Sum => self.sum.clone().unwrap_or_default(),
+            AggregateFunction::Avg => match &self.sum {
+                Some(sum) => arithmetic(
+                    &Value::from(sum.as_f64().unwrap_or_default()),
+                    BinaryOp::Divide,
+                    &Value::from(self.count as f64),
+                ),
+                None => Value::Null,
+            },
+            AggregateFunction::Min | AggregateFunction::Max => {
+                self.best.clone().unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// A table joined to the rows read so far
+struct Join {
+    table: usize,
+    /// Keep rows without a match, with `NULL` columns for this table
+    left: bool,
+    on: Option<Expr>,
+    /// The earlier table whose key this table's key must equal, to look
+    /// keys up instead of reading the whole tree
+    key_of: Option<usize>,
+}
+
+/// What `ORDER BY` sorts on
+enum SortKey {
+    /// A column of the result, by position or alias
+    Output(usize),
+    Expr(Expr),
+}
+
+struct Plan {
+    tables: Vec<Table>,
+    joins: Vec<Join>,
+    selection: Option<Expr>,
+    columns: Vec<String>,
+    outputs: Vec<Expr>,
+    /// `None` without `GROUP BY` or aggregates
+    group_by: Option<Vec<Expr>>,
+    having: Option<Expr>,
+    aggregates: Vec<Aggregate>,
+    distinct: bool,
+    order_by: Vec<(SortKey, bool)>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Plan {
+    fn new(viewer: &SledViewer, query: &ast::Query) -> Result<Self, SledoViewError> {
+        let ast::SetExpr::Select(select) = &*query.body else {
+            return Err(unsupported(&query.body));
+        };
+        if query.with.is_some() || query.fetch.is_some() || !query.limit_by.is_empty() {
+            return Err(unsupported(query));
+        }
+        let distinct = match &select.distinct {
+            None => false,
+            Some(ast::Distinct::Distinct) => true,
+            Some(distinct) => return Err(unsupported(distinct)),
+        };
+
+        // Tables: the first of FROM, what it joins, and further FROM items
+        // as cross joins
+        let mut tables = Vec::new();
+        let mut relations = Vec::new();
+        for (i, from) in select.from.iter().enumerate() {
+            let operator = (i > 0).then_some(&ast::JoinOperator::CrossJoin);
+            relations.push((&from.relation, operator));
+            for join in &from.joins {
+                relations.push((&join.relation, Some(&join.join_operator)));
+            }
+        }
+        if relations.is_empty() {
+            return Err(invalid("expected FROM <tree>"));
+        }
+        for (relation, _) in &relations {
+            tables.push(table(viewer, relation)?);
+        }
+
+        let mut binder = Binder {
+            tables: &tables,
+            aggregates: Vec::new(),
+        };
+        let mut joins = Vec::new();
+        for (index, (_, operator)) in relations.iter().enumerate().skip(1) {
+            let (left, constraint) = match operator {
+                Some(ast::JoinOperator::Inner(constraint)) => (false, Some(constraint)),
+                Some(ast::JoinOperator::LeftOuter(constraint)) => (true, Some(constraint)),
+                Some(ast::JoinOperator::CrossJoin) => (false, None),
+                Some(operator) => return Err(unsupported(format!("{:?} join", operator))),
+                None => unreachable!("only the first table has no join"),
+            };
+            let on = match constraint {
+                Some(ast::JoinConstraint::On(on)) => Some(binder.bind(on, false)?),
+                Some(ast::JoinConstraint::None) | None => None,
+                Some(_) => return Err(invalid("joins take ON <condition>")),
+            };
+            let key_of = on.as_ref().and_then(|on| match on {
+                Expr::Binary(a, BinaryOp::Eq, b) => match (&**a, &**b) {
+                    (Expr::Column(i, Column::Key), Expr::Column(j, Column::Key))
+                    | (Expr::Column(j, Column::Key), Expr::Column(i, Column::Key))
+                        if *j == index && *i < index =>
+                    {
+                        Some(*i)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            });
+            joins.push(Join {
+                table: index,
+                left,
+                on,
+                key_of,
+            });
+        }
+
+        let selection = select
+            .selection
+            .as_ref()
+            .map(|expr| binder.bind(expr, false))
+            .transpose()?;
+
+        let mut columns = Vec::new();
+        let mut outputs = Vec::new();
+        let mut aliases = HashMap::new();
+        for item in &select.projection {
+            match item {
+                ast::SelectItem::UnnamedExpr(expr) => {
+                    columns.push(expr.to_string());
+                    outputs.push(binder.bind(expr, true)?);
+                }
+                ast::SelectItem::ExprWithAlias { expr, alias } => {
+                    aliases.insert(alias.value.clone(), outputs.len());
+                    columns.push(alias.value.clone());
+                    outputs.push(binder.bind(expr, true)?);
+                }
+                ast::SelectItem::Wildcard(_) => {
+                    for index in 0..tables.len() {
+                        wildcard(&tables, index, &mut columns, &mut outputs);
+                    }
+                }
+                ast::SelectItem::QualifiedWildcard(name, _) => {
+                    let name = name.to_string();
+                    let index = tables
+                        .iter()
+                        .position(|t| t.name == name)
+                        .ok_or_else(|| invalid(format!("no table '{}' in FROM", name)))?;
+                    wildcard(&tables, index, &mut columns, &mut outputs);
+                }
+            }
+        }
+
+        let mut group_by = Vec::new();
+        let exprs = match &select.group_by {
+            ast::GroupByExpr::Expressions(exprs, modifiers) if modifiers.is_empty() => exprs,
+            group_by => return Err(unsupported(group_by)),
+        };
+        for expr in exprs {
+            let expr = match output_column(expr, &aliases, outputs.len())? {
+                Some(index) if outputs[index].has_aggregate() => {
+                    return Err(invalid(format!("cannot group by aggregate {}", expr)))
+                }
+                Some(index) => outputs[index].clone(),
+                None => binder.bind(expr, false)?,
+            };
+            group_by.push(expr);
+        }
+        let having = select
+            .having
+            .as_ref()
+            .map(|expr| binder.bind(expr, true))
+            .transpose()?;
+
+        let mut order_by = Vec::new();
+        for item in query.order_by.iter().flat_map(|order_by| &order_by.exprs) {
+            let key = match output_column(&item.expr, &aliases, outputs.len())? {
+                Some(index) => SortKey::Output(index),
+                None => SortKey::Expr(binder.bind(&item.expr, true)?),
+            };
+            order_by.push((key, item.asc.unwrap_or(true)));
+        }
+
+        let aggregates = binder.aggregates;
+        let grouped = !group_by.is_empty() || !aggregates.is_empty();
+        if !grouped && having.is_some() {
+            return Err(invalid("HAVING needs GROUP BY or an aggregate"));
+        }
+
+        Ok(Self {
+            joins,
+            selection,
+            columns: unique_names(columns),
+            outputs,
+            group_by: grouped.then_some(group_by),
+            having,
+            aggregates,
+            distinct,
+            order_by,
+            offset: query
+                .offset
+                .as_ref()
+                .map(|offset| count(&offset.value))
+                .transpose()?
+                .unwrap_or(0),
+            limit: query.limit.as_ref().map(count).transpose()?,
+            tables,
+        })
+    }
+
+    fn run(&self, observer: &mut dyn ScanObserver) -> Result<Rows> {
+        let mut read = 0;
+        let mut scanned = |observer: &mut dyn ScanObserver| {
+            read += 1;
+            observer.scanned(read)
+        };
+
+        // Tables joined on anything but their key are read once, up front
+        let mut joined: Vec<Option<Vec<Rc<Entry>>>> = Vec::new();
+        for join in &self.joins {
+            if join.key_of.is_some() {
+                joined.push(None);
+                continue;
+            }
+            let table = &self.tables[join.table];
+            let mut entries = Vec::new();
+            for entry in table.tree.iter() {
+                let (key, value) = entry?;
+                scanned(observer)?;
+                entries.push(Entry::new(&key, &value, &table.codecs));
+            }
+            joined.push(Some(entries));
+        }
+
+        // Without sorting, grouping or DISTINCT, stop once the page is full
+        let wanted = match (&self.group_by, self.order_by.is_empty(), self.distinct) {
+            (None, true, false) => self.limit.map(|limit| self.offset + limit),
+            _ => None,
+        };
+
+        let mut results: Vec<(Vec<Value>, Vec<Value>)> = Vec::new();
+        let mut groups: Vec<(Row, Vec<Accumulator>)> = Vec::new();
+        let mut group_index: HashMap<String, usize> = HashMap::new();
+
+        let first = &self.tables[0];
+        let prefix = self
+            .selection
+            .as_ref()
+            .and_then(Expr::key_prefix)
+            .filter(|prefix| {
+                first.codecs.key == KeyCodec::Text && bytes::escape(prefix.as_bytes()) == *prefix
+            })
+            .unwrap_or_default();
+
+        'scan: for entry in first.tree.scan_prefix(prefix.as_bytes()) {
+            let (key, value) = entry?;
+            scanned(observer)?;
+            let mut row: Row = vec![None; self.tables.len()];
+            row[0] = Some(Entry::new(&key, &value, &first.codecs));
+
+            for row in self.join(row, 0, &joined)? {
+                let selected = self
+                    .selection
+                    .as_ref()
+                    .is_none_or(|selection| truth(&selection.eval(&row, &[])) == Some(true));
+                if !selected {
+                    continue;
+                }
+
+                match &self.group_by {
+                    Some(group_by) => {
+                        let group: Vec<Value> =
+                            group_by.iter().map(|expr| expr.eval(&row, &[])).collect();
+                        let index = *group_index
+                            .entry(Value::Array(group).to_string())
+                            .or_insert_with(|| {
+                                groups.push((
+                                    row.clone(),
+                                    vec![Accumulator::default(); self.aggregates.len()],
+                                ));
+                                groups.len() - 1
+                            });
+                        for (aggregate, accumulator) in
+                            self.aggregates.iter().zip(&mut groups[index].1)
+                        {
+                            let value = aggregate
+                                .arg
+                                .as_ref()
+                                .map_or(Value::Null, |arg| arg.eval(&row, &[]));
+                            accumulator.add(aggregate, value);
+                        }
+                    }
+                    None => {
+                        results.push(self.output(&row, &[]));
+                        if wanted.is_some_and(|wanted| results.len() >= wanted) {
+                            break 'scan;
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(group_by) = &self.group_by {
+            // Aggregates without GROUP BY summarize even an empty result
+            if group_by.is_empty() && groups.is_empty() {
+                let empty = vec![None; self.tables.len()];
+                groups.push((empty, vec![Accumulator::default(); self.aggregates.len()]));
+            }
+            for (row, accumulators) in &groups {
+                let aggregates: Vec<Value> = self
+                    .aggregates
+                    .iter()
+                    .zip(accumulators)
+                    .map(|(aggregate, accumulator)| accumulator.finish(aggregate))
+                    .collect();
+                let kept = self
+                    .having
+                    .as_ref()
+                    .is_none_or(|having| truth(&having.eval(row, &aggregates)) == Some(true));
+                if kept {
+                    results.push(self.output(row, &aggregates));
+                }
+            }
+        }
+
+        if self.distinct {
+            let mut seen = HashSet::new();
+            results.retain(|(values, _)| seen.insert(Value::from(values.clone()).to_string()));
+        }
+        if !self.order_by.is_empty() {
+            results.sort_by(|(_, a), (_, b)| {
+                a.iter()
+                    .zip(b)
+                    .zip(&self.order_by)
+                    .map(|((a, b), (_, ascending))| {
+                        let ordering = sort_order(a, b);
+                        if *ascending {
+                            ordering
+                        } else {
+                            ordering.reverse()
+                        }
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        let rows = results
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|(values, _)| values)
+            .collect();
+        Ok(Rows {
+            columns: self.columns.clone(),
+            rows,
+        })
+    }
+
+    /// The rows `row` makes with the tables joined from `joins[level]` on
+    fn join(&self, row: Row, level: usize, joined: &[Option<Vec<Rc<Entry>>>]) -> Result<Vec<Row>> {
+        let Some(join) = self.joins.get(level) else {
+            return Ok(vec![row]);
+        };
+        let table = &self.tables[join.table];
+        let candidates = match (join.key_of, &joined[level]) {
+            (Some(other), _) => match &row[other] {
+                Some(entry) => table
+                    .tree
+                    .get(&entry.info.key)?
+                    .map(|value| Entry::new(&entry.info.key, &value, &table.codecs))
+                    .into_iter()
+                    .collect(),
+                None => Vec::new(),
+            },
+            (None, Some(entries)) => entries.clone(),
+            (None, None) => unreachable!("tables not joined on their key are read up front"),
+        };
+
+        let mut rows = Vec::new();
+        for candidate in candidates {
+            let mut next = row.clone();
+            next[join.table] = Some(candidate);
+            let matched = join
+                .on
+                .as_ref()
+                .is_none_or(|on| truth(&on.eval(&next, &[])) == Some(true));
+            if matched {
+                rows.extend(self.join(next, level + 1, joined)?);
+            }
+        }
+        if rows.is_empty() && join.left {
+            rows = self.join(row, level + 1, joined)?;
+        }
+        Ok(rows)
+    }
+
+    /// The result columns of a row or group, and the values it sorts by
+    fn output(&self, row: &Row, aggregates: &[Value]) -> (Vec<Value>, Vec<Value>) {
+        let values: Vec<Value> = self
+            .outputs
+            .iter()
+            .map(|expr| expr.eval(row, aggregates))
+            .collect();
+        let sort = self
+            .order_by
+            .iter()
+            .map(|(key, _)| match key {
+                SortKey::Output(index) => values[*index].clone(),
+                SortKey::Expr(expr) => expr.eval(row, aggregates),
+            })
+            .collect();
+        (values, sort)
+    }
+}
+
+/// Open the tree a `FROM` or `JOIN` item names
+fn table(viewer: &SledViewer, relation: &ast::TableFactor) -> Result<Table, SledoViewError> {
+    let ast::TableFactor::Table {
+        name, alias, args, ..
+    } = relation
+    else {
+        return Err(unsupported(relation));
+    };
+    if args.is_some() {
+        return Err(unsupported(relation));
+    }
+    let tree_name = match &name.0[..] {
+        [ident] => ident.value.clone(),
+        _ => return Err(invalid(format!("no tree '{}'", name))),
+    };
+    let (tree, codecs) = viewer
+        .existing_tree(tree_name.as_bytes())
+        .map_err(|e| invalid(e.to_string()))?
+        .ok_or_else(|| invalid(format!("no tree '{}'", tree_name)))?;
+    Ok(Table {
+        name: alias
+            .as_ref()
+            .map_or(tree_name, |alias| alias.name.value.clone()),
+        tree,
+        codecs,
+    })
+}
+
+/// The result column a `GROUP BY` or `ORDER BY` item refers to by position
+/// or alias
+fn output_column(
+    expr: &ast::Expr,
+    aliases: &HashMap<String, usize>,
+    columns: usize,
+) -> Result<Option<usize>, SledoViewError> {
+    match expr {
+        ast::Expr::Value(ast::Value::Number(n, _)) => n
+            .parse::<usize>()
+            .ok()
+            .filter(|position| (1..=columns).contains(position))
+            .map(|position| Some(position - 1))
+            .ok_or_else(|| invalid(format!("no column {}", n))),
+        ast::Expr::Identifier(ident) => Ok(aliases.get(&ident.value).copied()),
+        _ => Ok(None),
+    }
+}
+
+/// The key, value and size of a table, qualified when there are several
+fn wildcard(tables: &[Table], index: usize, columns: &mut Vec<String>, outputs: &mut Vec<Expr>) {
+    for (name, column) in [
+        ("key", Column::Key),
+        ("value", Column::Value),
+        ("size", Column::Size),
+    ] {
+        columns.push(if tables.len() > 1 {
+            format!("{}.{}", tables[index].name, name)
+        } else {
+            name.to_string()
+        });
+        outputs.push(Expr::Column(index, column));
+    }
+}
+
+/// Column names with repeats numbered, so every column of a JSON record
+/// keeps its value
+fn unique_names(columns: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    columns
+        .into_iter()
+        .map(|name| {
+            let mut unique = name.clone();
+            let mut n = 1;
+            while !seen.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}_{}", name, n);
+            }
+            unique
+        })
+        .collect()
+}
+
+/// A row count for `LIMIT` or `OFFSET`
+fn count(expr: &ast::Expr) -> Result<usize, SledoViewError> {
+    match expr {
+        ast::Expr::Value(ast::Value::Number(n, _)) => n.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| invalid(format!("expected a row count, not {}", expr)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn create_test_db() -> TempDir {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp directory");
+        {
+            let db = sled::open(temp_dir.path()).expect("Failed to create test database");
+            let users = db.open_tree("users").unwrap();
+            users
+                .insert("user_1", r#"{"name":"Ann","plan":"pro"}"#)
+                .unwrap();
+            users
+                .insert("user_2", r#"{"name":"Bob","plan":"free"}"#)
+                .unwrap();
+            users
+                .insert("user_3", r#"{"name":"Cy","plan":"pro"}"#)
+                .unwrap();
+            users.insert("admin_1", "root").unwrap();
+            let sessions = db.open_tree("sessions").unwrap();
+            sessions.insert("user_1", "token-a").unwrap();
+            sessions.insert("user_3", "token-ccc").unwrap();
+            db.flush().unwrap();
+        }
+        temp_dir
+    }
+
+    fn run(viewer: &SledViewer, statement: &str) -> Vec<Vec<Value>> {
+        execute(viewer, statement, &mut ()).unwrap().rows
+    }
+
+    #[test]
+    fn test_select() {
+        let temp_dir = create_test_db();
+        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+        let rows = execute(
+            &viewer,
+            "SELECT key, len(value) AS size FROM users WHERE key LIKE 'user_%' \
+             ORDER BY name DESC LIMIT 2",
+            &mut (),
+        )
+        .unwrap();
+        assert_eq!(rows.columns, ["key", "size"]);
+        assert_eq!(
+            rows.rows,
+            [[json!("user_3"), json!(26)], [json!("user_2"), json!(28)]]
+        );
+
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT name FROM users WHERE plan = 'pro' AND NOT key = 'user_1'"
+            ),
+            [[json!("Cy")]]
+        );
+        assert_eq!(
+            run(&viewer, "SELECT key FROM users WHERE name IS NULL"),
+            [[json!("admin_1")]]
+        );
+        assert_eq!(
+            run(&viewer, "SELECT value, size FROM users LIMIT 1"),
+            [[json!("root"), json!(4)]]
+        );
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT DISTINCT plan FROM users WHERE plan IS NOT NULL ORDER BY 1"
+            ),
+            [[json!("free")], [json!("pro")]]
+        );
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT upper(json_extract(value, '$.name')) || '!' FROM users \
+                 WHERE key BETWEEN 'user_1' AND 'user_2'"
+            ),
+            [[json!("ANN!")], [json!("BOB!")]]
+        );
+    }
+
+    #[test]
+    fn test_join() {
+        let temp_dir = create_test_db();
+        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT u.name, s.value FROM users u JOIN sessions s ON u.key = s.key"
+            ),
+            [
+                [json!("Ann"), json!("token-a")],
+                [json!("Cy"), json!("token-ccc")]
+            ]
+        );
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT users.key, sessions.size FROM users \
+                 LEFT JOIN sessions ON sessions.key = users.key WHERE users.key LIKE 'user_%'"
+            ),
+            [
+                [json!("user_1"), json!(7)],
+                [json!("user_2"), Value::Null],
+                [json!("user_3"), json!(9)]
+            ]
+        );
+        // Joins on anything but the key compare every pair
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT count(*) FROM users a JOIN users b ON a.plan = b.plan AND a.key < b.key"
+            ),
+            [[json!(1)]]
+        );
+    }
+
+    #[test]
+    fn test_group_by() {
+        let temp_dir = create_test_db();
+        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT prefix(key, '_') AS kind, count(*), sum(size) FROM users \
+                 GROUP BY kind ORDER BY 2 DESC"
+            ),
+            [
+                [json!("user"), json!(3), json!(81)],
+                [json!("admin"), json!(1), json!(4)]
+            ]
+        );
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT plan, count(*) FROM users WHERE plan IS NOT NULL \
+                 GROUP BY plan HAVING count(*) > 1"
+            ),
+            [[json!("pro"), json!(2)]]
+        );
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT count(DISTINCT plan), min(key), max(size) FROM users"
+            ),
+            [[json!(2), json!("admin_1"), json!(28)]]
+        );
+        assert_eq!(
+            run(
+                &viewer,
+                "SELECT count(*), avg(size) FROM sessions WHERE key = 'none'"
+            ),
+            [[json!(0), Value::Null]]
+        );
+    }
+
+    #[test]
+    fn test_invalid_sql() {
+        let temp_dir = create_test_db();
+        let viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+        for statement in [
+            "SELEC key FROM users",
+            "DELETE FROM users",
+            "SELECT key FROM missing",
+            "SELECT key FROM users WHERE count(*) > 1",
+            "SELECT nope(key) FROM users",
+            "SELECT key FROM users ORDER BY 3",
+            "SELECT x.key FROM users",
+        ] {
+            let error = execute(&viewer, statement, &mut ()).unwrap_err();
+            assert!(
+                matches!(
+                    error.downcast_ref::<SledoViewError>(),
+                    Some(SledoViewError::InvalidSql { .. })
+                ),
+                "{}: {}",
+                statement,
+                error
+            );
+        }
+    }
+}
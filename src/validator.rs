@@ -1,3 +1,4 @@
+use crate::db::{is_lock_error, lock_shared};
 use crate::error::SledoViewError;
 use anyhow::Result;
 use colored::*;
@@ -6,15 +7,24 @@ use std::path::Path;
 
 pub struct DatabaseValidator<'a> {
     path: &'a Path,
+    quiet: bool,
 }
 
 impl<'a> DatabaseValidator<'a> {
     pub fn new(path: &'a Path) -> Self {
-        Self { path }
+        Self { path, quiet: false }
+    }
+
+    /// Suppress progress messages (used by one-shot commands)
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
     }
 
     pub fn validate(&self) -> Result<()> {
-        println!("{}", "Validating database...".yellow());
+        if !self.quiet {
+            println!("{}", "Validating database...".yellow());
+        }
 
         self.check_file_exists()?;
         self.check_file_readable()?;
@@ -22,11 +32,13 @@ impl<'a> DatabaseValidator<'a> {
         self.check_sled_structure()?;
         self.check_not_locked()?;
 
-        println!(
-            "{} {}",
-            "✓".bright_green(),
-            "Database validation passed".green()
-        );
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✓".bright_green(),
+                "Database validation passed".green()
+            );
+        }
         Ok(())
     }
 
@@ -107,11 +119,12 @@ impl<'a> DatabaseValidator<'a> {
     }
 
     fn check_not_locked(&self) -> Result<()> {
-        // Try to open the database to check if it's locked
-        match sled::open(self.path) {
+        // Probe SLED's file lock; opening the database would write to it
+        match lock_shared(self.path) {
             Ok(_) => Ok(()),
-            Err(sled::Error::Io(ref io_err))
-                if io_err.kind() == std::io::ErrorKind::PermissionDenied =>
+            Err(ref io_err)
+                if io_err.kind() == std::io::ErrorKind::PermissionDenied
+                    || is_lock_error(io_err) =>
             {
                 Err(SledoViewError::DatabaseLocked {
                     path: self.path.display().to_string(),
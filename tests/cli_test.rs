@@ -0,0 +1,956 @@
+mod common;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+// Spawned processes briefly inherit open file descriptors, including the
+// lock sled holds on a database that another test has open in-process.
+// Running these tests one at a time keeps that from surfacing as a spurious
+// "could not acquire lock" failure.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial() -> MutexGuard<'static, ()> {
+    SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn sledoview() -> Command {
+    Command::cargo_bin("sledoview").unwrap()
+}
+
+#[test]
+fn test_cli_help() {
+    let _guard = serial();
+    let mut cmd = sledoview();
+    cmd.arg("--help");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "CLI tool for viewing and managing SLED databases",
+    ));
+}
+
+#[test]
+fn test_cli_nonexistent_database() {
+    let _guard = serial();
+    let mut cmd = sledoview();
+    cmd.arg("/nonexistent/database");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Database file not found"));
+}
+
+#[test]
+fn test_cli_one_shot_get() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "user_001"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("John Doe"))
+        .stdout(predicate::str::contains("Validating").not());
+}
+
+#[test]
+fn test_cli_one_shot_get_missing_key() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "nonexistent_key"]);
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("Key not found: nonexistent_key"));
+}
+
+#[test]
+fn test_cli_one_shot_set_and_count_in_tree() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["tree", "create", "archive"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Created tree archive"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "archive", "set", "old_user", "Old Value"]);
+    cmd.assert().success();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["count", "--tree", "archive"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Total records: 1"));
+}
+
+#[test]
+fn test_cli_one_shot_nonexistent_database() {
+    let _guard = serial();
+    let mut cmd = sledoview();
+    cmd.args(["/nonexistent/database", "count"]);
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains("Database file not found"));
+}
+
+#[test]
+fn test_cli_one_shot_json_output() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let output = sledoview()
+        .arg(temp_dir.path())
+        .args(["get", "user_001", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["key"], "user_001");
+    assert_eq!(record["value"], "John Doe");
+    assert_eq!(record["value_encoding"], "utf8");
+    assert_eq!(record["size"], 8);
+}
+
+#[test]
+fn test_cli_one_shot_byte_literals() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["set", "0x00ff", "b64:AAE="]);
+    cmd.assert().success();
+
+    let output = sledoview()
+        .arg(temp_dir.path())
+        .args(["get", "\\x00\\xff", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["key"], "AP8=");
+    assert_eq!(record["key_encoding"], "base64");
+    assert_eq!(record["value"], "\u{0}\u{1}");
+    assert_eq!(record["value_encoding"], "utf8");
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["list", "\\x00*"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\\x00\\xff"));
+}
+
+#[test]
+fn test_cli_range_and_prefix() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["range", "user_002", "user_999", "--format", "ndjson"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 2);
+    assert!(output.contains("user_002") && output.contains("user_003"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["prefix", "session_", "--format", "ndjson"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(String::from_utf8(output).unwrap().lines().count(), 2);
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["range", "z", "a"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid range"));
+}
+
+#[test]
+fn test_cli_sort() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let keys = |args: &[&str]| {
+        let mut cmd = sledoview();
+        cmd.arg(temp_dir.path())
+            .args(args)
+            .args(["--format", "ndjson"]);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["key"].clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        keys(&["list", "user_*", "--sort", "size", "--reverse"]),
+        ["user_003", "user_002", "user_001"]
+    );
+    assert_eq!(
+        keys(&["prefix", "session_", "--reverse", "--limit", "1"]),
+        ["session_def456"]
+    );
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["list", "--sort", "random"]);
+    cmd.assert().code(2);
+}
+
+#[test]
+fn test_cli_across_trees() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    {
+        // The fixture's handle may still hold the lock for a moment
+        let db = sledoview::db::open_db(temp_dir.path()).unwrap();
+        db.open_tree("archive")
+            .unwrap()
+            .insert("user_001", "John Doe (2019)")
+            .unwrap();
+        db.flush().unwrap();
+    }
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["search", "John*", "--all-trees"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("[__sled__default]"))
+        .stdout(predicate::str::contains("[archive]"))
+        .stdout(predicate::str::contains("Found 2 matches"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["list", "user_*", "--trees", "arch*", "--format", "ndjson"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 1);
+    assert!(output.starts_with("{\"tree\":\"archive\""));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["where", "user_001"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found in 2 trees"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["where", "user_002", "--format", "csv"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(String::from_utf8(output).unwrap().lines().count(), 2);
+}
+
+#[test]
+fn test_cli_search_filters() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args([
+        "search", "*JOHN*", "-i", "--key", "email_*", "--format", "ndjson",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 1);
+    assert!(output.contains("\"key\":\"email_john\""));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["list", "--binary", "--format", "ndjson"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 1);
+    assert!(output.contains("data_binary"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["list", "--min-size", "10", "--value", "^\\d{4}-"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found 2 keys"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["list", "--min-size", "big"]);
+    cmd.assert().code(2);
+}
+
+#[test]
+fn test_cli_query() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    for (key, value) in [
+        ("job_1", r#"{"status":"failed","retries":4}"#),
+        ("job_2", r#"{"status":"failed","retries":1}"#),
+        ("job_3", r#"{"status":"done","retries":7}"#),
+    ] {
+        let mut cmd = sledoview();
+        cmd.arg(temp_dir.path()).args(["set", key, value]);
+        cmd.assert().success();
+    }
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["query", r#"$.status == "failed" && $.retries > 3"#]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("job_1"))
+        .stdout(predicate::str::contains("Found 1 matches"));
+
+    // Sessions and scripts keep the double quotes of the query's strings
+    let line = "query $.status == \"failed\" && $.retries > 3 --key \"job_*\"\n";
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).write_stdin(line);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("job_1"))
+        .stdout(predicate::str::contains("job_2").not());
+    let script = temp_dir.path().join("failed.sled");
+    std::fs::write(&script, line).unwrap();
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).arg("--script").arg(&script);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found 1 matches"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args([
+        "query",
+        "$.retries >= 1",
+        "--key",
+        "job_[12]",
+        "--select",
+        "$.status,$.retries",
+        "--format",
+        "csv",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout("key,key_encoding,status,retries\njob_1,utf8,failed,4\njob_2,utf8,failed,1\n");
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["query", "$.status == failed"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("Invalid query"));
+}
+
+#[test]
+fn test_cli_sql() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args([
+        "sql",
+        "SELECT prefix(key, '_') AS kind, count(*) FROM __sled__default \
+         GROUP BY kind ORDER BY 2 DESC, 1 LIMIT 2",
+        "--format",
+        "csv",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout("kind,count(*)\nuser,3\nconfig,2\n");
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args([
+        "sql",
+        "SELECT key, len(value) FROM __sled__default WHERE key LIKE 'email_%'",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("email_jane"))
+        .stdout(predicate::str::contains("Found 2 rows"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["sql", "SELECT key FROM missing"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("Invalid SQL: no tree 'missing'"));
+}
+
+#[test]
+fn test_cli_get_decoded() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    // {"a": 1} as MessagePack
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["set", "packed", "0x81a16101"]);
+    cmd.assert().success();
+
+    let output = sledoview()
+        .arg(temp_dir.path())
+        .args(["get", "packed", "--format", "json"])
+        .output()
+        .unwrap();
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["content_type"], "msgpack");
+
+    let output = sledoview()
+        .arg(temp_dir.path())
+        .args(["get", "packed", "--as", "msgpack", "--format", "json"])
+        .output()
+        .unwrap();
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["decoded"], serde_json::json!({ "a": 1 }));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["get", "packed", "--as", "json"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot decode value as json"));
+}
+
+#[test]
+fn test_cli_profile() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    let profile = temp_dir.path().join("profile.yaml");
+    std::fs::write(
+        &profile,
+        "tree:\n  - name: \"counters\"\n    key: hex\n    value: bincode:u64\n  - name: \"ids\"\n    key: tuple(u32be, u64be)\n",
+    )
+    .unwrap();
+    for tree in ["counters", "ids"] {
+        let mut cmd = sledoview();
+        cmd.arg(temp_dir.path()).args(["tree", "create", tree]);
+        cmd.assert().success();
+    }
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "counters", "set", "0x0001", "0x2a00000000000000"]);
+    cmd.assert().success();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "counters", "--profile"])
+        .arg(&profile)
+        .args(["list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0x0001 = 42"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--profile"])
+        .arg(&profile)
+        .write_stdin("select counters\n");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Profile: keys hex, values bincode:u64",
+    ));
+
+    // Composite keys are typed the way they are shown
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "ids", "--profile"])
+        .arg(&profile)
+        .args(["set", "(3, 1700000000)", "v"]);
+    cmd.assert().success();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "ids", "--profile"])
+        .arg(&profile)
+        .args(["list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(3, 1700000000) = v"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "ids", "get", "0x00000003000000006553f100"]);
+    cmd.assert().success();
+
+    // Typed literals work with or without a profile
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "counters", "set", "u64:7", "0x0100000000000000"]);
+    cmd.assert().success();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "counters", "--profile"])
+        .arg(&profile)
+        .args(["get", "0x0000000000000007"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "decoded as bincode:u64 by profile",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--profile", "/nonexistent/profile.toml", "count"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile"));
+}
+
+#[test]
+fn test_cli_piped_stdin_formats() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).write_stdin(
+        "format csv
+count
+list user_00[12] --format ndjson
+",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "count\n10\n\
+             {\"key\":\"user_001\",\"key_encoding\":\"utf8\",\"size\":8,\"content_type\":\"text\",\"value\":\"John Doe\",\"value_encoding\":\"utf8\"}\n\
+             {\"key\":\"user_002\",\"key_encoding\":\"utf8\",\"size\":10,\"content_type\":\"text\",\"value\":\"Jane Smith\",\"value_encoding\":\"utf8\"}\n",
+        ));
+}
+
+#[test]
+fn test_cli_script() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    let script_dir = tempfile::tempdir().unwrap();
+    let script_path = script_dir.path().join("fix.sled");
+    std::fs::write(
+        &script_path,
+        "# rename a user\nselect archive --create\nset user_001 \"John Doe\"\nunselect\ndelete user_001\n",
+    )
+    .unwrap();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--write", "--script"])
+        .arg(&script_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Successfully deleted key"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "archive", "get", "user_001"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("John Doe"));
+}
+
+#[test]
+fn test_cli_script_failure() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    let script_dir = tempfile::tempdir().unwrap();
+    let script_path = script_dir.path().join("fix.sled");
+    std::fs::write(&script_path, "get missing_key\nset after_error yes\n").unwrap();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--write", "--script"])
+        .arg(&script_path);
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "fix.sled:1: Key not found: missing_key",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "after_error"]);
+    cmd.assert().code(3);
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--write", "--script"])
+        .arg(&script_path)
+        .arg("--continue-on-error");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("1 of 2 commands failed"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "after_error"]);
+    cmd.assert().success();
+}
+
+#[test]
+fn test_cli_piped_stdin() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .write_stdin("count\nget user_001\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Total records: 10"))
+        .stdout(predicate::str::contains("John Doe"))
+        .stdout(predicate::str::contains("Interactive SLED Database Client").not())
+        .stdout(predicate::str::contains("> ").not())
+        .stdout(predicate::str::contains("\u{1b}[").not());
+}
+
+#[test]
+fn test_cli_piped_stdin_transaction() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).arg("--write").write_stdin(
+        "begin\nset user_004 Ann\ndelete user_001\nstatus\ncount\ncommit\ncount\n\
+         begin\ndelete user_002\nrollback\nget user_002\nbegin\nset staged_only v\n",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Only 'get' sees staged writes; listings",
+        ))
+        .stdout(predicate::str::contains("Staged set of key user_004"))
+        .stdout(predicate::str::contains(
+            "Transaction in progress: 2 staged writes",
+        ))
+        .stdout(predicate::str::contains("Total records: 10"))
+        .stdout(predicate::str::contains("Committed 2 writes"))
+        .stdout(predicate::str::contains("Rolled back 1 staged write"))
+        .stdout(predicate::str::contains("Jane Smith"))
+        .stderr(predicate::str::contains(
+            "discarded 1 staged write(s) that were not committed",
+        ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "staged_only"]);
+    cmd.assert().code(3);
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "user_004"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Ann"));
+}
+
+#[test]
+fn test_cli_conditional_set() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["set", "user_001", "Ann", "--if-absent"]);
+    cmd.assert().code(5).stderr(predicate::str::contains(
+        "Conflict on key user_001: expected no value, found 'John Doe'",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["cas", "user_001", "John Doe", "John Smith"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Successfully swapped value of key",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["set", "user_001", "Ann", "--if-equals", "John Doe"]);
+    cmd.assert().code(5).stderr(predicate::str::contains(
+        "expected 'John Doe', found 'John Smith'",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["set", "user_004", "Ann", "--if-absent"]);
+    cmd.assert().success();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "user_001"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("John Smith"));
+}
+
+#[test]
+fn test_cli_bulk_delete() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["delete", "--pattern", "user_*", "--dry-run"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Would delete 3 keys:"))
+        .stdout(predicate::str::contains("1: user_001"));
+
+    // Piped input cannot be asked for confirmation
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .arg("--write")
+        .write_stdin("delete --regex ^user_00[12]$\n");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Would delete 2 keys:"))
+        .stderr(predicate::str::contains(
+            "Deleting 2 keys needs confirmation: add --yes",
+        ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["delete", "--range", "user_002", "user_999", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 2 keys"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["list", "user_*"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("user_001"))
+        .stdout(predicate::str::contains("user_002").not());
+}
+
+#[test]
+fn test_cli_copy_and_move() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    // The target tree is not created for a copy
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["mv", "--pattern", "user_00*", "archive:user_$1"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Tree 'archive' does not exist"));
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["tree", "create", "archive"]);
+    cmd.assert().success();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["mv", "--pattern", "user_00*", "archive:user_$1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Moved 3 keys"))
+        .stdout(predicate::str::contains("user_001 => archive:user_1"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "archive", "cp", "user_1", ":user_2"]);
+    cmd.assert().code(5).stderr(predicate::str::contains(
+        "Conflict on key user_2: expected no value, found 'Jane Smith'",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "archive", "get", "user_1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("John Doe"));
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "user_001"]);
+    cmd.assert().code(3);
+}
+
+#[test]
+fn test_cli_tree_lifecycle() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).write_stdin("select archive\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Tree 'archive' does not exist"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .arg("--write")
+        .write_stdin("select archive --create\nset a 1\nset b 2\n");
+    cmd.assert().success();
+
+    // Without a terminal to ask on, changes need --yes
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["tree", "drop", "archive"]);
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "Dropping tree 'archive' needs confirmation: add --yes",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["tree", "rename", "archive", "old", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed tree archive to old"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["tree", "drop", "old", "-y"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Dropped tree old and 2 keys"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).arg("trees");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("old").not());
+}
+
+#[test]
+fn test_cli_create_test_without_terminal() {
+    let _guard = serial();
+    let work_dir = tempfile::tempdir().unwrap();
+
+    let mut cmd = sledoview();
+    cmd.current_dir(work_dir.path())
+        .arg("create-test")
+        .write_stdin("");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("created successfully"));
+    assert!(work_dir.path().join("test.db").exists());
+}
+
+#[test]
+fn test_cli_piped_stdin_more() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .write_stdin("list user_* --limit 2\nmore\nmore\n");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Showing keys 1-2"))
+        .stdout(predicate::str::contains("type 'more' to see the next page"))
+        .stdout(predicate::str::contains("Showing keys 3-3"))
+        .stdout(predicate::str::contains("3: user_003"))
+        .stderr(predicate::str::contains(
+            "<stdin>:3: No more results to show",
+        ));
+}
+
+#[test]
+fn test_cli_piped_stdin_failure() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .arg("--continue-on-error")
+        .write_stdin("get missing_key\ncount\n");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Total records: 10"))
+        .stderr(predicate::str::contains(
+            "<stdin>:1: Key not found: missing_key",
+        ));
+}
+
+#[test]
+fn test_cli_read_only_refuses_writes() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--read-only", "set", "user_001", "Changed"]);
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "read-only mode, 'set' is not allowed",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .arg("--read-only")
+        .write_stdin("delete user_001\n");
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "read-only mode, 'delete' is not allowed",
+    ));
+
+    // Sessions are read-only unless --write is given
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .write_stdin("set user_001 Changed\n");
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "read-only mode, 'set' is not allowed",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["get", "user_001"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("John Doe"));
+}
+
+/// The path, content hash and modification time of every file under `dir`
+fn file_states(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut states = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        let path = entry.path();
+        if path.is_dir() {
+            states.extend(file_states(&path));
+            continue;
+        }
+        let mut hasher = DefaultHasher::new();
+        std::fs::read(&path).unwrap().hash(&mut hasher);
+        let modified = entry.metadata().unwrap().modified().unwrap();
+        states.push((path, hasher.finish(), modified));
+    }
+    states.sort();
+    states
+}
+
+#[test]
+fn test_cli_snapshot_leaves_files_untouched() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+    let before = file_states(temp_dir.path());
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).args(["--snapshot", "list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("user_001"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--snapshot", "set", "user_001", "x"]);
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "read-only mode, 'set' is not allowed",
+    ));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).arg("--snapshot").write_stdin(
+        "trees
+get user_001
+search *Doe*
+",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("John Doe"));
+
+    assert_eq!(file_states(temp_dir.path()), before);
+}
+
+#[test]
+fn test_cli_inspection_does_not_create_trees() {
+    let _guard = serial();
+    let temp_dir = common::create_test_db();
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path())
+        .args(["--tree", "typo_tree", "count"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("Tree 'typo_tree' does not exist"));
+
+    let mut cmd = sledoview();
+    cmd.arg(temp_dir.path()).arg("trees");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("typo_tree").not());
+}
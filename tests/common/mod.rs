@@ -1,3 +1,6 @@
+// Not every test binary uses every helper.
+#![allow(dead_code)]
+
 use tempfile::TempDir;
 
 /// Creates a temporary SLED database for testing
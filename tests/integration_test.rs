@@ -1,8 +1,10 @@
 mod common;
 
-use assert_cmd::Command;
-use predicates::prelude::*;
+use sledoview::commands::Command;
 use sledoview::db::SledViewer;
+use sledoview::decode::ContentType;
+use sledoview::output::{CommandOutput, OutputFormat};
+use sledoview::render;
 use sledoview::validator::DatabaseValidator;
 
 #[test]
@@ -39,8 +41,8 @@ fn test_sled_viewer_list_keys_all() {
     let viewer = SledViewer::new(temp_dir.path()).unwrap();
     let keys = viewer.list_keys("*", false).unwrap();
     assert_eq!(keys.len(), 10);
-    assert!(keys.contains(&"user_001".to_string()));
-    assert!(keys.contains(&"config_theme".to_string()));
+    assert!(keys.contains(&b"user_001".to_vec()));
+    assert!(keys.contains(&b"config_theme".to_vec()));
 }
 
 #[test]
@@ -49,9 +51,9 @@ fn test_sled_viewer_list_keys_pattern() {
     let viewer = SledViewer::new(temp_dir.path()).unwrap();
     let keys = viewer.list_keys("user_*", false).unwrap();
     assert_eq!(keys.len(), 3);
-    assert!(keys.contains(&"user_001".to_string()));
-    assert!(keys.contains(&"user_002".to_string()));
-    assert!(keys.contains(&"user_003".to_string()));
+    assert!(keys.contains(&b"user_001".to_vec()));
+    assert!(keys.contains(&b"user_002".to_vec()));
+    assert!(keys.contains(&b"user_003".to_vec()));
 }
 
 #[test]
@@ -67,10 +69,10 @@ fn test_sled_viewer_get_key() {
     let temp_dir = common::create_test_db();
     let viewer = SledViewer::new(temp_dir.path()).unwrap();
     let key_info = viewer.get_key("user_001").unwrap();
-    assert_eq!(key_info.key, "user_001");
-    assert_eq!(key_info.value, "John Doe");
+    assert_eq!(key_info.key, b"user_001");
+    assert_eq!(key_info.value, b"John Doe");
     assert_eq!(key_info.size, 8);
-    assert!(key_info.is_utf8);
+    assert_eq!(key_info.content_type, ContentType::Text);
 }
 
 #[test]
@@ -87,8 +89,8 @@ fn test_sled_viewer_search_values_pattern() {
     let viewer = SledViewer::new(temp_dir.path()).unwrap();
     let results = viewer.search_values("*@example.com", false).unwrap();
     assert_eq!(results.len(), 2);
-    assert!(results.iter().any(|r| r.key == "email_john"));
-    assert!(results.iter().any(|r| r.key == "email_jane"));
+    assert!(results.iter().any(|r| r.key == b"email_john"));
+    assert!(results.iter().any(|r| r.key == b"email_jane"));
 }
 
 #[test]
@@ -112,63 +114,45 @@ fn test_binary_data() {
     let temp_dir = common::create_test_db();
     let viewer = SledViewer::new(temp_dir.path()).unwrap();
     let key_info = viewer.get_key("data_binary").unwrap();
-    assert_eq!(key_info.key, "data_binary");
+    assert_eq!(key_info.key, b"data_binary");
     assert_eq!(key_info.size, 5);
-    assert!(!key_info.is_utf8);
-}
-
-#[test]
-fn test_cli_help() {
-    let mut cmd = Command::cargo_bin("sledoview").unwrap();
-    cmd.arg("--help");
-    cmd.assert().success().stdout(predicate::str::contains(
-        "CLI tool for viewing and managing SLED databases",
-    ));
-}
-
-#[test]
-fn test_cli_nonexistent_database() {
-    let mut cmd = Command::cargo_bin("sledoview").unwrap();
-    cmd.arg("/nonexistent/database");
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("Database file not found"));
+    assert_eq!(key_info.content_type, ContentType::Binary);
 }
 
 #[test]
 fn test_sled_viewer_set_key() {
     let temp_dir = common::create_test_db();
-    let viewer = SledViewer::new(temp_dir.path()).unwrap();
-    
+    let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
     // Test setting a new key
     assert!(viewer.set_key("new_test_key", "new_test_value").is_ok());
-    
+
     // Verify the key was set
     let key_info = viewer.get_key("new_test_key").unwrap();
-    assert_eq!(key_info.key, "new_test_key");
-    assert_eq!(key_info.value, "new_test_value");
-    
+    assert_eq!(key_info.key, b"new_test_key");
+    assert_eq!(key_info.value, b"new_test_value");
+
     // Test updating an existing key
     assert!(viewer.set_key("user_001", "Updated John Doe").is_ok());
     let key_info = viewer.get_key("user_001").unwrap();
-    assert_eq!(key_info.value, "Updated John Doe");
+    assert_eq!(key_info.value, b"Updated John Doe");
 }
 
 #[test]
 fn test_sled_viewer_delete_key() {
     let temp_dir = common::create_test_db();
-    let viewer = SledViewer::new(temp_dir.path()).unwrap();
-    
+    let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
     // Verify key exists before deletion
     assert!(viewer.get_key("user_001").is_ok());
-    
+
     // Test deleting an existing key
     let existed = viewer.delete_key("user_001").unwrap();
     assert!(existed);
-    
+
     // Verify the key was deleted
     assert!(viewer.get_key("user_001").is_err());
-    
+
     // Test deleting a non-existent key
     let existed = viewer.delete_key("nonexistent_key").unwrap();
     assert!(!existed);
@@ -177,33 +161,56 @@ fn test_sled_viewer_delete_key() {
 #[test]
 fn test_sled_viewer_set_with_spaces() {
     let temp_dir = common::create_test_db();
-    let viewer = SledViewer::new(temp_dir.path()).unwrap();
-    
+    let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
     // Test setting keys and values with spaces
-    assert!(viewer.set_key("key with spaces", "value with spaces").is_ok());
-    
+    assert!(viewer
+        .set_key("key with spaces", "value with spaces")
+        .is_ok());
+
     let key_info = viewer.get_key("key with spaces").unwrap();
-    assert_eq!(key_info.key, "key with spaces");
-    assert_eq!(key_info.value, "value with spaces");
+    assert_eq!(key_info.key, b"key with spaces");
+    assert_eq!(key_info.value, b"value with spaces");
 }
 
 #[test]
 fn test_sled_viewer_set_with_quotes() {
     let temp_dir = common::create_test_db();
-    let viewer = SledViewer::new(temp_dir.path()).unwrap();
-    
+    let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
     // Test setting values with quotes
     assert!(viewer.set_key("quote_key", "value with \"quotes\"").is_ok());
-    
+
     let key_info = viewer.get_key("quote_key").unwrap();
-    assert_eq!(key_info.value, "value with \"quotes\"");
+    assert_eq!(key_info.value, b"value with \"quotes\"");
 }
 
 #[test]
 fn test_sled_viewer_is_writable() {
     let temp_dir = common::create_test_db();
     let viewer = SledViewer::new(temp_dir.path()).unwrap();
-    
+
     // Test database should be writable
     assert!(viewer.is_writable());
 }
+
+#[test]
+fn test_command_execute_returns_data() {
+    let temp_dir = common::create_test_db();
+    let mut viewer = SledViewer::new(temp_dir.path()).unwrap();
+
+    let output = Command::parse("list user_*")
+        .unwrap()
+        .execute(&mut viewer)
+        .unwrap();
+    let CommandOutput::Keys(keys) = &output else {
+        panic!("unexpected output: {:?}", output);
+    };
+    assert_eq!(keys.len(), 3);
+    assert_eq!(keys[0].key, b"user_001");
+    assert_eq!(keys[0].value, b"John Doe");
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render::render(&output, OutputFormat::Json)).unwrap();
+    assert_eq!(json[2]["value"], "Bob Johnson");
+}